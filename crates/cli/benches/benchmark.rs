@@ -1,3 +1,4 @@
+use clap::Parser;
 use cli::command::dumb_jump::DumbJump;
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 use filter::{MatchedItem, Query, SourceItem};
@@ -112,7 +113,7 @@ fn bench_ctags(c: &mut Criterion) {
             .unwrap()
             .filter_map(|tag| {
                 if let Ok(tag) = serde_json::from_str::<ProjectTag>(&tag) {
-                    Some(tag.format_proj_tag())
+                    Some(tag.format_proj_tag(false))
                 } else {
                     None
                 }
@@ -129,13 +130,14 @@ fn bench_ctags(c: &mut Criterion) {
 }
 
 fn bench_regex_searcher(c: &mut Criterion) {
-    let dumb_jump = DumbJump {
-        word: "unsigned".to_string(),
-        extension: "rs".to_string(),
-        kind: None,
-        cmd_dir: Some("/home/xlc/src/github.com/paritytech/substrate".into()),
-        regex: true,
-    };
+    let dumb_jump = DumbJump::parse_from([
+        "dumb-jump",
+        "unsigned",
+        "rs",
+        "--cmd-dir",
+        "/home/xlc/src/github.com/paritytech/substrate",
+        "--regex",
+    ]);
 
     c.bench_function("regex searcher", |b| {
         b.iter(|| dumb_jump.regex_usages(false, &Default::default()))
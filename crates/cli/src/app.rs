@@ -85,6 +85,18 @@ pub struct Args {
     /// Specify the path of the config file.
     #[clap(long, value_parser)]
     pub config_file: Option<std::path::PathBuf>,
+
+    /// Specify the ripgrep executable, e.g., `rg14` or an absolute path.
+    ///
+    /// By default, `rg` in PATH will be used.
+    #[clap(long)]
+    pub rg_path: Option<String>,
+
+    /// Specify the ctags executable, e.g., `uctags` or an absolute path.
+    ///
+    /// By default, `ctags` in PATH will be used.
+    #[clap(long)]
+    pub ctags_path: Option<String>,
 }
 
 impl Args {
@@ -119,6 +131,15 @@ impl RunCmd {
             .build_global()
             .expect("Failed to configure the rayon global thread pool");
 
+        if let Some(rg_path) = args.rg_path.clone() {
+            maple_core::tools::set_rg_executable(rg_path)
+                .expect("Failed to configure the ripgrep executable");
+        }
+        if let Some(ctags_path) = args.ctags_path.clone() {
+            maple_core::tools::set_ctags_executable(ctags_path)
+                .expect("Failed to configure the ctags executable");
+        }
+
         match self {
             Self::Blines(blines) => blines.run(args),
             Self::Cache(cache) => cache.run(),
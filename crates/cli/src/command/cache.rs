@@ -5,6 +5,7 @@ use maple_core::dirs::Dirs;
 use std::fs::read_dir;
 use std::io::Write;
 use std::path::{PathBuf, MAIN_SEPARATOR};
+use std::time::Duration;
 use utils::remove_dir_contents;
 
 /// List and remove all the cached contents.
@@ -12,6 +13,7 @@ use utils::remove_dir_contents;
 pub enum Cache {
     List(List),
     Purge(Purge),
+    Prune(Prune),
 }
 
 impl Cache {
@@ -19,6 +21,7 @@ impl Cache {
         match self {
             Self::List(list) => list.run(),
             Self::Purge(purge) => purge.run(),
+            Self::Prune(prune) => prune.run(),
         }
     }
 }
@@ -110,6 +113,36 @@ impl Purge {
     }
 }
 
+/// Remove the oldest cached files until the cache directory is within the limits.
+#[derive(Parser, Debug, Clone)]
+pub struct Prune {
+    /// Remove the cached files older than the given days.
+    #[clap(long, default_value_t = 30)]
+    max_age_days: u64,
+
+    /// Remove the oldest cached files until the total size is within the given MiB.
+    #[clap(long, default_value_t = 1024)]
+    max_size_mb: u64,
+}
+
+impl Prune {
+    fn run(&self) -> Result<()> {
+        // The limits saturate instead of wrapping around to a small value, which would
+        // remove most of the cache.
+        let removed = maple_core::datastore::prune_cache(
+            Duration::from_secs(self.max_age_days.saturating_mul(24 * 60 * 60)),
+            self.max_size_mb.saturating_mul(1024 * 1024),
+        )?;
+
+        for path in &removed {
+            println!("Removed {}", path.display());
+        }
+        println!("{} cached files have been removed", removed.len());
+
+        Ok(())
+    }
+}
+
 // The cache directory is not huge and pretty deep, hence the recursive version is acceptable.
 fn dir_size(path: impl Into<PathBuf>) -> std::io::Result<u64> {
     fn dir_size(mut dir: std::fs::ReadDir) -> std::io::Result<u64> {
@@ -1,15 +1,16 @@
 use super::CtagsCommonArgs;
 use crate::app::Args;
-use crate::{send_response_from_cache, SendResponse};
+use crate::{send_response_from_cache, SendResponse, DEFAULT_PREVIEW_COUNT};
 use anyhow::Result;
 use clap::Parser;
 use filter::{FilterContext, SequentialSource};
 use itertools::Itertools;
-use maple_core::process::ShellCommand;
-use maple_core::tools::ctags::{ProjectCtagsCommand, CTAGS_HAS_JSON_FEATURE};
+use maple_core::process::{shell_quote, ShellCommand};
+use maple_core::tools::ctags::ProjectCtagsCommand;
+use maple_core::tools::ctags_executable;
 use matcher::{MatchScope, MatcherBuilder};
 use rayon::prelude::*;
-use std::ops::Deref;
+use std::path::PathBuf;
 use std::sync::Arc;
 use types::ClapItem;
 
@@ -28,6 +29,20 @@ pub struct RecursiveTags {
     #[clap(long)]
     par_run: bool,
 
+    /// Only keep the tags of these kinds, e.g., `f,struct`.
+    ///
+    /// Both the kind letters and the long names are accepted.
+    #[clap(long, value_delimiter = ',')]
+    kinds: Vec<String>,
+
+    /// Read the tags from an existing tags file instead of running ctags.
+    #[clap(long, value_parser)]
+    tags_file: Option<PathBuf>,
+
+    /// Print the ctags command instead of executing it.
+    #[clap(long)]
+    dry_run: bool,
+
     /// Ctags common arguments.
     #[clap(flatten)]
     pub(super) c_args: CtagsCommonArgs,
@@ -35,13 +50,20 @@ pub struct RecursiveTags {
 
 impl RecursiveTags {
     fn project_ctags_cmd(&self) -> Result<ProjectCtagsCommand> {
+        if let Some(ref tags_file) = self.tags_file {
+            let mut ctags_cmd = ProjectCtagsCommand::from_tags_file(tags_file.clone());
+            ctags_cmd.set_kinds(self.kinds.clone());
+            return Ok(ctags_cmd);
+        }
+
         let dir = self.c_args.dir()?;
         let exclude_args = self.c_args.exclude_args();
 
-        let mut std_cmd = std::process::Command::new(ProjectCtagsCommand::TAGS_CMD[0]);
+        let tags_cmd = ProjectCtagsCommand::tags_cmd();
+        let mut std_cmd = std::process::Command::new(ctags_executable());
         std_cmd
             .current_dir(&dir)
-            .args(&ProjectCtagsCommand::TAGS_CMD[1..])
+            .args(&tags_cmd[1..])
             .args(exclude_args);
         if let Some(ref languages) = self.c_args.languages {
             std_cmd.arg(format!("--languages={languages}"));
@@ -49,11 +71,14 @@ impl RecursiveTags {
 
         let shell_cmd = std::iter::once(std_cmd.get_program())
             .chain(std_cmd.get_args())
-            .map(|s| s.to_string_lossy())
+            .map(|s| shell_quote(&s.to_string_lossy()))
             .join(" ");
         let shell_cmd = ShellCommand::new(shell_cmd, dir);
 
-        Ok(ProjectCtagsCommand::new(std_cmd, shell_cmd))
+        let mut ctags_cmd = ProjectCtagsCommand::new(std_cmd, shell_cmd);
+        ctags_cmd.set_kinds(self.kinds.clone());
+
+        Ok(ctags_cmd)
     }
 
     pub fn run(
@@ -65,23 +90,28 @@ impl RecursiveTags {
             ..
         }: Args,
     ) -> Result<()> {
-        if !CTAGS_HAS_JSON_FEATURE.deref() {
-            return Err(anyhow::anyhow!(
-                "ctags executable is not compiled with +json feature, please recompile it."
-            ));
-        }
-
         let mut ctags_cmd = self.project_ctags_cmd()?;
 
+        if self.dry_run {
+            println!("{}", ctags_cmd.command_string());
+            return Ok(());
+        }
+
         if self.forerunner {
             let (total, cache) = if no_cache {
                 ctags_cmd.par_create_cache()?
             } else if let Some((total, cache_path)) = ctags_cmd.ctags_cache() {
                 (total, cache_path)
             } else {
-                ctags_cmd.par_create_cache()?
+                ctags_cmd.create_cache_incremental()?
             };
-            send_response_from_cache(&cache, total, SendResponse::Json, icon);
+            send_response_from_cache(
+                &cache,
+                total,
+                SendResponse::Json,
+                icon,
+                DEFAULT_PREVIEW_COUNT,
+            );
         } else {
             let filter_context = FilterContext::new(
                 icon,
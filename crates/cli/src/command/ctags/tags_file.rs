@@ -38,6 +38,10 @@ pub struct TagsFile {
     /// Generate the tags file whether the tags file exists or not.
     #[clap(long)]
     force_generate: bool,
+
+    /// Print the command generating the tags file instead of executing it.
+    #[clap(long)]
+    dry_run: bool,
 }
 
 impl TagsFile {
@@ -55,6 +59,11 @@ impl TagsFile {
             &exclude_opt,
         );
 
+        if self.dry_run {
+            println!("{}", tags_generator.command_string());
+            return Ok(());
+        }
+
         let tags_searcher = CtagsSearcher::new(tags_generator);
 
         if let Some(ref query) = self.query {
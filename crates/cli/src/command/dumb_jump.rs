@@ -4,9 +4,13 @@
 
 use anyhow::Result;
 use clap::Parser;
-use maple_core::find_usages::{CtagsSearcher, QueryType, RegexSearcher, UsageMatcher, Usages};
+use maple_core::find_usages::{
+    CtagsSearcher, FileOrder, QueryType, RegexSearcher, RegexUsages, UsageMatcher, Usages,
+};
+use maple_core::paths::{find_project_root, DEFAULT_ROOT_MARKERS};
 use maple_core::tools::ctags::{get_language, TagsGenerator};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 /// Search-based jump.
 #[derive(Parser, Debug, Clone)]
@@ -23,46 +27,127 @@ pub struct DumbJump {
     #[clap(long)]
     pub kind: Option<String>,
 
-    /// Specify the working directory.
+    /// Specify the working directory, defaults to current directory.
     #[clap(long, value_parser)]
     pub cmd_dir: Option<PathBuf>,
 
+    /// Search the project root of the working directory instead, i.e., the nearest
+    /// ancestor containing a root marker like `.git` or `Cargo.toml`.
+    #[clap(long)]
+    pub project_root: bool,
+
     /// Use RegexSearcher instead of CtagsSearcher
     #[clap(long)]
     pub regex: bool,
+
+    /// Number of context lines around each usage, only used by RegexSearcher.
+    #[clap(long, default_value_t = 0)]
+    pub context: usize,
+
+    /// Search the ignored and hidden files as well, only used by RegexSearcher.
+    #[clap(long)]
+    pub search_ignored: bool,
+
+    /// Comma-separated subdirectories of `cmd_dir` to search, only used by RegexSearcher.
+    #[clap(long, value_delimiter = ',')]
+    pub search_paths: Vec<PathBuf>,
+
+    /// Kill the search command after the given milliseconds, only used by RegexSearcher.
+    #[clap(long)]
+    pub timeout_ms: Option<u64>,
+
+    /// Extra argument passed to ripgrep as is, can be specified multiple times,
+    /// only used by RegexSearcher.
+    #[clap(long = "rg-arg", allow_hyphen_values = true)]
+    pub extra_rg_args: Vec<String>,
+
+    /// Keep the matches in comments and docstrings, only used by RegexSearcher.
+    #[clap(long)]
+    pub include_comments: bool,
+
+    /// Stop collecting the matches of each search beyond this number, only used by
+    /// RegexSearcher.
+    #[clap(long)]
+    pub max_matches: Option<usize>,
+
+    /// Sort the files searched by ripgrep, `path` or `modified` (the most recently
+    /// modified first), only used by RegexSearcher.
+    ///
+    /// Sorting makes ripgrep search in a single thread, which is much slower.
+    #[clap(long)]
+    pub sort: Option<FileOrder>,
+
+    /// Exclude the files matching the glob from this search, e.g., `*_test.go`, can be
+    /// specified multiple times, only used by RegexSearcher.
+    #[clap(long = "ignore-glob")]
+    pub ignore_globs: Vec<String>,
+
+    /// Treat the search term as a qualified path like `std::collections::HashMap`, the
+    /// last segment of which is searched, only used by RegexSearcher.
+    #[clap(long)]
+    pub qualified: bool,
+
+    /// Print the search commands instead of executing them.
+    #[clap(long)]
+    pub dry_run: bool,
 }
 
 impl DumbJump {
     pub fn run(self) -> Result<()> {
+        let cmd_dir = self.search_dir()?;
+
         let Self {
             word,
             extension,
-            cmd_dir,
+            context,
+            search_ignored,
+            search_paths,
+            timeout_ms,
+            extra_rg_args,
+            include_comments,
+            max_matches,
+            sort,
+            ignore_globs,
+            qualified,
+            dry_run,
             ..
         } = self;
 
         if self.regex {
-            let regex_searcher = RegexSearcher {
-                word,
-                extension,
-                dir: cmd_dir,
-            };
-            let usages = regex_searcher.cli_usages(&Default::default())?;
+            let regex_searcher = RegexSearcher::new(word, extension, Some(cmd_dir))
+                .search_paths(search_paths)
+                .context(context)
+                .search_ignored(search_ignored)
+                .timeout(timeout_ms.map(Duration::from_millis))
+                .extra_rg_args(extra_rg_args)
+                .include_comments(include_comments)
+                .max_matches(max_matches)
+                .file_order(sort)
+                .ignore_globs(ignore_globs)
+                .qualified_word(qualified);
+            if dry_run {
+                for command in regex_searcher.command_strings()? {
+                    println!("{command}");
+                }
+                return Ok(());
+            }
+            let RegexUsages { usages, truncated } =
+                regex_searcher.search_usages(false, &Default::default())?;
             let total = usages.len();
             let (lines, indices): (Vec<_>, Vec<_>) = usages
                 .into_iter()
                 .map(|usage| (usage.line, usage.indices))
                 .unzip();
-            printer::println_json_with_length!(total, lines, indices);
+            printer::println_json_with_length!(total, truncated, lines, indices);
         } else {
-            let cwd = match cmd_dir {
-                Some(cwd) => cwd,
-                None => std::env::current_dir()?,
-            };
-            let mut tags_generator = TagsGenerator::with_dir(cwd);
+            let mut tags_generator = TagsGenerator::with_dir(cmd_dir);
             if let Some(language) = get_language(&extension) {
                 tags_generator.set_languages(language.into());
             }
+            if dry_run {
+                println!("{}", tags_generator.command_string());
+                return Ok(());
+            }
 
             let ctags_searcher = CtagsSearcher::new(tags_generator);
             let usages = ctags_searcher.search_usages(
@@ -78,11 +163,37 @@ impl DumbJump {
     }
 
     pub fn regex_usages(&self, classify: bool, usage_matcher: &UsageMatcher) -> Result<Usages> {
-        let searcher = RegexSearcher {
-            word: self.word.to_string(),
-            extension: self.extension.to_string(),
-            dir: self.cmd_dir.clone(),
+        let dir = self.search_dir()?;
+        let searcher = RegexSearcher::new(self.word.clone(), self.extension.clone(), Some(dir))
+            .search_paths(self.search_paths.clone())
+            .context(self.context)
+            .search_ignored(self.search_ignored)
+            .timeout(self.timeout_ms.map(Duration::from_millis))
+            .extra_rg_args(self.extra_rg_args.clone())
+            .include_comments(self.include_comments)
+            .max_matches(self.max_matches)
+            .file_order(self.sort)
+            .ignore_globs(self.ignore_globs.clone())
+            .qualified_word(self.qualified);
+        Ok(searcher
+            .search_usages(classify, usage_matcher)?
+            .usages
+            .into())
+    }
+
+    /// Returns `cmd_dir` or current directory, or their project root if `project_root`
+    /// is specified and a root is found.
+    fn search_dir(&self) -> std::io::Result<PathBuf> {
+        let dir = match &self.cmd_dir {
+            Some(cmd_dir) => cmd_dir.clone(),
+            None => std::env::current_dir()?,
         };
-        Ok(searcher.search_usages(classify, usage_matcher)?.into())
+        if self.project_root {
+            Ok(find_project_root(&dir, DEFAULT_ROOT_MARKERS)
+                .map(Path::to_path_buf)
+                .unwrap_or(dir))
+        } else {
+            Ok(dir)
+        }
     }
 }
@@ -1,5 +1,5 @@
 use crate::app::Args;
-use crate::{send_response_from_cache, CacheableCommand, SendResponse};
+use crate::{send_response_from_cache, CacheableCommand, SendResponse, DEFAULT_PREVIEW_COUNT};
 use anyhow::Result;
 use clap::Parser;
 use maple_core::tools::rg::{rg_command, rg_shell_command};
@@ -59,6 +59,7 @@ impl RipGrepForerunner {
                             digest.total,
                             SendResponse::Json,
                             icon,
+                            DEFAULT_PREVIEW_COUNT,
                         );
                         return Ok(());
                     }
@@ -3,7 +3,7 @@ use crate::CacheableCommand;
 use anyhow::{Context, Result};
 use clap::Parser;
 use icon::Icon;
-use maple_core::process::{shell_command, ShellCommand};
+use maple_core::process::{shell_command, shell_quote, ShellCommand};
 use maple_core::tools::rg::Match;
 use rayon::prelude::*;
 use std::convert::TryFrom;
@@ -59,12 +59,13 @@ impl LiveGrep {
 
         if let Some(ref g) = self.glob {
             grep_cmd.push_str(" -g ");
-            grep_cmd.push_str(g);
+            grep_cmd.push_str(&shell_quote(g));
         }
 
-        // Force using json format.
-        grep_cmd.push_str(" --json ");
-        grep_cmd.push_str(&self.grep_query);
+        // Force using json format. The query is passed via `-e` so that a query starting
+        // with `-` is not parsed as an option.
+        grep_cmd.push_str(" --json -e ");
+        grep_cmd.push_str(&shell_quote(&self.grep_query));
 
         // currently vim-clap only supports rg.
         // Ref https://github.com/liuchengxu/vim-clap/pull/60
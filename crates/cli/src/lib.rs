@@ -7,7 +7,7 @@ pub use app::{Args, RunCmd};
 use icon::Icon;
 use maple_core::cache::Digest;
 use maple_core::process::ShellCommand;
-use printer::{println_json, println_json_with_length};
+use printer::{println_json, println_json_with_length, println_ndjson};
 use std::path::{Path, PathBuf};
 use std::process::Command as StdCommand;
 use utils::{line_count, read_first_lines};
@@ -17,17 +17,23 @@ use utils::{line_count, read_first_lines};
 pub enum SendResponse {
     Json,
     JsonWithContentLength,
+    /// One compact JSON object per line, without the length prefix.
+    NdJson,
 }
 
-/// Reads the first lines from cache file and send back the cached info.
+/// Number of lines sent by [`send_response_from_cache`] by default.
+pub const DEFAULT_PREVIEW_COUNT: usize = 100;
+
+/// Reads the first `preview_count` lines from cache file and send back the cached info.
 pub fn send_response_from_cache(
     tempfile: &Path,
     total: usize,
     response_ty: SendResponse,
     icon: Icon,
+    preview_count: usize,
 ) {
     let using_cache = true;
-    if let Ok(iter) = read_first_lines(&tempfile, 100) {
+    if let Ok(iter) = read_first_lines(&tempfile, preview_count) {
         let lines: Vec<String> = if let Some(icon_kind) = icon.icon_kind() {
             iter.map(|x| icon_kind.add_icon_to_text(x)).collect()
         } else {
@@ -38,6 +44,7 @@ pub fn send_response_from_cache(
             SendResponse::JsonWithContentLength => {
                 println_json_with_length!(total, tempfile, using_cache, lines)
             }
+            SendResponse::NdJson => println_ndjson!(total, tempfile, using_cache, lines),
         }
     } else {
         match response_ty {
@@ -45,6 +52,7 @@ pub fn send_response_from_cache(
             SendResponse::JsonWithContentLength => {
                 println_json_with_length!(total, tempfile, using_cache)
             }
+            SendResponse::NdJson => println_ndjson!(total, tempfile, using_cache),
         }
     }
 }
@@ -2,7 +2,7 @@ use maple_config::LanguageConfig;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::Path;
-use std::sync::OnceLock;
+use std::sync::{OnceLock, RwLock};
 
 type FileType = String;
 
@@ -143,6 +143,7 @@ struct ConfigurationInner {
     languages: HashMap<LanguageName, LanguageConfig>,
     filetypes: HashMap<FileType, LanguageName>,
     line_comments: HashMap<FileExtension, Vec<String>>,
+    block_comments: HashMap<FileExtension, Vec<(String, String)>>,
     /// Map of LanguageServerName to LanguageServerConfig.
     language_servers: HashMap<String, maple_lsp::LanguageServerConfig>,
 }
@@ -190,6 +191,16 @@ fn config_inner() -> &'static ConfigurationInner {
             })
             .collect::<HashMap<_, _>>();
 
+        let block_comments = language
+            .iter()
+            .filter(|l| !l.block_comments.is_empty())
+            .flat_map(|l| {
+                l.file_extensions
+                    .iter()
+                    .map(|ext| (ext.clone(), l.block_comments.clone()))
+            })
+            .collect::<HashMap<_, _>>();
+
         let mut user_languages = maple_config::config()
             .plugin
             .lsp
@@ -218,6 +229,7 @@ fn config_inner() -> &'static ConfigurationInner {
         ConfigurationInner {
             filetypes,
             line_comments,
+            block_comments,
             languages: final_languages,
             language_servers: language_server,
         }
@@ -231,7 +243,7 @@ pub fn is_comment(line: &str, file_ext: &str) -> bool {
         .any(|comment_syntax| line.trim_start().starts_with(comment_syntax))
 }
 
-pub fn get_line_comments(file_extension: &str) -> &[String] {
+pub fn get_line_comments(file_extension: &str) -> &'static [String] {
     config_inner()
         .line_comments
         .get(file_extension)
@@ -239,6 +251,147 @@ pub fn get_line_comments(file_extension: &str) -> &[String] {
         .unwrap_or_else(|| &[] as &[String])
 }
 
+/// Returns the pairs of opening and closing tokens of block comment.
+pub fn get_block_comments(file_extension: &str) -> &'static [(String, String)] {
+    config_inner()
+        .block_comments
+        .get(file_extension)
+        .map(|v| v.as_slice())
+        .unwrap_or_else(|| &[] as &[(String, String)])
+}
+
+/// Family of the languages sharing the same comment syntax.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommentFamily {
+    /// `//` and `/* */`.
+    CLike,
+    /// `#`.
+    HashLike,
+    /// `;`.
+    LispLike,
+}
+
+impl CommentFamily {
+    /// Returns the pair of line comments and block comments of this family.
+    fn comments(self) -> &'static (Vec<String>, Vec<(String, String)>) {
+        static CELL: OnceLock<[(Vec<String>, Vec<(String, String)>); 3]> = OnceLock::new();
+
+        let families = CELL.get_or_init(|| {
+            [
+                (vec!["//".into()], vec![("/*".into(), "*/".into())]),
+                (vec!["#".into()], Vec::new()),
+                (vec![";".into()], Vec::new()),
+            ]
+        });
+
+        match self {
+            Self::CLike => &families[0],
+            Self::HashLike => &families[1],
+            Self::LispLike => &families[2],
+        }
+    }
+
+    pub fn line_comments(self) -> &'static [String] {
+        &self.comments().0
+    }
+
+    pub fn block_comments(self) -> &'static [(String, String)] {
+        &self.comments().1
+    }
+}
+
+/// Default comment families of the ripgrep languages.
+pub const DEFAULT_COMMENT_FAMILIES: &[(&str, CommentFamily)] = &[
+    ("c", CommentFamily::CLike),
+    ("cpp", CommentFamily::CLike),
+    ("cs", CommentFamily::CLike),
+    ("d", CommentFamily::CLike),
+    ("dart", CommentFamily::CLike),
+    ("go", CommentFamily::CLike),
+    ("groovy", CommentFamily::CLike),
+    ("java", CommentFamily::CLike),
+    ("js", CommentFamily::CLike),
+    ("kotlin", CommentFamily::CLike),
+    ("objc", CommentFamily::CLike),
+    ("objcpp", CommentFamily::CLike),
+    ("php", CommentFamily::CLike),
+    ("protobuf", CommentFamily::CLike),
+    ("rust", CommentFamily::CLike),
+    ("scala", CommentFamily::CLike),
+    ("swift", CommentFamily::CLike),
+    ("ts", CommentFamily::CLike),
+    ("verilog", CommentFamily::CLike),
+    ("zig", CommentFamily::CLike),
+    ("awk", CommentFamily::HashLike),
+    ("cmake", CommentFamily::HashLike),
+    ("coffeescript", CommentFamily::HashLike),
+    ("crystal", CommentFamily::HashLike),
+    ("elixir", CommentFamily::HashLike),
+    ("fish", CommentFamily::HashLike),
+    ("julia", CommentFamily::HashLike),
+    ("make", CommentFamily::HashLike),
+    ("nim", CommentFamily::HashLike),
+    ("perl", CommentFamily::HashLike),
+    ("py", CommentFamily::HashLike),
+    ("r", CommentFamily::HashLike),
+    ("ruby", CommentFamily::HashLike),
+    ("sh", CommentFamily::HashLike),
+    ("tcl", CommentFamily::HashLike),
+    ("toml", CommentFamily::HashLike),
+    ("yaml", CommentFamily::HashLike),
+    ("clojure", CommentFamily::LispLike),
+    ("elisp", CommentFamily::LispLike),
+    ("lisp", CommentFamily::LispLike),
+    ("racket", CommentFamily::LispLike),
+];
+
+fn comment_families() -> &'static RwLock<HashMap<String, CommentFamily>> {
+    static CELL: OnceLock<RwLock<HashMap<String, CommentFamily>>> = OnceLock::new();
+
+    CELL.get_or_init(|| {
+        RwLock::new(
+            DEFAULT_COMMENT_FAMILIES
+                .iter()
+                .map(|(language, family)| (language.to_string(), *family))
+                .collect(),
+        )
+    })
+}
+
+/// Returns the comment family of the ripgrep language `language`.
+pub fn get_comment_family(language: &str) -> Option<CommentFamily> {
+    comment_families()
+        .read()
+        .ok()
+        .and_then(|families| families.get(language).copied())
+}
+
+/// Registers the comment family of the ripgrep language `language`, overriding
+/// the default one in [`DEFAULT_COMMENT_FAMILIES`] if any.
+pub fn register_comment_family(language: impl Into<String>, family: CommentFamily) {
+    if let Ok(mut families) = comment_families().write() {
+        families.insert(language.into(), family);
+    }
+}
+
+/// Returns the line comments and block comments of `file_extension`, falling back
+/// to the comment family of the ripgrep language `language` if the extension is unknown.
+pub fn get_comments_or_family(
+    file_extension: &str,
+    language: &str,
+) -> (&'static [String], &'static [(String, String)]) {
+    if config_inner().line_comments.contains_key(file_extension) {
+        return (
+            get_line_comments(file_extension),
+            get_block_comments(file_extension),
+        );
+    }
+
+    get_comment_family(language)
+        .map(|family| (family.line_comments(), family.block_comments()))
+        .unwrap_or_default()
+}
+
 pub fn get_root_markers(language_name: LanguageId) -> Vec<String> {
     let config = config_inner();
 
@@ -13,8 +13,11 @@ mod sequential_worker;
 use icon::Icon;
 use matcher::{Bonus, MatchScope, Matcher, MatcherBuilder};
 use rayon::prelude::*;
+use std::io::BufRead;
+use std::path::Path;
 use std::sync::Arc;
 use types::{ClapItem, FileNameItem, GrepItem};
+use utils::TopN;
 
 pub use self::parallel_worker::{
     par_dyn_run, par_dyn_run_inprocess, par_dyn_run_list, BestItems, ParallelSource,
@@ -145,3 +148,78 @@ pub fn par_filter_items(
         .into();
     matched_items.par_sort().inner()
 }
+
+/// Number of lines scored at a time in [`par_filter_file`].
+const FILE_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Performs the synchorous filtering on a large file in parallel, only the top `number`
+/// items are kept.
+///
+/// The file is read in chunks and the best items are tracked in a bounded heap, so that
+/// the memory usage does not grow with the file size.
+pub fn par_filter_file(
+    path: impl AsRef<Path>,
+    fuzzy_matcher: &Matcher,
+    number: usize,
+) -> std::io::Result<Vec<MatchedItem>> {
+    let match_scope = fuzzy_matcher.match_scope();
+    let mut lines = std::io::BufReader::new(std::fs::File::open(path)?).split(b'\n');
+    let mut best_items = TopN::new(number);
+
+    loop {
+        let chunk = lines
+            .by_ref()
+            .take(FILE_CHUNK_SIZE)
+            .collect::<std::io::Result<Vec<_>>>()?;
+
+        if chunk.is_empty() {
+            break;
+        }
+
+        let matched_items = chunk
+            .into_par_iter()
+            .filter_map(|line| {
+                let line = String::from_utf8_lossy(&line)
+                    .trim_end_matches('\r')
+                    .to_string();
+                to_clap_item(match_scope, line)
+            })
+            .filter_map(|item| fuzzy_matcher.match_item(item))
+            .collect::<Vec<_>>();
+
+        best_items.extend(matched_items);
+    }
+
+    Ok(best_items.into_sorted_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_par_filter_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("lines.txt");
+        std::fs::write(&path, "abc\nxyz\naxbxc\nabcd\nfoo abc\n").unwrap();
+
+        let matcher = MatcherBuilder::new().build("abc".into());
+        let matched_items = par_filter_file(&path, &matcher, 2).unwrap();
+        let best = par_filter(
+            ["abc", "xyz", "axbxc", "abcd", "foo abc"]
+                .map(|line| Arc::new(SourceItem::from(line.to_string())) as Arc<dyn ClapItem>),
+            &matcher,
+        );
+
+        assert_eq!(matched_items.len(), 2);
+        assert_eq!(matched_items[0].item.raw_text(), "abc");
+        assert_eq!(matched_items[0].indices, vec![0, 1, 2]);
+        assert_eq!(
+            matched_items
+                .iter()
+                .map(|item| item.rank)
+                .collect::<Vec<_>>(),
+            best[..2].iter().map(|item| item.rank).collect::<Vec<_>>()
+        );
+    }
+}
@@ -150,9 +150,7 @@ fn buffer_tags_icon(line: &str) -> IconType {
 
 fn proj_tags_icon(line: &str) -> IconType {
     pattern::extract_proj_tags_kind(line)
-        .and_then(|kind| {
-            bsearch_icon_table(kind, TAGKIND_ICON_TABLE).map(|idx| TAGKIND_ICON_TABLE[idx].1)
-        })
+        .and_then(kind_icon)
         .unwrap_or(DEFAULT_ICON)
 }
 
@@ -167,10 +165,13 @@ pub fn file_icon(line: &str) -> IconType {
     get_icon_or(Path::new(line), DEFAULT_ICON)
 }
 
+/// Returns the icon of tag `kind` if any.
+pub fn kind_icon(kind: &str) -> Option<IconType> {
+    bsearch_icon_table(kind, TAGKIND_ICON_TABLE).map(|idx| TAGKIND_ICON_TABLE[idx].1)
+}
+
 pub fn tags_kind_icon(kind: &str) -> IconType {
-    bsearch_icon_table(kind, TAGKIND_ICON_TABLE)
-        .map(|idx| TAGKIND_ICON_TABLE[idx].1)
-        .unwrap_or(DEFAULT_ICON)
+    kind_icon(kind).unwrap_or(DEFAULT_ICON)
 }
 
 #[cfg(test)]
@@ -304,6 +304,11 @@ pub struct UserLanguageConfig {
     /// Overrides the default value of the corresponding language configuration if it exists.
     pub line_comments: Option<Vec<String>>,
 
+    /// List of pairs of the block comment tokens for this language, e.g., `[["/*", "*/"]]`.
+    ///
+    /// Overrides the default value of the corresponding language configuration if it exists.
+    pub block_comments: Option<Vec<(String, String)>>,
+
     /// List of markers indicating project roots for this language. Examples include ".git" and "Cargo.toml".
     ///
     /// Overrides the default value of the corresponding language configuration if it exists.
@@ -322,6 +327,7 @@ impl From<UserLanguageConfig> for LanguageConfig {
             file_types: c.file_types.unwrap_or_default(),
             file_extensions: c.file_extensions.unwrap_or_default(),
             line_comments: c.line_comments.unwrap_or_default(),
+            block_comments: c.block_comments.unwrap_or_default(),
             root_markers: c.root_markers.unwrap_or_default(),
             language_servers: c.language_servers.unwrap_or_default(),
         }
@@ -345,6 +351,10 @@ pub struct LanguageConfig {
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub line_comments: Vec<String>,
 
+    /// Pairs of the opening and closing tokens of block comment.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub block_comments: Vec<(String, String)>,
+
     /// these indicate project roots <.git, Cargo.toml>
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub root_markers: Vec<String>,
@@ -360,6 +370,7 @@ impl LanguageConfig {
             file_types: maybe_file_types,
             file_extensions: maybe_file_extensions,
             line_comments: maybe_line_comments,
+            block_comments: maybe_block_comments,
             root_markers: maybe_root_markers,
             language_servers: maybe_language_servers,
         } = user_language_config;
@@ -380,6 +391,9 @@ impl LanguageConfig {
         merge_user_config(&mut self.file_types, maybe_file_types);
         merge_user_config(&mut self.file_extensions, maybe_file_extensions);
         merge_user_config(&mut self.line_comments, maybe_line_comments);
+        if let Some(block_comments) = maybe_block_comments {
+            self.block_comments = block_comments;
+        }
         merge_user_config(&mut self.root_markers, maybe_root_markers);
         merge_user_config(&mut self.language_servers, maybe_language_servers);
     }
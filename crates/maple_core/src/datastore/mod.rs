@@ -1,6 +1,8 @@
 //! This module provides the feature of persistent data store via file system.
 //! Typically, the info will be persisted in the json format.
 
+mod mru;
+
 use crate::cache::CacheInfo;
 use crate::recent_files::SortedRecentFiles;
 use crate::stdio_server::InputHistory;
@@ -12,14 +14,29 @@ use serde::Serialize;
 use std::io::BufReader;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
+
+pub use self::mru::{MruEntry, MruStore};
 
 /// Linux: ~/.local/share/vimclap/cache.json
 static CACHE_METADATA_PATH: Lazy<Option<PathBuf>> =
     Lazy::new(|| generate_data_file_path("cache.json").ok());
 
 pub static CACHE_INFO_IN_MEMORY: Lazy<Arc<Mutex<CacheInfo>>> = Lazy::new(|| {
-    let mut maybe_persistent =
-        load_json::<CacheInfo, _>(CACHE_METADATA_PATH.as_deref()).unwrap_or_else(CacheInfo::new);
+    let mut maybe_persistent = CACHE_METADATA_PATH
+        .as_deref()
+        .and_then(|path| match try_load_json::<CacheInfo, _>(path) {
+            Ok(maybe_cache_info) => maybe_cache_info,
+            Err(err) => {
+                tracing::error!(
+                    ?err,
+                    path = %path.display(),
+                    "Corrupted cache info, recreating it"
+                );
+                None
+            }
+        })
+        .unwrap_or_else(CacheInfo::new);
     maybe_persistent.remove_invalid_and_old_entries();
     Arc::new(Mutex::new(maybe_persistent))
 });
@@ -81,33 +98,161 @@ pub fn generate_cache_file_path(filename: impl AsRef<Path>) -> std::io::Result<P
     Ok(cache_dir.join(filename))
 }
 
-fn read_json_as<P: AsRef<Path>, T: DeserializeOwned>(path: P) -> std::io::Result<T> {
-    let file = std::fs::File::open(&path)?;
+/// Removes the oldest files in the project cache directory until none of the remaining
+/// files is older than `max_age` and their total size does not exceed `max_total_bytes`.
+///
+/// The files modified in the last 10 minutes are never removed as they may be still in use.
+/// Returns the paths of removed files.
+pub fn prune_cache(max_age: Duration, max_total_bytes: u64) -> std::io::Result<Vec<PathBuf>> {
+    const GRACE_PERIOD: Duration = Duration::from_secs(10 * 60);
+
+    let cache_dir = Dirs::project().cache_dir();
+    if !cache_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    utils::prune_dir(cache_dir, max_age, max_total_bytes, GRACE_PERIOD)
+}
+
+/// Version of the persisted json data, bump it whenever any persisted structure is
+/// changed in an incompatible way and add the conversion to [`migrate_json`] if possible.
+const JSON_DATA_VERSION: u64 = 1;
+
+/// The persisted json data is wrapped as `{ "version": N, "data": ... }`.
+#[derive(Serialize)]
+struct VersionedJson<'a, T> {
+    version: u64,
+    data: &'a T,
+}
+
+/// Migrates the json `data` of an older `version` to [`JSON_DATA_VERSION`], returns
+/// `None` if the data can not be migrated and has to be discarded.
+///
+/// Version 0 is the unversioned data written before the version was introduced,
+/// its layout is identical to version 1.
+fn migrate_json(version: u64, data: serde_json::Value) -> Option<serde_json::Value> {
+    match version {
+        0 => Some(data),
+        _ => None,
+    }
+}
+
+/// Returns `Ok(None)` if the version of json data is incompatible.
+fn read_json_as<P: AsRef<Path>, T: DeserializeOwned>(path: P) -> std::io::Result<Option<T>> {
+    let path = path.as_ref();
+    let file = std::fs::File::open(path)?;
     let reader = BufReader::new(&file);
-    let deserializd = serde_json::from_reader(reader).map_err(|e| {
+    let into_io_error = |e: serde_json::Error| {
         std::io::Error::new(
             std::io::ErrorKind::Other,
-            format!("Failed to write {} as json: {e:?}", path.as_ref().display()),
+            format!("Failed to read {} as json: {e:?}", path.display()),
         )
-    })?;
+    };
+
+    let (version, data) = match serde_json::from_reader(reader).map_err(into_io_error)? {
+        serde_json::Value::Object(mut map)
+            if map.contains_key("version") && map.contains_key("data") =>
+        {
+            let version = map.get("version").and_then(serde_json::Value::as_u64);
+            (version, map.remove("data").unwrap_or_default())
+        }
+        unversioned => (Some(0), unversioned),
+    };
 
-    Ok(deserializd)
+    let data = match version {
+        Some(JSON_DATA_VERSION) => Some(data),
+        Some(version) if version < JSON_DATA_VERSION => migrate_json(version, data),
+        _ => None,
+    };
+
+    match data {
+        Some(data) => serde_json::from_value(data)
+            .map(Some)
+            .map_err(into_io_error),
+        None => {
+            tracing::debug!(?version, path = %path.display(), "Ignored the incompatible json data");
+            Ok(None)
+        }
+    }
 }
 
+/// Loads the json file, returns `None` if the file does not exist or can not be parsed.
 fn load_json<T: DeserializeOwned, P: AsRef<Path>>(path: Option<P>) -> Option<T> {
-    path.and_then(|json_path| {
-        if json_path.as_ref().exists() {
-            read_json_as::<_, T>(json_path).ok()
-        } else {
-            None
-        }
-    })
+    path.and_then(|json_path| try_load_json(json_path).ok().flatten())
+}
+
+/// Loads the json file, returns `Ok(None)` only if the file does not exist or the version
+/// of its data is incompatible.
+///
+/// Unlike [`load_json`], an error is returned if the file exists but can not be parsed.
+fn try_load_json<T: DeserializeOwned, P: AsRef<Path>>(path: P) -> std::io::Result<Option<T>> {
+    match read_json_as::<_, T>(path) {
+        Ok(maybe_deserialized) => Ok(maybe_deserialized),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
 }
 
 fn write_json<T: Serialize, P: AsRef<Path>>(obj: T, path: Option<P>) -> std::io::Result<()> {
     if let Some(json_path) = path.as_ref() {
-        utils::create_or_overwrite(json_path, serde_json::to_string(&obj)?.as_bytes())?;
+        let versioned = VersionedJson {
+            version: JSON_DATA_VERSION,
+            data: &obj,
+        };
+        utils::write_atomically(json_path, serde_json::to_string(&versioned)?.as_bytes())?;
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_try_load_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data.json");
+
+        assert!(try_load_json::<Vec<usize>, _>(&path).unwrap().is_none());
+        assert!(load_json::<Vec<usize>, _>(Some(&path)).is_none());
+
+        std::fs::write(&path, "[1, 2").unwrap();
+        assert!(try_load_json::<Vec<usize>, _>(&path).is_err());
+        assert!(load_json::<Vec<usize>, _>(Some(&path)).is_none());
+
+        std::fs::write(&path, "[1, 2]").unwrap();
+        assert_eq!(
+            try_load_json::<Vec<usize>, _>(&path).unwrap(),
+            Some(vec![1, 2])
+        );
+    }
+
+    #[test]
+    fn test_versioned_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data.json");
+
+        write_json(vec![1, 2], Some(&path)).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            format!(r#"{{"version":{JSON_DATA_VERSION},"data":[1,2]}}"#)
+        );
+        assert_eq!(load_json::<Vec<usize>, _>(Some(&path)), Some(vec![1, 2]));
+
+        let newer_version = JSON_DATA_VERSION + 1;
+        std::fs::write(
+            &path,
+            format!(r#"{{"version":{newer_version},"data":[1,2]}}"#),
+        )
+        .unwrap();
+        assert!(try_load_json::<Vec<usize>, _>(&path).unwrap().is_none());
+
+        std::fs::write(
+            &path,
+            format!(r#"{{"version":{JSON_DATA_VERSION},"data":"1"}}"#),
+        )
+        .unwrap();
+        assert!(try_load_json::<Vec<usize>, _>(&path).is_err());
+    }
+}
@@ -0,0 +1,140 @@
+//! Provider-agnostic store of the most recently used files.
+
+use super::{generate_data_file_path, load_json, write_json};
+use crate::UtcTime;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MruEntry {
+    /// Canonical file path.
+    pub path: PathBuf,
+    /// Time of last access.
+    pub last_access: UtcTime,
+}
+
+/// A list of the most recently used files persisted in the data directory.
+///
+/// The entries are deduplicated by the canonical path and ordered by the last
+/// access time, the most recent one comes first.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MruStore {
+    /// Path of the json file, `None` if the data directory is unavailable.
+    #[serde(skip)]
+    file: Option<PathBuf>,
+    /// Maximum number of entries.
+    max_size: usize,
+    entries: Vec<MruEntry>,
+}
+
+impl MruStore {
+    /// Default maximum number of entries.
+    pub const DEFAULT_MAX_SIZE: usize = 1000;
+
+    /// Loads the store `name` from the data directory, e.g., `mru_oldfiles.json`
+    /// on Linux is at `~/.local/share/vimclap/mru_oldfiles.json`.
+    pub fn open(name: &str) -> Self {
+        Self::load_from(generate_data_file_path(&format!("mru_{name}.json")).ok())
+    }
+
+    fn load_from(file: Option<PathBuf>) -> Self {
+        let mut store = load_json::<Self, _>(file.as_ref()).unwrap_or_else(|| Self {
+            file: None,
+            max_size: Self::DEFAULT_MAX_SIZE,
+            entries: Vec::new(),
+        });
+        store.file = file;
+        store.prune();
+        store
+    }
+
+    /// Sets the maximum number of entries, the least recently used ones are dropped.
+    pub fn with_max_size(mut self, max_size: usize) -> Self {
+        self.max_size = max_size;
+        self.entries.truncate(max_size);
+        self
+    }
+
+    /// Records the access to `path` and persists the store.
+    pub fn record(&mut self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let path = std::fs::canonicalize(path)?;
+        self.entries.retain(|entry| entry.path != path);
+        self.entries.insert(
+            0,
+            MruEntry {
+                path,
+                last_access: Utc::now(),
+            },
+        );
+        self.entries.truncate(self.max_size);
+        write_json(&*self, self.file.as_ref())
+    }
+
+    /// Returns at most `limit` existing files, the most recent one comes first.
+    pub fn entries(&self, limit: usize) -> Vec<&MruEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.path.exists())
+            .take(limit)
+            .collect()
+    }
+
+    /// Removes the nonexistent files and the duplicate entries, used when loading from the disk.
+    fn prune(&mut self) {
+        self.entries
+            .sort_by_key(|entry| std::cmp::Reverse(entry.last_access));
+        let mut paths = HashSet::new();
+        self.entries = std::mem::take(&mut self.entries)
+            .into_iter()
+            .filter_map(|entry| {
+                let path = std::fs::canonicalize(&entry.path).ok()?;
+                paths.insert(path.clone()).then_some(MruEntry {
+                    path,
+                    last_access: entry.last_access,
+                })
+            })
+            .take(self.max_size)
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mru_store() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let dir = tempdir.path();
+        let file = dir.join("mru_test.json");
+
+        let [a, b, c] = ["a.rs", "b.rs", "c.rs"].map(|name| {
+            let path = dir.join(name);
+            std::fs::write(&path, "").unwrap();
+            std::fs::canonicalize(path).unwrap()
+        });
+
+        let mut store = MruStore::load_from(Some(file.clone())).with_max_size(2);
+        store.record(&a).unwrap();
+        store.record(&b).unwrap();
+        store.record(dir.join(".").join("a.rs")).unwrap();
+        let paths = |store: &MruStore| {
+            store
+                .entries(10)
+                .into_iter()
+                .map(|entry| entry.path.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(paths(&store), vec![a.clone(), b.clone()]);
+
+        store.record(&c).unwrap();
+        assert_eq!(paths(&store), vec![c.clone(), a.clone()]);
+        assert_eq!(store.entries(1).len(), 1);
+
+        std::fs::remove_file(&c).unwrap();
+        let store = MruStore::load_from(Some(file));
+        assert_eq!(paths(&store), vec![a]);
+    }
+}
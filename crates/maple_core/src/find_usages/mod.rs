@@ -1,17 +1,64 @@
 mod search_engine;
 
-use matcher::{ExactMatcher, InverseMatcher};
+use matcher::{ExactMatcher, FuzzyAlgorithm, FuzzyMatcher, InverseMatcher};
 use rayon::prelude::*;
-use std::ops::{Index, IndexMut};
-use types::{CaseMatching, ExactTerm, InverseTerm};
+use regex::Regex;
+use std::ops::{Index, IndexMut, Range};
+use types::{
+    CaseMatching, ExactTerm, ExactTermType, FuzzyTerm, FuzzyText, InverseTerm, MatchScope, Score,
+    SearchTerm, TermType,
+};
 
-pub use self::search_engine::{CtagsSearcher, GtagsSearcher, QueryType, RegexSearcher};
+pub use self::search_engine::{
+    search_definitions, CtagsSearcher, Definition, DefinitionSource, FileOrder, GtagsSearcher,
+    OccurrenceCursor, QueryType, RegexSearcher, RegexUsages, UsageBatch, UsageSource,
+};
+
+/// Outcome of applying [`UsageMatcher`] to a line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MatchOutcome {
+    /// The line passes all the checks.
+    Accepted(Vec<usize>),
+    /// The line is dropped because of this inverse term.
+    RejectedByInverse(InverseTerm),
+    /// The line does not contain this exact term.
+    MissingExact(ExactTerm),
+    /// The line contains none of the terms in this disjunction group.
+    MissingAny(Vec<ExactTerm>),
+    /// The line does not match this fuzzy term.
+    MissingFuzzy(FuzzyTerm),
+    /// The line does not match this regex pattern.
+    MissingRegex(String),
+    /// The line is empty.
+    EmptyLine,
+}
+
+/// Origin of a highlighted char returned by [`UsageMatcher::match_jump_line_with_kinds`].
+///
+/// A char highlighted by multiple origins is tagged by the first one in declaration order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum HighlightKind {
+    /// Matched by the exact terms, including the disjunction groups.
+    Exact,
+    /// Matched by the fuzzy terms.
+    Fuzzy,
+    /// Matched by the regex terms.
+    Regex,
+    /// Given along with the jump line, e.g., the searched word.
+    Upstream,
+}
 
 /// Matcher for filtering out the unqualified usages earlier at the searching stage.
 #[derive(Debug, Clone, Default)]
 pub struct UsageMatcher {
     pub exact_matcher: ExactMatcher,
     pub inverse_matcher: InverseMatcher,
+    /// Disjunction groups, a line has to match at least one term of each group.
+    pub or_groups: Vec<Vec<ExactMatcher>>,
+    /// Fuzzy terms matched against the whole line, each term is a subsequence.
+    pub fuzzy_matcher: FuzzyMatcher,
+    /// Regex patterns which all have to be matched.
+    pub regex_terms: Vec<Regex>,
 }
 
 impl UsageMatcher {
@@ -19,49 +66,400 @@ impl UsageMatcher {
         Self {
             exact_matcher: ExactMatcher::new(exact_terms, CaseMatching::Smart),
             inverse_matcher: InverseMatcher::new(inverse_terms),
+            or_groups: Vec::new(),
+            fuzzy_matcher: FuzzyMatcher::default(),
+            regex_terms: Vec::new(),
         }
     }
 
-    /// Returns the match indices of exact terms if given `line` passes all the checks.
-    fn match_indices(&self, line: &str) -> Option<Vec<usize>> {
-        match (
-            self.exact_matcher.find_matches(line),
-            self.inverse_matcher.match_any(line),
-        ) {
-            (Some((_, indices)), false) => Some(indices),
-            _ => None,
+    /// Parses the raw `query` typed by the user, e.g., `foo 'exact ^prefix suffix$ !bad`.
+    ///
+    /// The whitespaces inside a pair of double quotes are preserved, e.g., `'"let x"`
+    /// is the exact term `let x`. Returns an error if a double quote is unterminated.
+    pub fn parse(query: &str) -> std::io::Result<Self> {
+        let mut exact_terms = Vec::new();
+        let mut inverse_terms = Vec::new();
+        let mut fuzzy_terms = Vec::new();
+
+        for token in tokenize_query(query)? {
+            let SearchTerm { ty, text } = token.as_str().into();
+            if text.is_empty() {
+                continue;
+            }
+            match ty {
+                TermType::Exact(ty) => exact_terms.push(ExactTerm::new(ty, text)),
+                TermType::Inverse(ty) => inverse_terms.push(InverseTerm::new(ty, text)),
+                TermType::Fuzzy(ty) => fuzzy_terms.push(FuzzyTerm::new(ty, text)),
+                // The double quotes have been stripped by the tokenizer.
+                TermType::Word => exact_terms.push(ExactTerm::new(ExactTermType::Exact, text)),
+            }
+        }
+
+        let usage_matcher = Self::new(exact_terms, inverse_terms);
+        if fuzzy_terms.is_empty() {
+            Ok(usage_matcher)
+        } else {
+            Ok(usage_matcher.with_fuzzy_terms(fuzzy_terms))
         }
     }
 
-    /// Returns `true` if the result of The results of applying `self`
-    /// is a superset of applying `other` on the same source.
-    pub fn is_superset(&self, other: &Self) -> bool {
-        self.exact_matcher
+    /// Sets the regex terms, e.g., `foo\d+` in the query `/foo\d+/`.
+    ///
+    /// Returns an error if any of `patterns` is not a valid regex.
+    pub fn with_regex_terms<S: AsRef<str>>(mut self, patterns: &[S]) -> Result<Self, regex::Error> {
+        self.regex_terms = patterns
+            .iter()
+            .map(|pattern| Regex::new(pattern.as_ref()))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(self)
+    }
+
+    /// Sets the fuzzy terms which are matched using the default fuzzy algorithm.
+    pub fn with_fuzzy_terms(mut self, fuzzy_terms: Vec<FuzzyTerm>) -> Self {
+        self.fuzzy_matcher = FuzzyMatcher::new(
+            MatchScope::Full,
+            FuzzyAlgorithm::default(),
+            fuzzy_terms,
+            self.exact_matcher.case_matching,
+        );
+        self
+    }
+
+    /// Sets the case matching of all the terms, which is [`CaseMatching::Smart`] by default
+    /// for the positive terms and [`CaseMatching::Respect`] for the inverse terms.
+    pub fn with_case_matching(mut self, case_matching: CaseMatching) -> Self {
+        self.exact_matcher.case_matching = case_matching;
+        self.inverse_matcher = self.inverse_matcher.with_case_matching(case_matching);
+        self.or_groups
+            .iter_mut()
+            .flatten()
+            .for_each(|alternative| alternative.case_matching = case_matching);
+        self.fuzzy_matcher.case_matching = case_matching;
+        self
+    }
+
+    /// Sets the disjunction groups, e.g., `(foo|bar) baz` is expressed as
+    /// the group `[foo, bar]` plus the exact term `baz`.
+    pub fn with_or_groups(mut self, or_groups: Vec<Vec<ExactTerm>>) -> Self {
+        let case_matching = self.exact_matcher.case_matching;
+        self.or_groups = or_groups
+            .into_iter()
+            .filter(|group| !group.is_empty())
+            .map(|group| {
+                group
+                    .into_iter()
+                    .map(|term| ExactMatcher::new(vec![term], case_matching))
+                    .collect()
+            })
+            .collect();
+        self
+    }
+
+    /// Returns `true` if there is no term, in which case every line is accepted as is.
+    pub fn is_empty(&self) -> bool {
+        self.exact_matcher.exact_terms.is_empty()
+            && self.inverse_matcher.inverse_terms().is_empty()
+            && self.or_groups.is_empty()
+            && self.fuzzy_matcher.is_empty()
+            && self.regex_terms.is_empty()
+    }
+
+    /// Returns the char indices of matched terms if given `line` passes all the checks.
+    fn match_indices(&self, line: &str) -> Option<Vec<usize>> {
+        self.score_and_indices(line).map(|(_, indices)| indices)
+    }
+
+    /// Same as [`Self::match_indices`], but the matched chars are converted to the byte
+    /// ranges of `line`, the consecutive ones are collapsed into one range.
+    pub fn match_byte_ranges(&self, line: &str) -> Option<Vec<Range<usize>>> {
+        let mut indices = self.match_indices(line)?;
+        indices.sort_unstable();
+        indices.dedup();
+        Some(utils::char_indices_to_byte_ranges(line, &indices))
+    }
+
+    /// Returns the match indices or the reason why `line` is rejected.
+    pub fn match_detail(&self, line: &str) -> MatchOutcome {
+        if let Some(indices) = self.match_indices(line) {
+            return MatchOutcome::Accepted(indices);
+        }
+
+        if let Some(inverse_term) = self.inverse_matcher.find_any(line) {
+            return MatchOutcome::RejectedByInverse(inverse_term.clone());
+        }
+
+        let case_matching = self.exact_matcher.case_matching;
+
+        if let Some(exact_term) = self
+            .exact_matcher
             .exact_terms
             .iter()
-            .zip(other.exact_matcher.exact_terms.iter())
-            .all(|(local, other)| local.is_superset(other))
-            && self
+            .find(|term| find_exact_term(term, line, case_matching).is_none())
+        {
+            return MatchOutcome::MissingExact(exact_term.clone());
+        }
+
+        if let Some(group) = self.or_groups.iter().find(|group| {
+            group
+                .iter()
+                .all(|alternative| find_exact_matches(alternative, line).is_none())
+        }) {
+            return MatchOutcome::MissingAny(
+                group
+                    .iter()
+                    .flat_map(|alternative| alternative.exact_terms.clone())
+                    .collect(),
+            );
+        }
+
+        let fuzzy_text = FuzzyText::new(line, 0);
+        if let Some(fuzzy_term) = self.fuzzy_matcher.fuzzy_terms.iter().find(|term| {
+            self.fuzzy_matcher
+                .fuzzy_algo
+                .fuzzy_match(&term.text, &fuzzy_text, self.fuzzy_matcher.case_matching)
+                .is_none()
+        }) {
+            return MatchOutcome::MissingFuzzy(fuzzy_term.clone());
+        }
+
+        if let Some(regex) = self.regex_terms.iter().find(|regex| !regex.is_match(line)) {
+            return MatchOutcome::MissingRegex(regex.as_str().to_string());
+        }
+
+        MatchOutcome::EmptyLine
+    }
+
+    /// Returns the total score and the match indices if given `line` passes all the checks.
+    pub fn score_and_indices(&self, line: &str) -> Option<(Score, Vec<usize>)> {
+        self.score_and_tagged_indices(line)
+            .map(|(score, indices)| (score, indices.into_iter().map(|(index, _)| index).collect()))
+    }
+
+    /// Same as [`Self::score_and_indices`], but each index is tagged by the kind of term.
+    fn score_and_tagged_indices(&self, line: &str) -> Option<(Score, Vec<(usize, HighlightKind)>)> {
+        if self.is_empty() {
+            return Some((Score::default(), Vec::new()));
+        }
+
+        if self.inverse_matcher.match_any(line) {
+            return None;
+        }
+
+        let tagged = |indices: Vec<usize>, kind: HighlightKind| {
+            indices.into_iter().map(move |index| (index, kind))
+        };
+
+        let (mut score, exact_indices) = find_exact_matches(&self.exact_matcher, line)?;
+        let mut indices = tagged(exact_indices, HighlightKind::Exact).collect::<Vec<_>>();
+
+        for group in &self.or_groups {
+            let (group_score, group_indices) = group
+                .iter()
+                .find_map(|alternative| find_exact_matches(alternative, line))?;
+            score += group_score;
+            indices.extend(tagged(group_indices, HighlightKind::Exact));
+        }
+
+        if !self.fuzzy_matcher.is_empty() {
+            let (fuzzy_score, fuzzy_indices) = self
+                .fuzzy_matcher
+                .match_fuzzy_text(&FuzzyText::new(line, 0))?;
+            score += fuzzy_score;
+            indices.extend(tagged(fuzzy_indices, HighlightKind::Fuzzy));
+        }
+
+        for regex in &self.regex_terms {
+            let char_range = to_char_range(line, regex.find(line)?.range());
+            score += char_range.len() as Score;
+            indices.extend(char_range.map(|index| (index, HighlightKind::Regex)));
+        }
+
+        Some((score, indices))
+    }
+
+    /// Returns `true` if the results of applying `self` are a superset of
+    /// the results of applying `other` on the same source.
+    ///
+    /// Each term of `self` has to be implied by some term of `other` regardless of
+    /// the number and order of terms, e.g., `self` can not have an extra inverse term.
+    pub fn is_superset(&self, other: &Self) -> bool {
+        fn exact_terms_implied(local: &[ExactTerm], other: &[ExactTerm]) -> bool {
+            local
+                .iter()
+                .all(|local| other.iter().any(|other| local.is_superset(other)))
+        }
+
+        exact_terms_implied(
+            &self.exact_matcher.exact_terms,
+            &other.exact_matcher.exact_terms,
+        ) && self.inverse_matcher.inverse_terms().iter().all(|local| {
+            other
                 .inverse_matcher
                 .inverse_terms()
                 .iter()
-                .zip(other.inverse_matcher.inverse_terms().iter())
-                .all(|(local, other)| local.is_superset(other))
+                .any(|other| local.is_superset(other))
+        }) && self.or_groups.iter().all(|local| {
+            other.or_groups.iter().any(|other| {
+                // Every alternative of `other` has to be covered by some local alternative.
+                other.iter().all(|other_alternative| {
+                    local.iter().any(|local_alternative| {
+                        exact_terms_implied(
+                            &local_alternative.exact_terms,
+                            &other_alternative.exact_terms,
+                        )
+                    })
+                })
+            })
+        }) && self.fuzzy_matcher.fuzzy_terms.iter().all(|local| {
+            other
+                .fuzzy_matcher
+                .fuzzy_terms
+                .iter()
+                // Comparing with `abc`, `ab` has more results.
+                .any(|other| other.text.starts_with(&local.text))
+        }) && self.regex_terms.iter().all(|local| {
+            other
+                .regex_terms
+                .iter()
+                .any(|other| local.as_str() == other.as_str())
+        })
     }
 
-    pub fn match_jump_line(
+    pub fn match_jump_line(&self, jump_line: (String, Vec<usize>)) -> Option<(String, Vec<usize>)> {
+        self.match_jump_line_with_score(jump_line)
+            .map(|(jump_line, indices, _)| (jump_line, indices))
+    }
+
+    /// Same as [`Self::match_jump_line`], but the highlighted indices are coalesced into
+    /// the ranges of consecutive indices, which take much fewer highlight spans.
+    pub fn match_jump_line_with_ranges(
+        &self,
+        jump_line: (String, Vec<usize>),
+    ) -> Option<(String, Vec<Range<usize>>)> {
+        self.match_jump_line(jump_line)
+            .map(|(jump_line, indices)| (jump_line, utils::coalesce_indices(&indices)))
+    }
+
+    /// Same as [`Self::match_jump_line`], but also returns the score for ranking the results.
+    pub fn match_jump_line_with_score(
         &self,
         (jump_line, mut indices): (String, Vec<usize>),
-    ) -> Option<(String, Vec<usize>)> {
-        if let Some(exact_indices) = self.match_indices(&jump_line) {
+    ) -> Option<(String, Vec<usize>, Score)> {
+        if self.is_empty() {
+            return Some((jump_line, indices, Score::default()));
+        }
+
+        if let Some((score, exact_indices)) = self.score_and_indices(&jump_line) {
             indices.extend(exact_indices);
             indices.sort_unstable();
             indices.dedup();
-            Some((jump_line, indices))
+            Some((jump_line, indices, score))
         } else {
             None
         }
     }
+
+    /// Same as [`Self::match_jump_line`], but each highlighted index is tagged by its
+    /// origin so that the chars matched by the query terms can be colored differently.
+    pub fn match_jump_line_with_kinds(
+        &self,
+        (jump_line, indices): (String, Vec<usize>),
+    ) -> Option<(String, Vec<(usize, HighlightKind)>)> {
+        let mut tagged_indices = indices
+            .into_iter()
+            .map(|index| (index, HighlightKind::Upstream))
+            .collect::<Vec<_>>();
+
+        if !self.is_empty() {
+            let (_, term_indices) = self.score_and_tagged_indices(&jump_line)?;
+            tagged_indices.extend(term_indices);
+        }
+
+        tagged_indices.sort_unstable();
+        tagged_indices.dedup_by_key(|(index, _)| *index);
+        Some((jump_line, tagged_indices))
+    }
+}
+
+/// Same as [`ExactMatcher::find_matches`], but all kinds of exact terms are matched
+/// according to `matcher.case_matching` and the char indices are returned instead of
+/// the byte offsets, like the fuzzy and regex terms.
+///
+/// [`ExactMatcher`] is shared by all the providers and always ignores the case of the
+/// [`ExactTermType::Exact`] terms, the case matching is only configurable for the usages.
+fn find_exact_matches(matcher: &ExactMatcher, line: &str) -> Option<(Score, Vec<usize>)> {
+    if line.is_empty() {
+        return None;
+    }
+
+    let mut score = Score::default();
+    let mut indices = Vec::new();
+
+    for term in &matcher.exact_terms {
+        let byte_range = find_exact_term(term, line, matcher.case_matching)?;
+        score += term.text.len() as Score;
+        indices.extend(to_char_range(line, byte_range));
+    }
+
+    // Same bonus as `ExactMatcher`, the shorter line has a higher score.
+    score += (512 / line.len()) as Score;
+
+    Some((score, indices))
+}
+
+/// Returns the byte range of `line` matched by the exact `term`.
+fn find_exact_term(
+    term: &ExactTerm,
+    line: &str,
+    case_matching: CaseMatching,
+) -> Option<Range<usize>> {
+    let needle = term.text.as_str();
+    let ignore_case = !case_matching.is_case_sensitive(needle);
+
+    match term.ty {
+        ExactTermType::Exact => line.char_indices().find_map(|(start, _)| {
+            prefix_len(&line[start..], needle, ignore_case).map(|len| start..start + len)
+        }),
+        ExactTermType::PrefixExact => {
+            let trimmed = line.trim_start();
+            let start = line.len() - trimmed.len();
+            prefix_len(trimmed, needle, ignore_case).map(|len| start..start + len)
+        }
+        ExactTermType::SuffixExact => {
+            let trimmed = line.trim_end();
+            trimmed
+                .char_indices()
+                .map(|(start, _)| start)
+                .find(|&start| {
+                    prefix_len(&trimmed[start..], needle, ignore_case)
+                        == Some(trimmed.len() - start)
+                })
+                .map(|start| start..trimmed.len())
+        }
+    }
+}
+
+/// Converts the byte range of `line` to the range of char indices.
+fn to_char_range(line: &str, byte_range: Range<usize>) -> Range<usize> {
+    let start = line[..byte_range.start].chars().count();
+    start..start + line[byte_range].chars().count()
+}
+
+/// Returns the byte length of the prefix of `haystack` which is equal to `needle`.
+fn prefix_len(haystack: &str, needle: &str, ignore_case: bool) -> Option<usize> {
+    if !ignore_case {
+        return haystack.starts_with(needle).then_some(needle.len());
+    }
+
+    let mut haystack_chars = haystack.char_indices();
+    for needle_char in needle.chars() {
+        let (_, haystack_char) = haystack_chars.next()?;
+        if !haystack_char.to_lowercase().eq(needle_char.to_lowercase()) {
+            return None;
+        }
+    }
+
+    Some(haystack_chars.next().map_or(haystack.len(), |(end, _)| end))
 }
 
 #[derive(Clone, Debug, Default)]
@@ -72,6 +470,40 @@ pub struct Usage {
     pub indices: Vec<usize>,
 }
 
+/// Splits `query` by whitespaces, except for the whitespaces inside a pair of double quotes.
+///
+/// The double quotes are removed from the tokens.
+fn tokenize_query(query: &str) -> std::io::Result<Vec<String>> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut in_quotes = false;
+
+    for c in query.chars() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            c if c.is_whitespace() && !in_quotes => {
+                if !token.is_empty() {
+                    tokens.push(std::mem::take(&mut token));
+                }
+            }
+            c => token.push(c),
+        }
+    }
+
+    if in_quotes {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("Unterminated double quote in query: {query}"),
+        ));
+    }
+
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
 impl From<AddressableUsage> for Usage {
     fn from(addressable_usage: AddressableUsage) -> Self {
         let AddressableUsage { line, indices, .. } = addressable_usage;
@@ -92,6 +524,8 @@ pub struct AddressableUsage {
     pub indices: Vec<usize>,
     pub path: String,
     pub line_number: usize,
+    /// Byte column of the matched identifier, 0 if unknown.
+    pub column: usize,
 }
 
 impl PartialEq for AddressableUsage {
@@ -174,3 +608,304 @@ impl Usages {
         self.0.append(&mut other_usages);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use types::{FuzzyTermType, InverseTermType};
+
+    fn exact(text: &str) -> ExactTerm {
+        ExactTerm::new(ExactTermType::Exact, text.into())
+    }
+
+    #[test]
+    fn test_or_groups() {
+        let matcher = UsageMatcher::new(vec![exact("baz")], Vec::new())
+            .with_or_groups(vec![vec![exact("foo"), exact("bar")]]);
+
+        assert_eq!(
+            matcher.match_indices("foo baz"),
+            Some(vec![4, 5, 6, 0, 1, 2])
+        );
+        assert_eq!(
+            matcher.match_indices("bar baz"),
+            Some(vec![4, 5, 6, 0, 1, 2])
+        );
+        assert_eq!(matcher.match_indices("foo bar"), None);
+        assert_eq!(matcher.match_indices("qux baz"), None);
+
+        let narrower = UsageMatcher::new(vec![exact("baz")], Vec::new())
+            .with_or_groups(vec![vec![exact("foo")]]);
+        assert!(matcher.is_superset(&narrower));
+        assert!(!narrower.is_superset(&matcher));
+    }
+
+    #[test]
+    fn test_empty_matcher_accepts_line_unchanged() {
+        let matcher = UsageMatcher::default();
+        assert!(matcher.is_empty());
+        assert!(!UsageMatcher::new(vec![exact("foo")], Vec::new()).is_empty());
+
+        let jump_line = ("src/lib.rs:1:1:fn foo() {}".to_string(), vec![18, 16, 17]);
+        assert_eq!(matcher.match_jump_line(jump_line.clone()), Some(jump_line));
+        assert_eq!(matcher.match_indices(""), Some(Vec::new()));
+    }
+
+    #[test]
+    fn test_parse() {
+        let matcher = UsageMatcher::parse(r#"foo 'exact !bad .rs$ ^pre '"let x" !^"a b""#).unwrap();
+        assert_eq!(
+            matcher.exact_matcher.exact_terms,
+            vec![
+                exact("exact"),
+                ExactTerm::new(ExactTermType::SuffixExact, ".rs".into()),
+                ExactTerm::new(ExactTermType::PrefixExact, "pre".into()),
+                exact("let x"),
+            ]
+        );
+        assert_eq!(
+            matcher.inverse_matcher.inverse_terms(),
+            &[
+                InverseTerm::new(InverseTermType::InverseExact, "bad".into()),
+                InverseTerm::new(InverseTermType::InversePrefixExact, "a b".into()),
+            ]
+        );
+        assert_eq!(
+            matcher.fuzzy_matcher.fuzzy_terms,
+            vec![FuzzyTerm::new(FuzzyTermType::Fuzzy, "foo".into())]
+        );
+
+        assert!(UsageMatcher::parse(r#"'"let x"#).is_err());
+    }
+
+    #[test]
+    fn test_is_superset_with_reordered_and_sized_terms() {
+        let inverse = |text: &str| InverseTerm::new(InverseTermType::InverseExact, text.into());
+
+        let matcher = UsageMatcher::new(vec![exact("foo"), exact("ba")], Vec::new());
+        let reordered = UsageMatcher::new(vec![exact("bar"), exact("foo")], Vec::new());
+        assert!(matcher.is_superset(&reordered));
+        assert!(!reordered.is_superset(&matcher));
+
+        let more_terms = UsageMatcher::new(vec![exact("bar"), exact("foo"), exact("baz")], vec![]);
+        assert!(matcher.is_superset(&more_terms));
+        assert!(!more_terms.is_superset(&matcher));
+
+        let fewer_terms = UsageMatcher::new(vec![exact("foo")], Vec::new());
+        assert!(fewer_terms.is_superset(&matcher));
+        assert!(!matcher.is_superset(&fewer_terms));
+
+        // An extra inverse term narrows down the results.
+        let with_inverse = UsageMatcher::new(vec![exact("foo"), exact("ba")], vec![inverse("x")]);
+        assert!(matcher.is_superset(&with_inverse));
+        assert!(!with_inverse.is_superset(&matcher));
+
+        let two_inverses = UsageMatcher::new(
+            vec![exact("foo"), exact("ba")],
+            vec![inverse("y"), inverse("x")],
+        );
+        assert!(with_inverse.is_superset(&two_inverses));
+        assert!(!two_inverses.is_superset(&with_inverse));
+    }
+
+    #[test]
+    fn test_fuzzy_terms() {
+        let matcher = UsageMatcher::new(vec![exact("let")], Vec::new())
+            .with_fuzzy_terms(vec![FuzzyTerm::new(FuzzyTermType::Fuzzy, "ucr".into())]);
+
+        let (line, indices) = matcher
+            .match_jump_line((
+                "let usage_matcher = UsageMatcher::default();".into(),
+                vec![],
+            ))
+            .unwrap();
+        assert!(indices.windows(2).all(|w| w[0] < w[1]));
+        assert!(indices.starts_with(&[0, 1, 2]));
+        assert_eq!(indices.len(), 6);
+        assert!(line.contains("usage_matcher"));
+
+        assert!(matcher.match_indices("let x = y;").is_none());
+    }
+
+    #[test]
+    fn test_score_and_indices() {
+        let matcher = UsageMatcher::new(vec![exact("foo")], Vec::new());
+        let (short_score, _) = matcher.score_and_indices("foo").unwrap();
+        let (long_score, _) = matcher.score_and_indices("let foo = bar;").unwrap();
+        assert!(short_score > long_score);
+
+        let matcher = UsageMatcher::new(
+            vec![exact("foo")],
+            vec![InverseTerm::new(
+                InverseTermType::InverseExact,
+                "bar".into(),
+            )],
+        );
+        assert!(matcher.score_and_indices("let foo = bar;").is_none());
+    }
+
+    #[test]
+    fn test_match_jump_line_with_kinds() {
+        let matcher = UsageMatcher::parse("'let bar").unwrap();
+        // `foo` is the searched word.
+        let jump_line = ("let foo = bar;".to_string(), vec![4, 5, 6]);
+
+        let (_, tagged_indices) = matcher
+            .match_jump_line_with_kinds(jump_line.clone())
+            .unwrap();
+        let kind_of = |index: usize| {
+            tagged_indices
+                .iter()
+                .find_map(|(i, kind)| (*i == index).then_some(*kind))
+        };
+        assert_eq!(kind_of(0), Some(HighlightKind::Exact));
+        assert_eq!(kind_of(4), Some(HighlightKind::Upstream));
+        assert_eq!(kind_of(10), Some(HighlightKind::Fuzzy));
+        assert_eq!(kind_of(3), None);
+
+        let (_, indices) = matcher.match_jump_line(jump_line).unwrap();
+        assert_eq!(
+            indices,
+            tagged_indices
+                .iter()
+                .map(|(index, _)| *index)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_non_ascii_indices_are_char_indices() {
+        let matcher = UsageMatcher::parse("'foo").unwrap();
+        assert_eq!(matcher.match_indices("let é = foo;"), Some(vec![8, 9, 10]));
+
+        let matcher = UsageMatcher::parse("^é é$").unwrap();
+        assert_eq!(matcher.match_indices("  é = é"), Some(vec![2, 6]));
+
+        let matcher = UsageMatcher::parse("'ü")
+            .unwrap()
+            .with_regex_terms(&["b.r"])
+            .unwrap();
+        let (_, tagged_indices) = matcher
+            .match_jump_line_with_kinds(("ü = bär;".to_string(), vec![]))
+            .unwrap();
+        assert_eq!(
+            tagged_indices,
+            vec![
+                (0, HighlightKind::Exact),
+                (4, HighlightKind::Regex),
+                (5, HighlightKind::Regex),
+                (6, HighlightKind::Regex),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_match_jump_line_with_ranges() {
+        let matcher = UsageMatcher::parse("'let 'foo").unwrap();
+        let jump_line = ("let foo = bar;".to_string(), vec![4, 5, 6]);
+        let (_, ranges) = matcher.match_jump_line_with_ranges(jump_line).unwrap();
+        assert_eq!(ranges, vec![0..3, 4..7]);
+    }
+
+    #[test]
+    fn test_case_matching() {
+        let matcher = UsageMatcher::new(vec![exact("Foo")], Vec::new())
+            .with_case_matching(CaseMatching::Respect);
+        assert!(matcher.match_indices("let Foo = 1;").is_some());
+        assert!(matcher.match_indices("let foo = 1;").is_none());
+
+        // Smart case, the uppercase term is matched case-sensitively.
+        let matcher = UsageMatcher::new(vec![exact("Foo")], Vec::new());
+        assert_eq!(matcher.match_indices("foo = Foo;"), Some(vec![6, 7, 8]));
+        assert!(matcher.match_indices("let foo = 1;").is_none());
+
+        let prefix = ExactTerm::new(ExactTermType::PrefixExact, "LET".into());
+        let matcher =
+            UsageMatcher::new(vec![prefix], Vec::new()).with_case_matching(CaseMatching::Ignore);
+        assert_eq!(matcher.match_indices("  let foo"), Some(vec![2, 3, 4]));
+
+        let inverse = || {
+            vec![InverseTerm::new(
+                InverseTermType::InverseExact,
+                "test".into(),
+            )]
+        };
+        let matcher = UsageMatcher::new(Vec::new(), inverse());
+        assert!(matcher.match_indices("fn Test()").is_some());
+        let matcher =
+            UsageMatcher::new(Vec::new(), inverse()).with_case_matching(CaseMatching::Ignore);
+        assert!(matcher.match_indices("fn Test()").is_none());
+    }
+
+    #[test]
+    fn test_match_detail() {
+        let test = InverseTerm::new(InverseTermType::InverseExact, "test".into());
+        let matcher = UsageMatcher::new(vec![exact("foo")], vec![test.clone()]);
+
+        assert_eq!(
+            matcher.match_detail("foo"),
+            MatchOutcome::Accepted(vec![0, 1, 2])
+        );
+        assert_eq!(
+            matcher.match_detail("foo_test"),
+            MatchOutcome::RejectedByInverse(test)
+        );
+        assert_eq!(
+            matcher.match_detail("bar"),
+            MatchOutcome::MissingExact(exact("foo"))
+        );
+        assert_eq!(
+            matcher.match_detail(""),
+            MatchOutcome::MissingExact(exact("foo"))
+        );
+    }
+
+    #[test]
+    fn test_regex_terms() {
+        let matcher = UsageMatcher::new(vec![exact("let")], Vec::new())
+            .with_regex_terms(&[r"foo\d+"])
+            .unwrap();
+
+        assert_eq!(
+            matcher.match_jump_line(("let foo12 = 1;".into(), vec![])),
+            Some(("let foo12 = 1;".into(), vec![0, 1, 2, 4, 5, 6, 7, 8]))
+        );
+        // Byte offsets are converted to char indices.
+        assert_eq!(
+            matcher.match_indices("let é = foo1;"),
+            Some(vec![0, 1, 2, 8, 9, 10, 11])
+        );
+        assert_eq!(
+            matcher.match_indices("é; let foo1;"),
+            Some(vec![3, 4, 5, 7, 8, 9, 10])
+        );
+        assert!(matcher.match_indices("let foo = 1;").is_none());
+        assert_eq!(
+            matcher.match_detail("let foo = 1;"),
+            MatchOutcome::MissingRegex(r"foo\d+".into())
+        );
+
+        assert!(UsageMatcher::default().with_regex_terms(&["foo("]).is_err());
+    }
+
+    #[test]
+    fn test_match_byte_ranges() {
+        let matcher = UsageMatcher::new(vec![exact("foo"), exact("é")], Vec::new());
+
+        assert_eq!(
+            matcher.match_byte_ranges("let é = foo;"),
+            Some(vec![4..6, 9..12])
+        );
+        assert_eq!(matcher.match_byte_ranges("foo_é"), Some(vec![0..3, 4..6]));
+        assert!(matcher.match_byte_ranges("let foo = 1;").is_none());
+
+        let matcher = UsageMatcher::new(vec![exact("名前")], Vec::new())
+            .with_regex_terms(&["fo+"])
+            .unwrap();
+        let line = "let 名前 = foo;";
+        let ranges = matcher.match_byte_ranges(line).unwrap();
+        assert_eq!(ranges, vec![4..10, 13..16]);
+        assert_eq!(&line[ranges[0].clone()], "名前");
+        assert_eq!(&line[ranges[1].clone()], "foo");
+    }
+}
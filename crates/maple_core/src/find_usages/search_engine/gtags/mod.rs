@@ -1,11 +1,11 @@
 use super::Symbol;
 use crate::find_usages::{AddressableUsage, UsageMatcher};
 use crate::process::subprocess::exec;
-use crate::tools::gtags::GTAGS_DIR;
+use crate::tools::gtags::gtags_db_path;
 use code_tools::analyzer::resolve_reference_kind;
 use rayon::prelude::*;
 use std::io::{Error, ErrorKind, Result};
-use std::path::{PathBuf, MAIN_SEPARATOR};
+use std::path::PathBuf;
 use subprocess::Exec;
 
 #[derive(Clone, Debug)]
@@ -16,16 +16,7 @@ pub struct GtagsSearcher {
 
 impl GtagsSearcher {
     pub fn new(project_root: PathBuf) -> Self {
-        // Directory for GTAGS, GRTAGS, GPATH, e.g.,
-        //
-        // `~/.local/share/vimclap/gtags/project_root`
-        let mut db_path = GTAGS_DIR.to_path_buf();
-        db_path.push(
-            project_root
-                .display()
-                .to_string()
-                .replace(MAIN_SEPARATOR, "_"),
-        );
+        let db_path = gtags_db_path(&project_root);
 
         Self {
             project_root,
@@ -193,6 +184,7 @@ impl GtagsUsage {
             indices: self.indices,
             path: self.path,
             line_number: self.line_number,
+            column: 0,
         }
     }
 }
@@ -7,11 +7,17 @@ mod ctags;
 mod gtags;
 mod regex;
 
-use super::AddressableUsage;
+use super::{AddressableUsage, UsageMatcher};
+use crate::tools::ctags::CTAGS_EXISTS;
+use std::collections::HashSet;
+use std::hash::Hash;
+use std::path::Path;
 
 pub use self::ctags::CtagsSearcher;
 pub use self::gtags::GtagsSearcher;
-pub use self::regex::RegexSearcher;
+pub use self::regex::{
+    FileOrder, OccurrenceCursor, RegexSearcher, RegexUsages, UsageBatch, UsageSource,
+};
 
 /// When spawning the ctags/gtags request, we can specify the searching strategy.
 #[derive(Clone, Debug, PartialEq, Eq, Default)]
@@ -166,6 +172,116 @@ impl Symbol {
             indices,
             path: self.path,
             line_number: self.line_number,
+            column: 0,
         }
     }
 }
+
+/// Search engine where a [`Definition`] is found.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DefinitionSource {
+    Ctags,
+    Regex,
+}
+
+/// Definition found by [`search_definitions`].
+#[derive(Clone, Debug)]
+pub struct Definition {
+    pub usage: AddressableUsage,
+    pub source: DefinitionSource,
+}
+
+/// Searches the definitions of `regex_searcher.word` using both ctags and the regex rules
+/// concurrently, ctags is skipped if neither the tags file nor the ctags executable exists.
+///
+/// The results of ctags go first, the regex results found by ctags too are removed.
+pub fn search_definitions<P: AsRef<Path> + Hash + Sync>(
+    ctags_searcher: Option<&CtagsSearcher<'_, P>>,
+    regex_searcher: &RegexSearcher,
+    usage_matcher: &UsageMatcher,
+) -> Vec<Definition> {
+    let ctags_searcher = ctags_searcher.filter(|searcher| searcher.tags_exists() || *CTAGS_EXISTS);
+
+    let (ctags_results, regex_results) = rayon::join(
+        || {
+            ctags_searcher
+                .map(|searcher| {
+                    searcher.search_usages(
+                        &regex_searcher.word,
+                        usage_matcher,
+                        QueryType::Exact,
+                        false,
+                    )
+                })
+                .transpose()
+        },
+        || {
+            regex_searcher
+                .search_definitions(usage_matcher)
+                .map(|results| results.usages)
+        },
+    );
+
+    if let Err(e) = &ctags_results {
+        tracing::debug!(error = ?e, "Failed to search the definitions using ctags");
+    }
+    if let Err(e) = &regex_results {
+        tracing::debug!(error = ?e, "Failed to search the definitions using regex");
+    }
+
+    merge_definitions(
+        ctags_results.ok().flatten().unwrap_or_default(),
+        regex_results.unwrap_or_default(),
+    )
+}
+
+/// Merges the definitions of ctags and regex, the duplicate ones at the same location
+/// are deduplicated in favor of ctags.
+fn merge_definitions(
+    ctags_results: Vec<AddressableUsage>,
+    regex_results: Vec<AddressableUsage>,
+) -> Vec<Definition> {
+    let normalize = |path: &str| path.trim_start_matches("./").to_string();
+
+    let mut seen = HashSet::new();
+    ctags_results
+        .into_iter()
+        .map(|usage| (usage, DefinitionSource::Ctags))
+        .chain(
+            regex_results
+                .into_iter()
+                .map(|usage| (usage, DefinitionSource::Regex)),
+        )
+        .filter(|(usage, _)| seen.insert((normalize(&usage.path), usage.line_number)))
+        .map(|(usage, source)| Definition { usage, source })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_definitions() {
+        let usage = |path: &str, line_number: usize, line: &str| AddressableUsage {
+            line: line.into(),
+            path: path.into(),
+            line_number,
+            ..Default::default()
+        };
+
+        let definitions = merge_definitions(
+            vec![usage("src/lib.rs", 10, "ctags")],
+            vec![
+                usage("./src/lib.rs", 10, "regex"),
+                usage("src/lib.rs", 20, "regex"),
+            ],
+        );
+
+        assert_eq!(definitions.len(), 2);
+        assert_eq!(definitions[0].usage.line, "ctags");
+        assert_eq!(definitions[0].source, DefinitionSource::Ctags);
+        assert_eq!(definitions[1].usage.line_number, 20);
+        assert_eq!(definitions[1].source, DefinitionSource::Regex);
+    }
+}
@@ -1,22 +1,83 @@
 use super::executable_searcher::LanguageRegexSearcher;
-use crate::tools::rg::{Match, Word};
+use crate::tools::rg::{Data, Match, SubMatch, Word};
+use dirs::Dirs;
 use itertools::Itertools;
 use once_cell::sync::Lazy;
 use rayon::prelude::*;
-use serde::Deserialize;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::fmt::Display;
+use std::path::{Path, PathBuf};
 
 /// A map of the ripgrep language to a set of regular expressions.
 ///
+/// The user rules are merged over the built-in ones.
+///
 /// Ref: https://github.com/jacktasia/dumb-jump/blob/master/dumb-jump.el.
-static RG_PCRE2_REGEX_RULES: Lazy<HashMap<&str, DefinitionRules>> = Lazy::new(|| {
-    serde_json::from_str(include_str!(
-        "../../../../../../scripts/dumb_jump/rg_pcre2_regex.json"
-    ))
-    .expect("Wrong path for rg_pcre2_regex.json")
+static RG_PCRE2_REGEX_RULES: Lazy<HashMap<String, DefinitionRules>> = Lazy::new(|| {
+    let mut rules =
+        serde_json::from_str(RG_PCRE2_REGEX_JSON).expect("Wrong path for rg_pcre2_regex.json");
+
+    if let Some(user_rules_json) = USER_RULES_JSON.as_deref() {
+        match serde_json::from_str(user_rules_json) {
+            Ok(user_rules) => merge_user_rules(&mut rules, user_rules),
+            Err(err) => tracing::error!(
+                ?err,
+                path = %user_rules_file().display(),
+                "Ignored the malformed user definition rules"
+            ),
+        }
+    }
+
+    rules
 });
 
+const RG_PCRE2_REGEX_JSON: &str =
+    include_str!("../../../../../../scripts/dumb_jump/rg_pcre2_regex.json");
+
+/// Returns the path of the user definition rules, which are in the same format as
+/// rg_pcre2_regex.json, e.g., `{ "mydsl": { "function": ["\\bdef\\s+JJJ\\b"] } }`.
+///
+/// Linux: ~/.config/vimclap/definition_rules.json
+fn user_rules_file() -> PathBuf {
+    Dirs::project().config_dir().join("definition_rules.json")
+}
+
+/// Content of the user definition rules, `None` if the file does not exist.
+static USER_RULES_JSON: Lazy<Option<String>> =
+    Lazy::new(|| std::fs::read_to_string(user_rules_file()).ok());
+
+/// Version of the definition rules, changed whenever the rules are updated.
+pub(super) static DEFINITION_RULES_VERSION: Lazy<u64> =
+    Lazy::new(|| utils::calculate_hash(&(RG_PCRE2_REGEX_JSON, USER_RULES_JSON.as_deref())));
+
+/// Merges `user_rules` over `rules`, the regexps of the same language and kind are replaced.
+///
+/// The user regexps without the placeholder `JJJ` are reported and ignored.
+fn merge_user_rules(
+    rules: &mut HashMap<String, DefinitionRules>,
+    user_rules: HashMap<String, DefinitionRules>,
+) {
+    for (lang, DefinitionRules(user_kind_rules)) in user_rules {
+        for (kind, regexp) in user_kind_rules {
+            if let Some(invalid) = regexp.iter().find(|regexp| !regexp.contains("JJJ")) {
+                tracing::error!(
+                    lang = lang.as_str(),
+                    kind = kind.as_ref(),
+                    regexp = invalid.as_str(),
+                    "Ignored the user definition rule without the placeholder JJJ"
+                );
+                continue;
+            }
+            rules
+                .entry(lang.clone())
+                .or_default()
+                .0
+                .insert(kind, regexp);
+        }
+    }
+}
+
 /// Type of match result of ripgrep.
 #[derive(Clone, Debug, Deserialize, PartialEq, Eq, Hash)]
 pub enum MatchKind {
@@ -47,7 +108,7 @@ impl From<DefinitionKind> for MatchKind {
 /// Unit type wrapper of the kind of definition.
 ///
 /// Possibale values: variable, function, type, etc.
-#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct DefinitionKind(String);
 
 impl AsRef<str> for DefinitionKind {
@@ -69,10 +130,18 @@ impl DefinitionRegexp {
 }
 
 /// Definition rules of a language.
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Default, Deserialize)]
 pub struct DefinitionRules(pub HashMap<DefinitionKind, DefinitionRegexp>);
 
 impl DefinitionRules {
+    /// Returns the definition kinds sorted by name, so that the definitions
+    /// searched per kind are always in the same order.
+    pub fn kinds(&self) -> Vec<&DefinitionKind> {
+        let mut kinds = self.0.keys().collect::<Vec<_>>();
+        kinds.sort_unstable();
+        kinds
+    }
+
     fn kind_rules_for(&self, kind: &DefinitionKind) -> Option<impl Iterator<Item = &str>> {
         self.0.get(kind).map(|x| x.iter().map(|x| x.as_str()))
     }
@@ -87,44 +156,317 @@ pub fn get_definition_rules(lang: &str) -> Option<&DefinitionRules> {
     RG_PCRE2_REGEX_RULES.get(lang).or_else(|| {
         EXTENSION_LANGUAGE_MAP
             .get(lang)
-            .and_then(|l| RG_PCRE2_REGEX_RULES.get(l))
+            .and_then(|l| RG_PCRE2_REGEX_RULES.get(*l))
     })
 }
 
+/// Builds the regexp of definition `kind` by substituting `word` for the placeholder `JJJ`.
+///
+/// Only the word is escaped, the regex of rule itself is kept intact.
 pub(super) fn build_full_regexp(lang: &str, kind: &DefinitionKind, word: &Word) -> Option<String> {
+    let escaped_word = word.escaped();
     let regexp = get_definition_rules(lang)?
         .kind_rules_for(kind)?
-        .map(|x| x.replace("\\\\", "\\").replace("JJJ", &word.raw))
+        .map(|x| x.replace("\\\\", "\\").replace("JJJ", &escaped_word))
         .join("|");
     Some(regexp)
 }
 
+/// Languages whose definitions are likely to span multiple lines, e.g., the
+/// function signatures in C or the trait impls in Rust.
+const MULTILINE_LANGUAGES: &[&str] = &[
+    "c",
+    "cpp",
+    "csharp",
+    "go",
+    "java",
+    "kotlin",
+    "rust",
+    "scala",
+    "swift",
+    "typescript",
+];
+
+/// Returns `true` if the definitions of `lang` have to be searched in the multiline mode.
+pub(super) fn is_multiline_language(lang: &str) -> bool {
+    MULTILINE_LANGUAGES.contains(&lang)
+}
+
+/// Languages whose keywords and identifiers are case-insensitive, e.g., `CREATE TABLE Users`
+/// and `create table users` are equivalent in SQL.
+const CASE_INSENSITIVE_LANGUAGES: &[&str] = &["sql"];
+
+/// Returns `true` if the definitions of `lang` have to be searched case insensitively.
+///
+/// Only the definition search is affected, the occurrences are still searched as is.
+pub(super) fn is_case_insensitive_language(lang: &str) -> bool {
+    CASE_INSENSITIVE_LANGUAGES.contains(&lang)
+}
+
+/// Adapts the single line `regexp` to the multiline-dotall mode.
+///
+/// `.` matches the newline in the multiline-dotall mode, it's restricted to not cross
+/// the boundary of a statement or block, otherwise `.+` could match the rest of file.
+pub(super) fn build_multiline_regexp(regexp: &str) -> String {
+    let mut adapted = String::with_capacity(regexp.len());
+    let mut chars = regexp.chars();
+    let mut in_class = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                adapted.push(c);
+                adapted.extend(chars.next());
+            }
+            '[' => {
+                in_class = true;
+                adapted.push(c);
+            }
+            ']' => {
+                in_class = false;
+                adapted.push(c);
+            }
+            '.' if !in_class => adapted.push_str("[^;{}]"),
+            _ => adapted.push(c),
+        }
+    }
+    adapted
+}
+
+/// Narrows a multiline match down to the line where `word` appears.
+pub(super) fn narrow_to_keyword_line(mut matched: Match, word: &Word) -> Match {
+    let text = matched.lines.text().into_owned();
+    if !text.trim_end().contains('\n') {
+        return matched;
+    }
+
+    let mut offset = 0;
+    for (idx, line) in text.split_inclusive('\n').enumerate() {
+        if let Some(mat) = word.re.find(line) {
+            matched.line_number = matched.line_number.map(|n| n + idx as u64);
+            matched.absolute_offset += offset as u64;
+            matched.lines = Data::Text { text: line.into() };
+            matched.submatches = vec![SubMatch {
+                m: Data::Text {
+                    text: mat.as_str().into(),
+                },
+                start: mat.start(),
+                end: mat.end(),
+            }];
+            break;
+        }
+        offset += line.len();
+    }
+
+    matched
+}
+
+/// Comment tokens of a language.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CommentSyntax<'a> {
+    pub line_comments: &'a [String],
+    /// Pairs of the opening and closing tokens.
+    pub block_comments: &'a [(String, String)],
+}
+
+impl CommentSyntax<'static> {
+    pub fn from_extension(file_extension: &str) -> Self {
+        Self {
+            line_comments: code_tools::language::get_line_comments(file_extension),
+            block_comments: code_tools::language::get_block_comments(file_extension),
+        }
+    }
+
+    /// Falls back to the comment syntax of the language family of the ripgrep
+    /// language `lang` if `file_extension` is unknown.
+    pub fn from_extension_or_language(file_extension: &str, lang: &str) -> Self {
+        let (line_comments, block_comments) =
+            code_tools::language::get_comments_or_family(file_extension, lang);
+        Self {
+            line_comments,
+            block_comments,
+        }
+    }
+}
+
+impl<'a> CommentSyntax<'a> {
+    /// Returns `true` if `line` is entirely a comment, e.g., `// foo`, `/* foo */`,
+    /// or the first line of a block comment.
+    pub fn is_comment_line(&self, line: &str) -> bool {
+        let line = line.trim();
+        self.line_comments
+            .iter()
+            .any(|comment| line.starts_with(comment.as_str()))
+            || self.block_comments.iter().any(|(open, close)| {
+                line.starts_with(open.as_str())
+                    && ((line.len() >= open.len() + close.len() && line.ends_with(close.as_str()))
+                        || !line[open.len()..].contains(close.as_str()))
+            })
+    }
+
+    /// Returns the numbers (1-based) of the lines starting inside a block comment.
+    pub fn lines_in_block_comment(&self, content: &str) -> HashSet<u64> {
+        let mut lines = HashSet::new();
+        // Closing token of the unterminated block comment.
+        let mut inside: Option<&'a str> = None;
+
+        for (idx, line) in content.lines().enumerate() {
+            let mut rest = line;
+
+            if let Some(close) = inside {
+                lines.insert(idx as u64 + 1);
+                match rest.find(close) {
+                    Some(pos) => {
+                        rest = &rest[pos + close.len()..];
+                        inside = None;
+                    }
+                    None => continue,
+                }
+            }
+
+            while let Some((pos, open, close)) = self
+                .block_comments
+                .iter()
+                .filter_map(|(open, close)| rest.find(open.as_str()).map(|pos| (pos, open, close)))
+                .min_by_key(|(pos, _, _)| *pos)
+            {
+                // The opening token is part of a line comment.
+                if self
+                    .line_comments
+                    .iter()
+                    .any(|comment| rest[..pos].contains(comment.as_str()))
+                {
+                    break;
+                }
+
+                let after_open = &rest[pos + open.len()..];
+                match after_open.find(close.as_str()) {
+                    Some(close_pos) => rest = &after_open[close_pos + close.len()..],
+                    None => {
+                        inside = Some(close);
+                        break;
+                    }
+                }
+            }
+        }
+
+        lines
+    }
+}
+
 /// Returns true if the ripgrep match is a comment line.
 #[inline]
-pub(super) fn is_comment(mat: &Match, comments: &[String]) -> bool {
-    comments.iter().any(|c| mat.line_starts_with(c))
+pub(super) fn is_comment(mat: &Match, comment_syntax: &CommentSyntax) -> bool {
+    comment_syntax.is_comment_line(&mat.pattern())
+}
+
+/// Removes the matches inside a multiline block comment.
+///
+/// The block comment can not be detected from the match line alone, hence the
+/// files of `matches` are read from `dir` to find out the commented lines.
+pub(super) fn remove_block_comment_matches(
+    matches: &mut Vec<Match>,
+    comment_syntax: &CommentSyntax,
+    dir: Option<&Path>,
+) {
+    if comment_syntax.block_comments.is_empty() {
+        return;
+    }
+
+    let paths = matches
+        .iter()
+        .filter(|m| !m.is_context)
+        .map(|m| m.path().into_owned())
+        .collect::<HashSet<_>>();
+
+    let commented_lines = paths
+        .into_par_iter()
+        .filter_map(|path| {
+            let file = match dir {
+                Some(dir) => dir.join(&path),
+                None => PathBuf::from(&path),
+            };
+            let content = std::fs::read_to_string(file).ok()?;
+            let lines = comment_syntax.lines_in_block_comment(&content);
+            (!lines.is_empty()).then_some((path, lines))
+        })
+        .collect::<HashMap<_, _>>();
+
+    matches.retain(|m| {
+        m.is_context
+            || !commented_lines
+                .get(&*m.path())
+                .map_or(false, |lines| lines.contains(&m.line_number()))
+    });
+}
+
+/// Returns the sort key of `matched` for ranking the matches of same priority,
+/// the files closer to `cwd` and then the shallower paths come first.
+fn relevance_key(matched: &Match, cwd: &Path) -> (code_tools::analyzer::Priority, usize, usize) {
+    let cwd = paths::build_abs_path(cwd, "");
+    let path = paths::build_abs_path(&cwd, matched.path().as_ref());
+    let dir = path.parent().unwrap_or(&path);
+
+    let common = cwd
+        .components()
+        .zip(dir.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let distance = cwd.components().count() + dir.components().count() - 2 * common;
+
+    (
+        matched.pattern_priority(),
+        distance,
+        path.components().count(),
+    )
+}
+
+/// Sorts `matches` by the priority, the ties are broken by the relevance to `cwd`.
+///
+/// The sort is stable, the order of matches with the same key is kept.
+fn sort_by_relevance(matches: &mut [Match], cwd: &Path) {
+    matches.sort_by_cached_key(|matched| relevance_key(matched, cwd));
 }
 
 /// Search results of a specific definition kind.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DefinitionSearchResult {
     pub kind: DefinitionKind,
     pub matches: Vec<Match>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Definitions {
     pub defs: Vec<DefinitionSearchResult>,
+    /// Whether any definition search is stopped at the `max_matches` limit.
+    #[serde(default)]
+    pub truncated: bool,
 }
 
 impl Definitions {
-    pub fn flatten(&self) -> Vec<Match> {
-        let defs_count = self.defs.iter().map(|def| def.matches.len()).sum();
-        let mut defs = Vec::with_capacity(defs_count);
-        for DefinitionSearchResult { matches, .. } in self.defs.iter() {
-            defs.extend_from_slice(matches);
+    /// Returns `true` if no definition of any kind is found.
+    pub fn is_empty(&self) -> bool {
+        self.defs.iter().all(|def| def.matches.is_empty())
+    }
+
+    /// Returns the number of matches of each definition kind, sorted by the kind.
+    ///
+    /// The kinds without any match are excluded.
+    pub fn counts(&self) -> Vec<(DefinitionKind, usize)> {
+        let mut counts = self
+            .defs
+            .iter()
+            .filter(|def| !def.matches.is_empty())
+            .map(|def| (def.kind.clone(), def.matches.len()))
+            .collect::<Vec<_>>();
+        counts.sort_by(|a, b| a.0.as_ref().cmp(b.0.as_ref()));
+        counts
+    }
+
+    /// Sorts the matches of each kind, see [`Occurrences::sort_by_relevance`].
+    pub fn sort_by_relevance(&mut self, cwd: &Path) {
+        for def in self.defs.iter_mut() {
+            sort_by_relevance(&mut def.matches, cwd);
         }
-        defs
     }
 
     #[allow(unused)]
@@ -137,71 +479,502 @@ impl Definitions {
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct Occurrences(pub Vec<Match>);
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Occurrences {
+    pub matches: Vec<Match>,
+    /// Whether the occurrence search is stopped at the `max_matches` limit.
+    #[serde(default)]
+    pub truncated: bool,
+}
+
+impl From<Vec<Match>> for Occurrences {
+    fn from(matches: Vec<Match>) -> Self {
+        Self {
+            matches,
+            truncated: false,
+        }
+    }
+}
 
 impl Occurrences {
-    pub fn contains(&self, m: &Match) -> bool {
-        self.0.contains(m)
+    /// Returns the `(path, line_number)` of all the occurrences.
+    pub fn positions(&self) -> HashSet<(String, u64)> {
+        self.matches
+            .iter()
+            .map(|m| (m.path().into_owned(), m.line_number()))
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.matches.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.matches.is_empty()
     }
 
     #[allow(unused)]
     pub fn par_iter(&self) -> rayon::slice::Iter<'_, Match> {
-        self.0.par_iter()
+        self.matches.par_iter()
     }
 
     pub fn into_iter(self) -> std::vec::IntoIter<Match> {
-        self.0.into_iter()
+        self.matches.into_iter()
     }
 
-    pub fn retain<F>(&mut self, f: F)
-    where
-        F: FnMut(&Match) -> bool,
-    {
-        self.0.retain(f)
+    pub fn into_inner(self) -> Vec<Match> {
+        self.matches
     }
 
-    pub fn into_inner(self) -> Vec<Match> {
-        self.0
+    /// Sorts the occurrences by the priority of pattern, the ties are broken by preferring
+    /// the files closer to `cwd` and then the shallower paths.
+    ///
+    /// The relative paths are resolved against `cwd`. The sort is stable.
+    pub fn sort_by_relevance(&mut self, cwd: &Path) {
+        sort_by_relevance(&mut self.matches, cwd);
+    }
+
+    /// Removes the occurrences which are already in `definitions`.
+    ///
+    /// The matches are compared by `(path, line_number)` only, since the same line
+    /// may be formatted differently by the definition search, e.g., `--trim`.
+    pub fn remove_definitions(&mut self, definitions: &Definitions) {
+        let def_lines = definitions
+            .defs
+            .iter()
+            .flat_map(|def| def.matches.iter())
+            .map(|def| (def.path().into_owned(), def.line_number()))
+            .collect::<HashSet<_>>();
+        self.matches
+            .retain(|m| !def_lines.contains(&(m.path().into_owned(), m.line_number())));
+    }
+
+    /// Removes the context lines and returns them.
+    pub fn take_context_lines(&mut self) -> Vec<Match> {
+        let (context_lines, occurrences) = std::mem::take(&mut self.matches)
+            .into_iter()
+            .partition(|m| m.is_context);
+        self.matches = occurrences;
+        context_lines
+    }
+}
+
+/// Cursor cycling through the occurrences in the order of `(path, line, column)`,
+/// which wraps around at both ends, e.g., for the `]r`/`[r` reference navigation.
+///
+/// The cursor is not placed on any occurrence until the first move.
+#[derive(Debug, Clone, Default)]
+pub struct OccurrenceCursor {
+    /// Sorted and deduplicated `(path, line_number, column)` of the occurrences.
+    targets: Vec<(String, u64, usize)>,
+    position: Option<usize>,
+}
+
+impl From<Occurrences> for OccurrenceCursor {
+    fn from(occurrences: Occurrences) -> Self {
+        let targets = occurrences
+            .into_iter()
+            .filter(|m| !m.is_context)
+            .map(|m| {
+                let (path, line_number, column) = m.jump_target();
+                (path.into_owned(), line_number, column)
+            })
+            .sorted()
+            .dedup()
+            .collect();
+        Self {
+            targets,
+            position: None,
+        }
+    }
+}
+
+impl OccurrenceCursor {
+    pub fn len(&self) -> usize {
+        self.targets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.targets.is_empty()
+    }
+
+    /// Returns the occurrence the cursor is on.
+    pub fn current(&self) -> Option<(&str, u64, usize)> {
+        self.position.map(|position| {
+            let (path, line_number, column) = &self.targets[position];
+            (path.as_str(), *line_number, *column)
+        })
+    }
+
+    /// Moves to the next occurrence, the first one follows the last one.
+    // Not an `Iterator` as the returned occurrence borrows the cursor and never ends.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Option<(&str, u64, usize)> {
+        if self.targets.is_empty() {
+            return None;
+        }
+        self.position = Some(match self.position {
+            Some(position) => (position + 1) % self.targets.len(),
+            None => 0,
+        });
+        self.current()
+    }
+
+    /// Moves to the previous occurrence, the last one precedes the first one.
+    pub fn prev(&mut self) -> Option<(&str, u64, usize)> {
+        if self.targets.is_empty() {
+            return None;
+        }
+        self.position = Some(match self.position {
+            Some(0) | None => self.targets.len() - 1,
+            Some(position) => position - 1,
+        });
+        self.current()
     }
 }
 
 pub(super) fn find_definitions_and_references(
     lang_regex_searcher: LanguageRegexSearcher,
-    comments: &[String],
+    comments: &CommentSyntax,
 ) -> std::io::Result<HashMap<MatchKind, Vec<Match>>> {
     let (definitions, mut occurrences) = lang_regex_searcher.all(comments);
-
-    let defs = definitions.flatten();
+    occurrences.take_context_lines();
 
     // There are some negative definitions we need to filter them out, e.g., the word
     // is a substring in some identifier but we consider every word is a valid identifier.
-    let positive_defs = defs
-        .iter()
-        .filter(|def| occurrences.contains(def))
-        .collect::<Vec<_>>();
+    //
+    // The definition lines are trimmed, hence the lines are compared by the position.
+    let occurrence_positions = occurrences.positions();
+
+    // References are the occurrences that are not in the definition set.
+    occurrences.remove_definitions(&definitions);
 
     let res: HashMap<MatchKind, Vec<Match>> = definitions
         .into_iter()
         .filter_map(|DefinitionSearchResult { kind, mut matches }| {
-            matches.retain(|ref def| positive_defs.contains(def));
+            matches.retain(|def| {
+                occurrence_positions.contains(&(def.path().into_owned(), def.line_number()))
+            });
             if matches.is_empty() {
                 None
             } else {
                 Some((kind.into(), matches))
             }
         })
-        .chain(std::iter::once((MatchKind::Reference, {
-            occurrences.retain(|r| !defs.contains(r));
-            occurrences.into_inner()
-        })))
+        .chain(std::iter::once((
+            MatchKind::Reference,
+            occurrences.into_inner(),
+        )))
         .collect();
 
     if res.is_empty() {
-        lang_regex_searcher
-            .regexp_search(comments)
-            .map(|results| std::iter::once((MatchKind::Occurrence, results)).collect())
+        lang_regex_searcher.regexp_search(comments).map(|output| {
+            let mut results = output.matches;
+            results.retain(|m| !m.is_context);
+            std::iter::once((MatchKind::Occurrence, results)).collect()
+        })
     } else {
         Ok(res)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_definition_kinds_order() {
+        let rules: DefinitionRules = serde_json::from_str(
+            r#"{ "variable": ["JJJ"], "function": ["JJJ"], "type": ["JJJ"], "module": ["JJJ"] }"#,
+        )
+        .unwrap();
+        let kinds = rules
+            .kinds()
+            .into_iter()
+            .map(AsRef::as_ref)
+            .collect::<Vec<_>>();
+        assert_eq!(kinds, ["function", "module", "type", "variable"]);
+
+        let rust_kinds = get_definition_rules("rust").unwrap().kinds();
+        assert!(rust_kinds.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn test_merge_user_rules() {
+        let mut rules: HashMap<String, DefinitionRules> =
+            serde_json::from_str(RG_PCRE2_REGEX_JSON).unwrap();
+        let builtin_variable_rules = rules["rust"].0[&DefinitionKind("variable".into())].clone();
+
+        let user_rules = serde_json::from_str(
+            r#"{
+              "rust": { "function": ["\\bmyfn\\s+JJJ\\b"] },
+              "mydsl": { "function": ["\\brule\\s+JJJ\\b"], "type": ["\\bschema\\b"] }
+            }"#,
+        )
+        .unwrap();
+        merge_user_rules(&mut rules, user_rules);
+
+        let function = DefinitionKind("function".into());
+        assert_eq!(
+            rules["rust"].0[&function].0,
+            vec!["\\bmyfn\\s+JJJ\\b".to_string()]
+        );
+        assert_eq!(
+            rules["rust"].0[&DefinitionKind("variable".into())].0,
+            builtin_variable_rules.0
+        );
+        assert!(rules["mydsl"].0.contains_key(&function));
+        // No placeholder.
+        assert!(!rules["mydsl"]
+            .0
+            .contains_key(&DefinitionKind("type".into())));
+
+        assert!(
+            serde_json::from_str::<HashMap<String, DefinitionRules>>(r#"{"rust": []}"#).is_err()
+        );
+    }
+
+    #[test]
+    fn test_c_block_comments() {
+        let line_comments = ["//".to_string()];
+        let block_comments = [("/*".to_string(), "*/".to_string())];
+        let comment_syntax = CommentSyntax {
+            line_comments: &line_comments,
+            block_comments: &block_comments,
+        };
+
+        assert!(comment_syntax.is_comment_line("  /* foo(); */"));
+        assert!(comment_syntax.is_comment_line("/* foo();"));
+        assert!(!comment_syntax.is_comment_line("/* unused */ foo();"));
+        assert!(!comment_syntax.is_comment_line("foo(); /* call */"));
+
+        let content = r#"int main() {
+    /* foo(); */ bar();
+    /*
+     * foo();
+     foo();
+     */
+    foo(); // baz /*
+    foo();
+}
+"#;
+        let mut lines = comment_syntax
+            .lines_in_block_comment(content)
+            .into_iter()
+            .collect::<Vec<_>>();
+        lines.sort();
+        assert_eq!(lines, vec![4, 5, 6]);
+    }
+
+    #[test]
+    fn test_python_block_comments() {
+        let line_comments = ["#".to_string()];
+        let block_comments = [
+            (r#"""""#.to_string(), r#"""""#.to_string()),
+            ("'''".to_string(), "'''".to_string()),
+        ];
+        let comment_syntax = CommentSyntax {
+            line_comments: &line_comments,
+            block_comments: &block_comments,
+        };
+
+        assert!(comment_syntax.is_comment_line(r#"    """foo()""""#));
+        assert!(comment_syntax.is_comment_line(r#"    """"#));
+        assert!(!comment_syntax.is_comment_line("    foo()"));
+
+        let content = r#"def bar():
+    """
+    Calls foo().
+    """
+    # '''
+    foo()
+    '''foo()'''
+    foo()
+"#;
+        let mut lines = comment_syntax
+            .lines_in_block_comment(content)
+            .into_iter()
+            .collect::<Vec<_>>();
+        lines.sort();
+        assert_eq!(lines, vec![3, 4]);
+    }
+
+    #[test]
+    fn test_comment_family_fallback() {
+        let comments = CommentSyntax::from_extension_or_language("unknown_ext", "rust");
+        assert!(comments.is_comment_line("// fn foo() {}"));
+        assert!(comments.is_comment_line("/* fn foo() {} */"));
+
+        let comments = CommentSyntax::from_extension_or_language("unknown_ext", "lisp");
+        assert!(comments.is_comment_line("; (defun foo ())"));
+        assert!(!comments.is_comment_line("(defun foo ())"));
+
+        code_tools::language::register_comment_family(
+            "unknown_lang",
+            code_tools::language::CommentFamily::HashLike,
+        );
+        let comments = CommentSyntax::from_extension_or_language("unknown_ext", "unknown_lang");
+        assert!(comments.is_comment_line("# def foo():"));
+    }
+
+    #[test]
+    fn test_build_multiline_regexp() {
+        assert_eq!(
+            build_multiline_regexp(r"\bfn\s+.+\s*\(.\.[^.]"),
+            r"\bfn\s+[^;{}]+\s*\([^;{}]\.[^.]"
+        );
+    }
+
+    #[test]
+    fn test_build_full_regexp_escapes_word() {
+        let word = |raw: &str| {
+            Word::new(
+                raw.into(),
+                regex::Regex::new(&format!(r"\b{}\b", regex::escape(raw))).unwrap(),
+            )
+        };
+
+        let function = DefinitionKind("function".into());
+
+        let regexp = build_full_regexp("rust", &function, &word("a.b")).unwrap();
+        let re = regex::Regex::new(&regexp).unwrap();
+        assert!(re.is_match("fn a.b("));
+        assert!(!re.is_match("fn axb("));
+
+        let regexp = build_full_regexp("rust", &function, &word("foo$bar")).unwrap();
+        let re = regex::Regex::new(&regexp).unwrap();
+        assert!(re.is_match("macro_rules! foo$bar"));
+        assert!(!re.is_match("macro_rules! foo"));
+    }
+
+    #[test]
+    fn test_narrow_to_keyword_line() {
+        let word = Word::new("Foo".into(), regex::Regex::new(r"\bFoo\b").unwrap());
+        let matched = rg_match(
+            "src/lib.rs",
+            10,
+            "impl<T> Display\n    for Foo<T>\n{\n",
+            100,
+        );
+
+        let narrowed = narrow_to_keyword_line(matched, &word);
+        assert_eq!(narrowed.line_number(), 11);
+        assert_eq!(narrowed.absolute_offset, 116);
+        assert_eq!(narrowed.pattern(), "    for Foo<T>\n");
+        assert_eq!(narrowed.column(), 8);
+    }
+
+    fn rg_match(path: &str, line_number: u64, line: &str, absolute_offset: u64) -> Match {
+        let json = serde_json::json!({
+            "type": "match",
+            "data": {
+                "path": { "text": path },
+                "lines": { "text": line },
+                "line_number": line_number,
+                "absolute_offset": absolute_offset,
+                "submatches": []
+            }
+        });
+        Match::try_from(json.to_string().as_str()).unwrap()
+    }
+
+    #[test]
+    fn test_occurrence_cursor() {
+        let mut cursor = OccurrenceCursor::from(Occurrences::from(vec![
+            rg_match("src/main.rs", 10, "    foo();\n", 80),
+            rg_match("src/lib.rs", 20, "    foo();\n", 300),
+            rg_match("src/lib.rs", 10, "fn foo() {\n", 120),
+            rg_match("src/lib.rs", 20, "    foo();\n", 300),
+        ]));
+        assert_eq!(cursor.len(), 3);
+        assert_eq!(cursor.current(), None);
+
+        assert_eq!(cursor.next(), Some(("src/lib.rs", 10, 0)));
+        assert_eq!(cursor.next(), Some(("src/lib.rs", 20, 0)));
+        assert_eq!(cursor.next(), Some(("src/main.rs", 10, 0)));
+        assert_eq!(cursor.next(), Some(("src/lib.rs", 10, 0)));
+        assert_eq!(cursor.prev(), Some(("src/main.rs", 10, 0)));
+        assert_eq!(cursor.current(), Some(("src/main.rs", 10, 0)));
+
+        let mut cursor = OccurrenceCursor::from(Occurrences::default());
+        assert_eq!(cursor.next(), None);
+        assert_eq!(cursor.prev(), None);
+    }
+
+    #[test]
+    fn test_remove_definitions() {
+        let definitions = Definitions {
+            defs: vec![DefinitionSearchResult {
+                kind: DefinitionKind("function".into()),
+                matches: vec![rg_match("src/lib.rs", 10, "fn foo() {\n", 120)],
+            }],
+            truncated: false,
+        };
+        let mut occurrences = Occurrences::from(vec![
+            rg_match("src/lib.rs", 10, "    fn foo() {\n", 116),
+            rg_match("src/lib.rs", 20, "    foo();\n", 300),
+            rg_match("src/main.rs", 10, "    foo();\n", 80),
+        ]);
+
+        occurrences.remove_definitions(&definitions);
+
+        let remaining = occurrences
+            .into_iter()
+            .map(|m| (m.path().into_owned(), m.line_number()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            remaining,
+            vec![("src/lib.rs".into(), 20), ("src/main.rs".into(), 10)]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_sort_by_relevance() {
+        let mut occurrences = Occurrences::from(vec![
+            rg_match("/other/lib.rs", 1, "foo();\n", 0),
+            rg_match("../tests/a/b.rs", 1, "foo();\n", 0),
+            rg_match("sub/x.rs", 1, "foo();\n", 0),
+            rg_match("lib.rs", 2, "foo();\n", 0),
+            rg_match("./lib.rs", 1, "foo();\n", 0),
+        ]);
+
+        occurrences.sort_by_relevance(Path::new("/home/user/proj/src"));
+
+        let paths = occurrences
+            .into_iter()
+            .map(|m| (m.path().into_owned(), m.line_number()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            vec![
+                ("lib.rs".into(), 2),
+                ("./lib.rs".into(), 1),
+                ("sub/x.rs".into(), 1),
+                ("../tests/a/b.rs".into(), 1),
+                ("/other/lib.rs".into(), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_definition_counts() {
+        let def = |kind: &str, n: usize| DefinitionSearchResult {
+            kind: DefinitionKind(kind.into()),
+            matches: (0..n)
+                .map(|i| rg_match("src/lib.rs", i as u64 + 1, "foo\n", i as u64 * 4))
+                .collect(),
+        };
+        let definitions = Definitions {
+            defs: vec![def("struct", 1), def("function", 3), def("variable", 0)],
+            truncated: false,
+        };
+        assert_eq!(
+            definitions.counts(),
+            vec![
+                (DefinitionKind("function".into()), 3),
+                (DefinitionKind("struct".into()), 1)
+            ]
+        );
+    }
+}
@@ -1,82 +1,533 @@
 use super::definition::{
-    build_full_regexp, get_definition_rules, is_comment, DefinitionKind, DefinitionSearchResult,
-    Definitions, Occurrences,
+    build_full_regexp, build_multiline_regexp, get_definition_rules, is_case_insensitive_language,
+    is_comment, is_multiline_language, narrow_to_keyword_line, remove_block_comment_matches,
+    CommentSyntax, DefinitionKind, DefinitionSearchResult, Definitions, Occurrences,
 };
-use crate::tools::rg::{Match, Word, RG_EXISTS};
+use super::results_cache::SearchCache;
+use super::{FileOrder, RegexSearcher};
+use crate::process::{command_string, for_each_stdout_line};
+use crate::tools::rg::{get_language_globs, Data, Match, Message, SubMatch, Word, RG_EXISTS};
+use crate::tools::rg_executable;
+use itertools::Itertools;
+use once_cell::sync::Lazy;
 use rayon::prelude::*;
-use std::convert::TryFrom;
 use std::io::{Error, ErrorKind, Result};
 use std::path::PathBuf;
-use std::process::Command;
+use std::process::{Command, Stdio};
+use std::time::Duration;
 
-/// Searches a directory for pattern matches using ripgrep.
+/// Executable used for searching the regex patterns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SearchBackend {
+    /// `rg --json`.
+    Ripgrep,
+    /// `ag --vimgrep`, used only when ripgrep is unavailable.
+    Ag,
+}
+
+static AG_EXISTS: Lazy<bool> = Lazy::new(|| {
+    Command::new("ag")
+        .arg("--version")
+        .stdout(Stdio::null())
+        .status()
+        .map(|exit_status| exit_status.success())
+        .unwrap_or(false)
+});
+
+/// The backend is detected only once, ripgrep is always preferred.
+static SEARCH_BACKEND: Lazy<Option<SearchBackend>> = Lazy::new(|| {
+    if *RG_EXISTS {
+        Some(SearchBackend::Ripgrep)
+    } else if *AG_EXISTS {
+        Some(SearchBackend::Ag)
+    } else {
+        None
+    }
+});
+
+/// Files to search.
+#[derive(Debug, Clone, Copy)]
+enum FileFilter<'a> {
+    /// Language type defined by ripgrep.
+    Language(&'a str),
+    /// File extension.
+    Extension(&'a str),
+}
+
+/// Backend-agnostic description of a search.
+#[derive(Debug, Clone, Copy)]
+struct SearchQuery<'a> {
+    pattern: &'a str,
+    word_regexp: bool,
+    /// Whether `pattern` requires the PCRE2 syntax.
+    pcre2: bool,
+    /// Whether to trim the leading whitespaces of the matched lines.
+    trim: bool,
+    /// Whether a match can span multiple lines, only supported by ripgrep.
+    multiline: bool,
+    /// Whether to match `pattern` case insensitively.
+    ignore_case: bool,
+    file_filter: FileFilter<'a>,
+    /// Globs of the files excluded from the search, e.g., `*_test.go`.
+    ignore_globs: &'a [String],
+    context: usize,
+    /// Whether to search the ignored and hidden files.
+    search_ignored: bool,
+    /// Working directory of the search command.
+    dir: Option<&'a PathBuf>,
+    /// Paths to search, relative to `dir` or absolute. Search `dir` if empty.
+    search_paths: &'a [PathBuf],
+    /// The search command is killed if it runs longer than this.
+    timeout: Option<Duration>,
+    /// Extra arguments appended to the ripgrep command as is, ignored by ag.
+    extra_rg_args: &'a [String],
+    /// Maximum number of matches collected, the context lines are not counted.
+    max_matches: Option<usize>,
+    /// Order of the files searched by ripgrep, ignored by ag.
+    file_order: Option<FileOrder>,
+}
+
+impl<'a> SearchQuery<'a> {
+    fn new(pattern: &'a str, file_filter: FileFilter<'a>, dir: Option<&'a PathBuf>) -> Self {
+        Self {
+            pattern,
+            word_regexp: false,
+            pcre2: false,
+            trim: false,
+            multiline: false,
+            ignore_case: false,
+            file_filter,
+            ignore_globs: &[],
+            context: 0,
+            search_ignored: false,
+            dir,
+            search_paths: &[],
+            timeout: None,
+            extra_rg_args: &[],
+            max_matches: None,
+            file_order: None,
+        }
+    }
+
+    fn rg_command(&self) -> Command {
+        let mut command = Command::new(rg_executable());
+        if self.trim {
+            command.arg("--trim");
+        }
+        command.arg("--json");
+        if self.pcre2 {
+            command.arg("--pcre2");
+        }
+        if self.multiline {
+            command.arg("--multiline").arg("--multiline-dotall");
+        }
+        if self.word_regexp {
+            command.arg("--word-regexp");
+        }
+        if self.ignore_case {
+            command.arg("--ignore-case");
+        }
+        command.arg("--regexp").arg(self.pattern);
+        match self.file_filter {
+            FileFilter::Language(lang) => command.arg("--type").arg(lang),
+            FileFilter::Extension(ext) => command.arg("-g").arg(format!("*.{ext}")),
+        };
+        for glob in self.ignore_globs {
+            command.arg("--glob").arg(format!("!{glob}"));
+        }
+        if self.context > 0 {
+            command.arg("--context").arg(self.context.to_string());
+        }
+        if self.search_ignored {
+            command.arg("--no-ignore").arg("--hidden");
+        }
+        if let Some(file_order) = self.file_order {
+            command.arg(file_order.rg_arg());
+        }
+        command.args(self.extra_rg_args);
+        command.args(self.search_paths);
+        command
+    }
+
+    /// Returns the command of `backend` running in `dir`.
+    fn command(&self, backend: SearchBackend) -> Command {
+        let mut command = match backend {
+            SearchBackend::Ripgrep => self.rg_command(),
+            SearchBackend::Ag => self.ag_command(),
+        };
+        if let Some(dir) = self.dir {
+            command.current_dir(dir);
+        }
+        command
+    }
+
+    /// Returns the command line of the search without executing it, the ripgrep
+    /// command is shown if no search executable is found.
+    fn command_string(&self) -> String {
+        command_string(&self.command(SEARCH_BACKEND.unwrap_or(SearchBackend::Ripgrep)))
+    }
+
+    /// `ag` does not print the context lines in the vimgrep format, hence `context` is ignored.
+    ///
+    /// `search_ignored` maps to `--unrestricted`, which skips all the ignore files like
+    /// `rg --no-ignore --hidden`, but makes ag search the binary files too, the matches
+    /// in which are reported in a format dropped by [`parse_vimgrep_line`].
+    fn ag_command(&self) -> Command {
+        let mut command = Command::new("ag");
+        command.arg("--vimgrep").arg("--nocolor");
+        if self.word_regexp {
+            command.arg("--word-regexp");
+        }
+        if self.ignore_case {
+            command.arg("--ignore-case");
+        }
+        if self.search_ignored {
+            command.arg("--unrestricted");
+        }
+        for glob in self.ignore_globs {
+            command.arg("--ignore").arg(glob);
+        }
+        let file_regex = match self.file_filter {
+            FileFilter::Language(lang) => language_file_regex(lang),
+            FileFilter::Extension(ext) => format!("\\.{}$", regex::escape(ext)),
+        };
+        command
+            .arg("-G")
+            .arg(file_regex)
+            .arg("--")
+            .arg(self.pattern);
+        command.args(self.search_paths);
+        command
+    }
+}
+
+/// Converts the ripgrep file globs of `lang` to a regex used by `ag -G`.
+fn language_file_regex(lang: &str) -> String {
+    get_language_globs(lang)
+        .unwrap_or_default()
+        .iter()
+        .map(|glob| format!("(^|/){}$", regex::escape(glob).replace("\\*", ".*")))
+        .join("|")
+}
+
+/// Parses a line of `ag --vimgrep` output into a ripgrep `Match` of one submatch.
+///
+/// The end of submatch is unknown, so it is extended to the end of line.
+fn parse_vimgrep_line(line: &str, trim: bool) -> Option<Match> {
+    let mut parts = line.splitn(4, ':');
+    let (Some(path), Some(Ok(line_number)), Some(Ok(column)), Some(text)) = (
+        parts.next(),
+        parts.next().map(str::parse::<u64>),
+        parts.next().map(str::parse::<usize>),
+        parts.next(),
+    ) else {
+        return None;
+    };
+
+    let (text, start) = if trim {
+        let trimmed = text.trim_start();
+        (
+            trimmed,
+            column.saturating_sub(1 + text.len() - trimmed.len()),
+        )
+    } else {
+        (text, column.saturating_sub(1))
+    };
+    let matched_text = text.get(start..)?;
+
+    Some(Match {
+        path: Data::Text { text: path.into() },
+        lines: Data::Text {
+            text: format!("{text}\n"),
+        },
+        line_number: Some(line_number),
+        absolute_offset: 0,
+        submatches: vec![SubMatch {
+            m: Data::Text {
+                text: matched_text.to_string(),
+            },
+            start,
+            end: text.len(),
+        }],
+        is_context: false,
+    })
+}
+
+/// Collects the ripgrep `Match` parsed from the output of search command line by line,
+/// until `max_matches` matches are collected.
+struct MatchCollector<F> {
+    backend: SearchBackend,
+    trim: bool,
+    is_not_comment: F,
+    max_matches: Option<usize>,
+    matches: Vec<Match>,
+    /// Number of the collected matches, the context lines are not counted.
+    count: usize,
+    truncated: bool,
+}
+
+impl<F: Fn(&Match) -> bool> MatchCollector<F> {
+    fn new(
+        backend: SearchBackend,
+        trim: bool,
+        is_not_comment: F,
+        max_matches: Option<usize>,
+    ) -> Self {
+        Self {
+            backend,
+            trim,
+            is_not_comment,
+            max_matches,
+            matches: Vec::new(),
+            count: 0,
+            truncated: false,
+        }
+    }
+
+    /// Parses a line of output, returns `false` if there are more matches than
+    /// `max_matches`, i.e., the rest of output has to be skipped.
+    fn push_line(&mut self, line: &[u8]) -> bool {
+        let matched = match self.backend {
+            SearchBackend::Ripgrep => match serde_json::from_slice::<Message>(line) {
+                Ok(Message::Match(matched)) => matched,
+                // Only emitted when `--context` is specified.
+                Ok(Message::Context(context)) => {
+                    self.matches.push(context.into());
+                    return true;
+                }
+                _ => return true,
+            },
+            SearchBackend::Ag => {
+                let Some(matched) = parse_vimgrep_line(&String::from_utf8_lossy(line), self.trim)
+                else {
+                    return true;
+                };
+                // Multiple matches on the same line are merged into one `Match`.
+                match self.matches.last_mut() {
+                    Some(last)
+                        if last.line_number == matched.line_number
+                            && last.path.text() == matched.path.text() =>
+                    {
+                        last.submatches.extend(matched.submatches);
+                        return true;
+                    }
+                    _ => matched,
+                }
+            }
+        };
+
+        if !(self.is_not_comment)(&matched) {
+            return true;
+        }
+        if self.max_matches == Some(self.count) {
+            self.truncated = true;
+            return false;
+        }
+        self.count += 1;
+        self.matches.push(matched);
+        true
+    }
+}
+
+/// Matches found by [`ExecutableSearcher`].
+#[derive(Debug, Default)]
+pub(super) struct SearchOutput {
+    pub matches: Vec<Match>,
+    /// Whether the matches are truncated due to the `max_matches` limit.
+    pub truncated: bool,
+}
+
+/// Source of the matches streamed by [`LanguageRegexSearcher::stream_all`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MatchSource {
+    Definition(DefinitionKind),
+    Occurrence,
+}
+
+/// Searches a directory for pattern matches using ripgrep, or ag as a fallback.
 #[derive(Debug)]
 pub struct ExecutableSearcher {
     command: Command,
+    backend: SearchBackend,
+    trim: bool,
+    dir: Option<PathBuf>,
+    timeout: Option<Duration>,
+    max_matches: Option<usize>,
 }
 
 impl ExecutableSearcher {
-    fn new(command: Command) -> Result<Self> {
-        if !*RG_EXISTS {
-            return Err(Error::new(
+    fn new(query: SearchQuery) -> Result<Self> {
+        let backend = SEARCH_BACKEND.ok_or_else(|| {
+            Error::new(
                 ErrorKind::NotFound,
-                String::from("rg executable not found"),
-            ));
-        }
+                String::from("neither rg nor ag executable is found"),
+            )
+        })?;
 
-        Ok(Self { command })
+        Ok(Self {
+            command: query.command(backend),
+            backend,
+            trim: query.trim,
+            dir: query.dir.cloned(),
+            timeout: query.timeout,
+            max_matches: query.max_matches,
+        })
     }
 
     /// Executes `command` as a child process.
     ///
-    /// Convert the entire output into a stream of ripgrep `Match`.
-    fn search(self, maybe_comments: Option<&[String]>) -> Result<Vec<Match>> {
-        let mut cmd = self.command;
-
-        let cmd_output = cmd.output()?;
-
-        if !cmd_output.status.success() && !cmd_output.stderr.is_empty() {
-            return Err(Error::new(
-                ErrorKind::Other,
-                String::from_utf8_lossy(&cmd_output.stderr),
-            ));
-        }
-
-        Ok(cmd_output
-            .stdout
-            .par_split(|x| x == &b'\n')
-            .filter_map(|s| {
-                Match::try_from(s).ok().filter(|matched| {
-                    maybe_comments
-                        .map(|comments| !is_comment(matched, comments))
-                        .unwrap_or(true)
-                })
-            })
-            .collect())
+    /// Convert the output into a stream of ripgrep `Match` line by line, the context lines
+    /// are included as well if any. The child is killed once `max_matches` is reached.
+    ///
+    /// Returns an error of [`ErrorKind::TimedOut`] if the command is killed due to the timeout.
+    fn search(self, maybe_comments: Option<&CommentSyntax>) -> Result<SearchOutput> {
+        let Self {
+            mut command,
+            backend,
+            trim,
+            dir,
+            timeout,
+            max_matches,
+        } = self;
+
+        let is_not_comment = |matched: &Match| {
+            maybe_comments
+                .map(|comments| !is_comment(matched, comments))
+                .unwrap_or(true)
+        };
+
+        let mut collector = MatchCollector::new(backend, trim, is_not_comment, max_matches);
+        for_each_stdout_line(&mut command, timeout, |line| collector.push_line(line))?;
+
+        let MatchCollector {
+            mut matches,
+            truncated,
+            ..
+        } = collector;
+
+        if let Some(comments) = maybe_comments {
+            remove_block_comment_matches(&mut matches, comments, dir.as_deref());
+        }
+
+        Ok(SearchOutput { matches, truncated })
+    }
+
+    /// Executes `command` like [`Self::search`], but passes the matches to `send` in
+    /// batches as soon as ripgrep finishes searching each file.
+    ///
+    /// The search is stopped once `send` returns `false` or `max_matches` is reached.
+    /// The timeout is not applied. ag can not be streamed, all of its matches are
+    /// sent in one batch.
+    fn stream(
+        self,
+        maybe_comments: Option<&CommentSyntax>,
+        mut send: impl FnMut(Vec<Match>) -> bool,
+    ) -> Result<()> {
+        if self.backend == SearchBackend::Ag {
+            let SearchOutput { matches, truncated } = self.search(maybe_comments)?;
+            if truncated {
+                tracing::warn!(
+                    count = matches.len(),
+                    "Too many matches, the regex search is stopped"
+                );
+            }
+            if !matches.is_empty() {
+                send(matches);
+            }
+            return Ok(());
+        }
+
+        let Self {
+            mut command,
+            dir,
+            max_matches,
+            ..
+        } = self;
+
+        let mut batch = Vec::new();
+        let mut count = 0;
+        for_each_stdout_line(&mut command, None, |line| {
+            let file_end = match serde_json::from_slice::<Message>(line) {
+                Ok(Message::Match(matched)) => {
+                    if maybe_comments.map_or(true, |comments| !is_comment(&matched, comments)) {
+                        batch.push(matched);
+                        count += 1;
+                    }
+                    false
+                }
+                // Only emitted when `--context` is specified.
+                Ok(Message::Context(context)) => {
+                    batch.push(context.into());
+                    false
+                }
+                Ok(Message::End(_)) => true,
+                _ => false,
+            };
+
+            let reached_max = max_matches.map_or(false, |max| count >= max);
+            if file_end || reached_max {
+                if let Some(comments) = maybe_comments {
+                    remove_block_comment_matches(&mut batch, comments, dir.as_deref());
+                }
+                if !batch.is_empty() && !send(std::mem::take(&mut batch)) {
+                    return false;
+                }
+            }
+            if reached_max {
+                tracing::warn!(count, "Too many matches, the regex search is stopped");
+                return false;
+            }
+            true
+        })
     }
 }
 
+/// Searches `search_pattern` in the files with the extension of `searcher`.
 pub(super) fn word_regex_search_with_extension(
     search_pattern: &str,
     ignore_comment: bool,
-    file_extension: &str,
-    maybe_dir: Option<&PathBuf>,
-) -> Result<Vec<Match>> {
-    let mut command = Command::new("rg");
-    command
-        .arg("--json")
-        .arg("--word-regexp")
-        .arg(search_pattern)
-        .arg("-g")
-        .arg(format!("*.{file_extension}"));
-    if let Some(ref dir) = maybe_dir {
-        command.current_dir(dir);
-    }
-    ExecutableSearcher::new(command)?.search(if ignore_comment {
-        Some(code_tools::language::get_line_comments(file_extension))
-    } else {
-        None
-    })
+    searcher: &RegexSearcher,
+) -> Result<SearchOutput> {
+    let query = word_regex_query(search_pattern, searcher);
+    let comment_syntax = CommentSyntax::from_extension(&searcher.extension);
+    let ignore_comment = ignore_comment && !searcher.include_comments;
+    ExecutableSearcher::new(query)?.search(ignore_comment.then_some(&comment_syntax))
+}
+
+/// Streams the matches of [`word_regex_search_with_extension`] in batches to `send`.
+pub(super) fn word_regex_stream_with_extension(
+    search_pattern: &str,
+    searcher: &RegexSearcher,
+    send: impl FnMut(Vec<Match>) -> bool,
+) -> Result<()> {
+    let comment_syntax = CommentSyntax::from_extension(&searcher.extension);
+    let maybe_comments = (!searcher.include_comments).then_some(&comment_syntax);
+    ExecutableSearcher::new(word_regex_query(search_pattern, searcher))?
+        .stream(maybe_comments, send)
+}
+
+/// Returns the command line of [`word_regex_search_with_extension`] without executing it.
+pub(super) fn word_regex_command_string(search_pattern: &str, searcher: &RegexSearcher) -> String {
+    word_regex_query(search_pattern, searcher).command_string()
+}
+
+fn word_regex_query<'a>(search_pattern: &'a str, searcher: &'a RegexSearcher) -> SearchQuery<'a> {
+    SearchQuery {
+        word_regexp: true,
+        ignore_globs: &searcher.ignore_globs,
+        context: searcher.context,
+        search_ignored: searcher.search_ignored,
+        search_paths: &searcher.search_paths,
+        timeout: searcher.timeout,
+        extra_rg_args: &searcher.extra_rg_args,
+        max_matches: searcher.max_matches,
+        file_order: searcher.file_order,
+        ..SearchQuery::new(
+            search_pattern,
+            FileFilter::Extension(&searcher.extension),
+            searcher.dir.as_ref(),
+        )
+    }
 }
 
 /// [`LanguageRegexSearcher`] with a known language type.
@@ -84,91 +535,572 @@ pub(super) fn word_regex_search_with_extension(
 pub struct LanguageRegexSearcher {
     /// Directory to perform the ripgrep search.
     pub dir: Option<PathBuf>,
+    /// Subdirectories of `dir` to search, the entire `dir` is searched if empty.
+    pub search_paths: Vec<PathBuf>,
     /// Keyword of searching.
     pub word: Word,
     /// Language type defined by ripgrep.
     pub lang: String,
+    /// Number of context lines around the occurrences.
+    pub context: usize,
+    /// Whether to search the files ignored by `.gitignore`/`.ignore` and the hidden files.
+    pub search_ignored: bool,
+    /// Maximum duration of each search command, no timeout if `None`.
+    pub timeout: Option<Duration>,
+    /// Extra arguments appended to every ripgrep command, e.g., `--max-filesize=1M`.
+    pub extra_rg_args: Vec<String>,
+    /// Whether to keep the matches in comments, e.g., the definitions in the docstrings.
+    pub include_comments: bool,
+    /// Maximum number of matches collected by each search, no limit if `None`.
+    pub max_matches: Option<usize>,
+    /// Order of the files searched by ripgrep, the files are unordered if `None`.
+    pub file_order: Option<FileOrder>,
+    /// Globs of the files excluded from every search, e.g., `*_test.go`.
+    pub ignore_globs: Vec<String>,
+    /// Directory of the cached results, the project cache directory if `None`.
+    pub cache_dir: Option<PathBuf>,
 }
 
 impl LanguageRegexSearcher {
     pub fn new(dir: Option<PathBuf>, word: Word, lang: String) -> Self {
-        Self { dir, word, lang }
+        Self {
+            dir,
+            search_paths: Vec::new(),
+            word,
+            lang,
+            context: 0,
+            search_ignored: false,
+            timeout: None,
+            extra_rg_args: Vec::new(),
+            include_comments: false,
+            max_matches: None,
+            file_order: None,
+            ignore_globs: Vec::new(),
+            cache_dir: None,
+        }
+    }
+
+    pub fn search_paths(mut self, search_paths: Vec<PathBuf>) -> Self {
+        self.search_paths = search_paths;
+        self
+    }
+
+    pub fn context(mut self, context: usize) -> Self {
+        self.context = context;
+        self
+    }
+
+    pub fn search_ignored(mut self, search_ignored: bool) -> Self {
+        self.search_ignored = search_ignored;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn extra_rg_args(mut self, extra_rg_args: Vec<String>) -> Self {
+        self.extra_rg_args = extra_rg_args;
+        self
+    }
+
+    pub fn include_comments(mut self, include_comments: bool) -> Self {
+        self.include_comments = include_comments;
+        self
+    }
+
+    pub fn max_matches(mut self, max_matches: Option<usize>) -> Self {
+        self.max_matches = max_matches;
+        self
+    }
+
+    pub fn file_order(mut self, file_order: Option<FileOrder>) -> Self {
+        self.file_order = file_order;
+        self
+    }
+
+    pub fn ignore_globs(mut self, ignore_globs: Vec<String>) -> Self {
+        self.ignore_globs = ignore_globs;
+        self
+    }
+
+    pub fn cache_dir(mut self, cache_dir: Option<PathBuf>) -> Self {
+        self.cache_dir = cache_dir;
+        self
+    }
+
+    /// Returns the comment syntax used to filter out the comment lines, `None` if
+    /// the comments are included.
+    fn comments_filter<'a>(&self, comments: &'a CommentSyntax) -> Option<&'a CommentSyntax> {
+        (!self.include_comments).then_some(comments)
+    }
+
+    fn query<'a>(&'a self, pattern: &'a str) -> SearchQuery<'a> {
+        SearchQuery {
+            context: self.context,
+            search_ignored: self.search_ignored,
+            search_paths: &self.search_paths,
+            timeout: self.timeout,
+            extra_rg_args: &self.extra_rg_args,
+            max_matches: self.max_matches,
+            file_order: self.file_order,
+            ignore_globs: &self.ignore_globs,
+            ..SearchQuery::new(pattern, FileFilter::Language(&self.lang), self.dir.as_ref())
+        }
     }
 
     /// Finds the occurrences and all definitions concurrently.
-    pub fn all(&self, comments: &[String]) -> (Definitions, Occurrences) {
-        (
-            Definitions {
-                defs: self.definitions().unwrap_or_default(),
-            },
-            Occurrences(self.occurrences(comments).unwrap_or_default()),
-        )
+    ///
+    /// The matches in `comments` are excluded unless `include_comments` is set, the same
+    /// as [`Self::definitions_only`].
+    ///
+    /// A failed search, e.g., killed due to the timeout, results in nothing found
+    /// instead of an error. The results are cached for a short while, unless the
+    /// occurrence search failed.
+    pub fn all(&self, comments: &CommentSyntax) -> (Definitions, Occurrences) {
+        let cache = SearchCache::all(self);
+        if let Some(cached) = cache.load() {
+            return cached;
+        }
+
+        let definitions = self
+            .definitions(self.comments_filter(comments))
+            .unwrap_or_default();
+
+        match self.occurrences(comments) {
+            Ok(SearchOutput { matches, truncated }) => {
+                let occurrences = Occurrences { matches, truncated };
+                cache.store_all(&definitions, &occurrences);
+                (definitions, occurrences)
+            }
+            Err(_) => (definitions, Occurrences::default()),
+        }
+    }
+
+    /// Streams the definitions and the occurrences concurrently, the matches in each
+    /// file are passed to `send` tagged by their source as soon as they are found.
+    ///
+    /// Unlike [`Self::all`], the occurrences still contain the definitions and the
+    /// results are not cached. Returns once all the searches finish, each search is
+    /// stopped early once `send` returns `false`.
+    pub fn stream_all(
+        &self,
+        comments: &CommentSyntax,
+        send: impl Fn(MatchSource, Vec<Match>) -> bool + Sync,
+    ) {
+        let kinds = get_definition_rules(&self.lang)
+            .map(|rules| rules.kinds().into_iter().cloned().collect::<Vec<_>>())
+            .unwrap_or_default();
+        let send = &send;
+
+        std::thread::scope(|s| {
+            for kind in &kinds {
+                s.spawn(move || {
+                    let result = self.stream_definitions(kind, comments, |defs| {
+                        send(MatchSource::Definition(kind.clone()), defs)
+                    });
+                    if let Err(err) = result {
+                        tracing::debug!(?err, ?kind, "Failed to stream the definitions");
+                    }
+                });
+            }
+
+            let pattern = self.word.escaped();
+            let result =
+                ExecutableSearcher::new(self.occurrences_query(&pattern)).and_then(|searcher| {
+                    searcher.stream(self.comments_filter(comments), |occurrences| {
+                        send(MatchSource::Occurrence, occurrences)
+                    })
+                });
+            if let Err(err) = result {
+                tracing::debug!(?err, "Failed to stream the occurrences");
+            }
+        });
+    }
+
+    fn stream_definitions(
+        &self,
+        kind: &DefinitionKind,
+        comments: &CommentSyntax,
+        mut send: impl FnMut(Vec<Match>) -> bool,
+    ) -> Result<()> {
+        let regexp = self.definition_regexp(kind)?;
+        let multiline = is_multiline_language(&self.lang);
+        let searcher = ExecutableSearcher::new(self.definition_query(&regexp))?;
+        searcher.stream(self.comments_filter(comments), |defs| {
+            if multiline {
+                send(
+                    defs.into_iter()
+                        .map(|matched| narrow_to_keyword_line(matched, &self.word))
+                        .collect(),
+                )
+            } else {
+                send(defs)
+            }
+        })
+    }
+
+    /// Finds all the definitions only, skipping the occurrence search used for
+    /// finding the references.
+    ///
+    /// The definitions in `comments` are excluded unless `include_comments` is set.
+    ///
+    /// The results are cached for a short while like [`Self::all`], including the
+    /// negative results where no definition is found.
+    pub fn definitions_only(&self, comments: &CommentSyntax) -> Definitions {
+        let cache = SearchCache::definitions(self);
+        if let Some(cached) = cache.load() {
+            return cached;
+        }
+
+        match self.definitions(self.comments_filter(comments)) {
+            Ok(definitions) => {
+                cache.store_definitions(&definitions);
+                definitions
+            }
+            Err(_) => Definitions::default(),
+        }
     }
 
-    /// Returns all kinds of definitions.
-    fn definitions(&self) -> Result<Vec<DefinitionSearchResult>> {
-        Ok(get_definition_rules(&self.lang)
+    /// Returns all kinds of definitions, ordered by the kind name.
+    fn definitions(&self, maybe_comments: Option<&CommentSyntax>) -> Result<Definitions> {
+        let results = get_definition_rules(&self.lang)
             .ok_or_else(|| Error::new(ErrorKind::Other, "Can not find the definition rules"))?
-            .0
-            .keys()
-            .map(|kind| self.find_definitions(kind))
-            .par_bridge()
-            .filter_map(|def| {
-                def.ok()
-                    .map(|(kind, matches)| DefinitionSearchResult { kind, matches })
+            .kinds()
+            .into_par_iter()
+            .filter_map(|kind| self.find_definitions(kind, maybe_comments).ok())
+            .collect::<Vec<_>>();
+
+        let truncated = results.iter().any(|(_, output)| output.truncated);
+        let defs = results
+            .into_iter()
+            .map(|(kind, output)| DefinitionSearchResult {
+                kind,
+                matches: output.matches,
             })
-            .collect())
+            .collect();
+
+        Ok(Definitions { defs, truncated })
     }
 
     /// Finds all the occurrences of `word`.
     ///
     /// Basically the occurrences are composed of definitions and usages.
-    fn occurrences(&self, comments: &[String]) -> Result<Vec<Match>> {
-        let mut command = Command::new("rg");
-        command
-            .arg("--json")
-            .arg("--word-regexp")
-            .arg(&self.word.raw)
-            .arg("--type")
-            .arg(&self.lang);
-        if let Some(ref dir) = self.dir {
-            command.current_dir(dir);
-        }
-        ExecutableSearcher::new(command)?.search(Some(comments))
+    fn occurrences(&self, comments: &CommentSyntax) -> Result<SearchOutput> {
+        let pattern = self.word.escaped();
+        ExecutableSearcher::new(self.occurrences_query(&pattern))?
+            .search(self.comments_filter(comments))
     }
 
-    pub(super) fn regexp_search(&self, comments: &[String]) -> Result<Vec<Match>> {
-        let mut command = Command::new("rg");
-        command
-            .arg("--json")
-            .arg("--regexp")
-            .arg(self.word.raw.replace(char::is_whitespace, ".*"))
-            .arg("--type")
-            .arg(&self.lang);
-        if let Some(ref dir) = self.dir {
-            command.current_dir(dir);
+    fn occurrences_query<'a>(&'a self, pattern: &'a str) -> SearchQuery<'a> {
+        SearchQuery {
+            word_regexp: true,
+            ..self.query(pattern)
         }
-        ExecutableSearcher::new(command)?.search(Some(comments))
+    }
+
+    /// Returns the command lines of the definition searches and the occurrence search
+    /// performed by [`Self::all`], without executing them.
+    pub fn command_strings(&self) -> Vec<String> {
+        let mut commands = get_definition_rules(&self.lang)
+            .map(|rules| {
+                rules
+                    .kinds()
+                    .into_iter()
+                    .filter_map(|kind| {
+                        let regexp = self.definition_regexp(kind).ok()?;
+                        Some(self.definition_query(&regexp).command_string())
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        let pattern = self.word.escaped();
+        commands.push(self.occurrences_query(&pattern).command_string());
+        commands
+    }
+
+    pub(super) fn regexp_search(&self, comments: &CommentSyntax) -> Result<SearchOutput> {
+        let pattern = self
+            .word
+            .raw
+            .split(char::is_whitespace)
+            .map(regex::escape)
+            .join(".*");
+        ExecutableSearcher::new(self.query(&pattern))?.search(self.comments_filter(comments))
     }
 
     /// Returns a tuple of (definition_kind, ripgrep_matches) by searching given language `lang`.
-    fn find_definitions(&self, kind: &DefinitionKind) -> Result<(DefinitionKind, Vec<Match>)> {
+    fn find_definitions(
+        &self,
+        kind: &DefinitionKind,
+        maybe_comments: Option<&CommentSyntax>,
+    ) -> Result<(DefinitionKind, SearchOutput)> {
+        let regexp = self.definition_regexp(kind)?;
+        let multiline = is_multiline_language(&self.lang);
+        ExecutableSearcher::new(self.definition_query(&regexp))?
+            .search(maybe_comments)
+            .map(|mut output| {
+                if multiline {
+                    output.matches = output
+                        .matches
+                        .into_iter()
+                        .map(|matched| narrow_to_keyword_line(matched, &self.word))
+                        .collect();
+                }
+                (kind.clone(), output)
+            })
+    }
+
+    /// Returns the regexp of the definitions of `kind`.
+    fn definition_regexp(&self, kind: &DefinitionKind) -> Result<String> {
         let regexp = build_full_regexp(&self.lang, kind, &self.word)
             .ok_or_else(|| Error::new(ErrorKind::Other, "Can not find the definition rule"))?;
-        let mut command = Command::new("rg");
-        command
-            .arg("--trim")
-            .arg("--json")
-            .arg("--pcre2")
-            .arg("--regexp")
-            .arg(regexp)
-            .arg("--type")
-            .arg(&self.lang);
-        if let Some(ref dir) = self.dir {
-            command.current_dir(dir);
+        if is_multiline_language(&self.lang) {
+            Ok(build_multiline_regexp(&regexp))
+        } else {
+            Ok(regexp)
+        }
+    }
+
+    fn definition_query<'a>(&'a self, regexp: &'a str) -> SearchQuery<'a> {
+        SearchQuery {
+            pcre2: true,
+            trim: true,
+            multiline: is_multiline_language(&self.lang),
+            ignore_case: is_case_insensitive_language(&self.lang),
+            context: 0,
+            ..self.query(regexp)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Collects the matches from `output` like [`ExecutableSearcher::search`].
+    fn collect_matches(
+        output: &[u8],
+        backend: SearchBackend,
+        max_matches: Option<usize>,
+    ) -> (Vec<Match>, bool) {
+        let mut collector = MatchCollector::new(backend, true, |_: &Match| true, max_matches);
+        for line in output.split(|x| x == &b'\n') {
+            if !collector.push_line(line) {
+                break;
+            }
+        }
+        (collector.matches, collector.truncated)
+    }
+
+    #[test]
+    fn test_collect_json_output_with_max_matches() {
+        let stdout = (1..=5)
+            .map(|line_number| {
+                format!(
+                    r#"{{"type":"match","data":{{"path":{{"text":"src/lib.rs"}},"lines":{{"text":"foo();\n"}},"line_number":{line_number},"absolute_offset":0,"submatches":[{{"match":{{"text":"foo"}},"start":0,"end":3}}]}}}}"#
+                )
+            })
+            .join("\n");
+
+        let (matches, truncated) =
+            collect_matches(stdout.as_bytes(), SearchBackend::Ripgrep, Some(3));
+        assert!(truncated);
+        assert_eq!(
+            matches.iter().map(Match::line_number).collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+
+        let (matches, truncated) =
+            collect_matches(stdout.as_bytes(), SearchBackend::Ripgrep, Some(5));
+        assert!(!truncated);
+        assert_eq!(matches.len(), 5);
+
+        let (matches, truncated) = collect_matches(stdout.as_bytes(), SearchBackend::Ripgrep, None);
+        assert!(!truncated);
+        assert_eq!(matches.len(), 5);
+    }
+
+    #[test]
+    fn test_collect_vimgrep_output() {
+        let output = b"src/lib.rs:3:8:    fn foo() { foo() }\nsrc/lib.rs:3:16:    fn foo() { foo() }\nsrc/main.rs:10:1:foo();\n";
+
+        let (matches, truncated) = collect_matches(output, SearchBackend::Ag, None);
+        assert!(!truncated);
+        assert_eq!(matches.len(), 2);
+        assert_eq!(matches[0].path(), "src/lib.rs");
+        assert_eq!(matches[0].line_number(), 3);
+        assert_eq!(matches[0].pattern(), "fn foo() { foo() }\n");
+        assert_eq!(matches[0].submatches.len(), 2);
+        assert_eq!(matches[0].column(), 3);
+        assert_eq!(matches[0].submatches[1].start, 11);
+        assert_eq!(matches[1].path(), "src/main.rs");
+        assert_eq!(matches[1].column(), 0);
+
+        // The submatches on the same line are not counted separately.
+        let (matches, truncated) = collect_matches(output, SearchBackend::Ag, Some(1));
+        assert!(truncated);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].submatches.len(), 2);
+    }
+
+    #[test]
+    fn test_extra_rg_args() {
+        let extra_rg_args = vec!["--max-filesize=1M".to_string(), "-U".to_string()];
+        let query = SearchQuery {
+            extra_rg_args: &extra_rg_args,
+            search_paths: &[PathBuf::from("src")],
+            ..SearchQuery::new("foo", FileFilter::Extension("rs"), None)
+        };
+        let command = query.rg_command();
+        let args = command.get_args().collect::<Vec<_>>();
+        assert_eq!(args[args.len() - 3..], ["--max-filesize=1M", "-U", "src"]);
+    }
+
+    #[test]
+    fn test_ignore_globs() {
+        let ignore_globs = vec!["*_test.go".to_string(), "vendor/**".to_string()];
+        let query = SearchQuery {
+            ignore_globs: &ignore_globs,
+            ..SearchQuery::new("foo", FileFilter::Extension("go"), None)
+        };
+        let command = query.rg_command();
+        let args = command.get_args().collect::<Vec<_>>();
+        assert_eq!(
+            args[args.len() - 6..],
+            ["-g", "*.go", "--glob", "!*_test.go", "--glob", "!vendor/**"]
+        );
+
+        let command = query.ag_command();
+        let args = command.get_args().collect::<Vec<_>>();
+        assert_eq!(
+            args[2..6],
+            ["--ignore", "*_test.go", "--ignore", "vendor/**"]
+        );
+    }
+
+    #[test]
+    fn test_ag_command() {
+        let search_paths = [PathBuf::from("crates/a"), PathBuf::from("crates/b")];
+        let query = SearchQuery {
+            search_ignored: true,
+            search_paths: &search_paths,
+            ..SearchQuery::new("foo", FileFilter::Extension("rs"), None)
+        };
+        let command = query.ag_command();
+        let args = command.get_args().collect::<Vec<_>>();
+        assert_eq!(args[2], "--unrestricted");
+        assert_eq!(
+            args[args.len() - 4..],
+            ["--", "foo", "crates/a", "crates/b"]
+        );
+    }
+
+    #[test]
+    fn test_case_insensitive_definitions() {
+        if !*RG_EXISTS {
+            return;
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("schema.sql"),
+            "CREATE TABLE IF NOT EXISTS USERS (\n  id INT\n);\nSELECT * FROM users;\n",
+        )
+        .unwrap();
+
+        let word = Word::new("users".into(), regex::Regex::new("\\busers\\b").unwrap());
+        let lang_regex_searcher =
+            LanguageRegexSearcher::new(Some(dir.path().into()), word, "sql".into())
+                .cache_dir(Some(dir.path().into()));
+        let definitions =
+            lang_regex_searcher.definitions_only(&CommentSyntax::from_extension("sql"));
+        let tables = definitions
+            .into_iter()
+            .find(|def| def.kind.as_ref() == "table")
+            .map(|def| def.matches)
+            .unwrap_or_default();
+        assert_eq!(tables.len(), 1);
+        assert_eq!(tables[0].line_number(), 1);
+    }
+
+    #[test]
+    fn test_definitions_kind_order() {
+        if !*RG_EXISTS {
+            return;
         }
-        ExecutableSearcher::new(command)?
-            .search(None)
-            .map(|defs| (kind.clone(), defs))
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("lib.rs"),
+            "struct foo;\nfn foo() {}\nfn main() {\n    let foo = 1;\n}\n",
+        )
+        .unwrap();
+
+        let word = Word::new("foo".into(), regex::Regex::new("\\bfoo\\b").unwrap());
+        let lang_regex_searcher =
+            LanguageRegexSearcher::new(Some(dir.path().into()), word, "rust".into());
+        let kinds = || {
+            lang_regex_searcher
+                .definitions(None)
+                .unwrap()
+                .into_iter()
+                .map(|def| def.kind)
+                .collect::<Vec<_>>()
+        };
+        let first_run = kinds();
+        assert!(first_run.len() > 1);
+        assert!(first_run.windows(2).all(|w| w[0] < w[1]));
+        for _ in 0..5 {
+            assert_eq!(kinds(), first_run);
+        }
+    }
+
+    #[test]
+    fn test_include_comments() {
+        if !*RG_EXISTS {
+            return;
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("lib.rs"),
+            "// fn parse() {}\nfn parse() {}\n",
+        )
+        .unwrap();
+
+        let comments = CommentSyntax::from_extension("rs");
+        let word = Word::new("parse".into(), regex::Regex::new("\\bparse\\b").unwrap());
+        let count_functions = |searcher: LanguageRegexSearcher| {
+            searcher
+                .definitions_only(&comments)
+                .into_iter()
+                .find(|def| def.kind.as_ref() == "function")
+                .map(|def| def.matches.len())
+                .unwrap_or_default()
+        };
+
+        let searcher = LanguageRegexSearcher::new(Some(dir.path().into()), word, "rust".into())
+            .cache_dir(Some(dir.path().into()));
+        assert_eq!(count_functions(searcher.clone()), 1);
+        assert_eq!(count_functions(searcher.clone().include_comments(true)), 2);
+
+        // `all` filters the definitions in the same way.
+        let (definitions, _) = searcher.all(&comments);
+        assert_eq!(
+            definitions
+                .into_iter()
+                .find(|def| def.kind.as_ref() == "function")
+                .map(|def| def.matches.len()),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn test_language_file_regex() {
+        let re = regex::Regex::new(&language_file_regex("rust")).unwrap();
+        assert!(re.is_match("src/lib.rs"));
+        assert!(!re.is_match("src/lib.rsx"));
     }
 }
@@ -10,16 +10,27 @@
 
 mod definition;
 mod executable_searcher;
-
-use self::definition::{find_definitions_and_references, DefinitionSearchResult, MatchKind};
-use self::executable_searcher::{word_regex_search_with_extension, LanguageRegexSearcher};
+mod results_cache;
+
+use self::definition::{
+    find_definitions_and_references, CommentSyntax, DefinitionSearchResult, MatchKind, Occurrences,
+};
+use self::executable_searcher::{
+    word_regex_command_string, word_regex_search_with_extension, word_regex_stream_with_extension,
+    LanguageRegexSearcher, MatchSource, SearchOutput,
+};
 use crate::find_usages::{AddressableUsage, Usage, UsageMatcher, Usages};
 use crate::tools::rg::{get_language, Match, Word};
 use code_tools::analyzer::{resolve_reference_kind, Priority};
 use rayon::prelude::*;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::io::{Error, ErrorKind, Result};
+use std::ops::RangeInclusive;
 use std::path::PathBuf;
+use std::time::Duration;
+use tokio::sync::mpsc::UnboundedSender;
+
+pub use self::definition::OccurrenceCursor;
 
 /// [`Usage`] with some structured information.
 #[derive(Clone, Debug, Default, PartialEq, Eq)]
@@ -28,7 +39,11 @@ pub struct RegexUsage {
     pub indices: Vec<usize>,
     pub path: String,
     pub line_number: usize,
+    /// Byte column of the first submatch.
+    pub column: usize,
     pub pattern_priority: Priority,
+    /// Whether the line contains the full qualified path of the searched word.
+    pub qualified_match: bool,
 }
 
 impl From<RegexUsage> for AddressableUsage {
@@ -38,6 +53,7 @@ impl From<RegexUsage> for AddressableUsage {
             indices,
             path,
             line_number,
+            column,
             ..
         } = regex_usage;
         Self {
@@ -45,29 +61,42 @@ impl From<RegexUsage> for AddressableUsage {
             indices,
             path,
             line_number,
+            column,
         }
     }
 }
 
 impl RegexUsage {
     fn from_matched(matched: &Match, line: String, indices: Vec<usize>) -> Self {
+        let (path, line_number, column) = matched.jump_target();
         Self {
             line,
             indices,
-            path: matched.path().into(),
-            line_number: matched.line_number() as usize,
+            path: path.into(),
+            line_number: line_number as usize,
+            column,
             pattern_priority: matched.pattern_priority(),
+            qualified_match: false,
         }
     }
 }
 
 impl PartialOrd for RegexUsage {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some((self.pattern_priority, &self.path, self.line_number).cmp(&(
-            other.pattern_priority,
-            &other.path,
-            other.line_number,
-        )))
+        Some(
+            (
+                !self.qualified_match,
+                self.pattern_priority,
+                &self.path,
+                self.line_number,
+            )
+                .cmp(&(
+                    !other.qualified_match,
+                    other.pattern_priority,
+                    &other.path,
+                    other.line_number,
+                )),
+        )
     }
 }
 
@@ -77,16 +106,256 @@ impl Ord for RegexUsage {
     }
 }
 
+/// Source of the usages streamed by [`RegexSearcher::stream_usages`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum UsageSource {
+    /// Definitions of the kind, e.g., `function`.
+    Definition(String),
+    /// Occurrences of the word, which include the definitions as well.
+    Occurrence,
+}
+
+/// Usages found in one file by [`RegexSearcher::stream_usages`].
 #[derive(Clone, Debug)]
+pub struct UsageBatch {
+    pub source: UsageSource,
+    pub usages: Vec<AddressableUsage>,
+}
+
+/// Usages found by [`RegexSearcher`].
+#[derive(Clone, Debug, Default)]
+pub struct RegexUsages {
+    pub usages: Vec<AddressableUsage>,
+    /// Whether any search is stopped at [`RegexSearcher::max_matches`], in which case
+    /// some usages are missing.
+    pub truncated: bool,
+}
+
+/// Order of the files searched by ripgrep.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FileOrder {
+    /// `--sort=path`.
+    Path,
+    /// `--sortr=modified`, the most recently modified files come first.
+    RecentlyModified,
+}
+
+impl FileOrder {
+    fn rg_arg(&self) -> &'static str {
+        match self {
+            Self::Path => "--sort=path",
+            Self::RecentlyModified => "--sortr=modified",
+        }
+    }
+}
+
+impl std::str::FromStr for FileOrder {
+    type Err = String;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "path" => Ok(Self::Path),
+            "modified" => Ok(Self::RecentlyModified),
+            _ => Err(format!("unknown file order {s}, expected path or modified")),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
 pub struct RegexSearcher {
     pub word: String,
     pub extension: String,
+    /// Working directory of the search.
     pub dir: Option<PathBuf>,
+    /// Subdirectories to search, relative to `dir` or absolute, e.g., the crates
+    /// in a workspace. The entire `dir` is searched if empty.
+    pub search_paths: Vec<PathBuf>,
+    /// Number of context lines shown around each usage, no context lines if 0.
+    pub context: usize,
+    /// Whether to search the files ignored by `.gitignore`/`.ignore` and the hidden files,
+    /// e.g., the generated code which is usually ignored.
+    pub search_ignored: bool,
+    /// Maximum duration of each search command, no timeout if `None`.
+    ///
+    /// The definition searches that timed out contribute no usages, while the plain
+    /// occurrence search for an unknown language returns an error of [`ErrorKind::TimedOut`].
+    pub timeout: Option<Duration>,
+    /// Extra arguments appended to every ripgrep command, e.g., `--glob=!vendor`.
+    ///
+    /// The conflicts with the built-in arguments are not checked.
+    pub extra_rg_args: Vec<String>,
+    /// Whether to keep the matches in comments and docstrings, which are filtered
+    /// out by default.
+    pub include_comments: bool,
+    /// Maximum number of matches collected by each search command, no limit if `None`.
+    ///
+    /// The search command is killed once the limit is reached and the results are marked
+    /// as [`RegexUsages::truncated`], which prevents the extremely common words from
+    /// exhausting the memory.
+    pub max_matches: Option<usize>,
+    /// Order of the files searched by ripgrep, the usages are kept in that order
+    /// instead of being sorted by the path, ignored by ag.
+    ///
+    /// Sorting forces ripgrep to search in a single thread, which is considerably
+    /// slower in a large project.
+    pub file_order: Option<FileOrder>,
+    /// Globs of the files excluded from this search only, e.g., `*_test.go`, in
+    /// addition to the ignore files of the project.
+    pub ignore_globs: Vec<String>,
+    /// Whether `word` is a qualified path like `std::collections::HashMap`, in which
+    /// case the last segment is searched and the usages of the full path come first.
+    ///
+    /// Otherwise `word` is searched as is, e.g., `a.b` is searched literally.
+    pub qualified_word: bool,
+    /// Directory of the cached results, the project cache directory if `None`.
+    pub cache_dir: Option<PathBuf>,
 }
 
 impl RegexSearcher {
+    pub fn new(word: String, extension: String, dir: Option<PathBuf>) -> Self {
+        Self {
+            word,
+            extension,
+            dir,
+            ..Default::default()
+        }
+    }
+
+    pub fn search_paths(mut self, search_paths: Vec<PathBuf>) -> Self {
+        self.search_paths = search_paths;
+        self
+    }
+
+    pub fn context(mut self, context: usize) -> Self {
+        self.context = context;
+        self
+    }
+
+    pub fn search_ignored(mut self, search_ignored: bool) -> Self {
+        self.search_ignored = search_ignored;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn extra_rg_args(mut self, extra_rg_args: Vec<String>) -> Self {
+        self.extra_rg_args = extra_rg_args;
+        self
+    }
+
+    pub fn include_comments(mut self, include_comments: bool) -> Self {
+        self.include_comments = include_comments;
+        self
+    }
+
+    pub fn max_matches(mut self, max_matches: Option<usize>) -> Self {
+        self.max_matches = max_matches;
+        self
+    }
+
+    pub fn file_order(mut self, file_order: Option<FileOrder>) -> Self {
+        self.file_order = file_order;
+        self
+    }
+
+    pub fn ignore_globs(mut self, ignore_globs: Vec<String>) -> Self {
+        self.ignore_globs = ignore_globs;
+        self
+    }
+
+    pub fn qualified_word(mut self, qualified_word: bool) -> Self {
+        self.qualified_word = qualified_word;
+        self
+    }
+
+    pub fn cache_dir(mut self, cache_dir: Option<PathBuf>) -> Self {
+        self.cache_dir = cache_dir;
+        self
+    }
+
+    /// Returns the word to search, the last segment is searched if `self.word` is
+    /// specified as a qualified path.
+    fn word(&self) -> Result<Word> {
+        let word = if self.qualified_word {
+            Word::from_qualified(&self.word)
+        } else {
+            regex::Regex::new(&format!("\\b{}\\b", regex::escape(&self.word)))
+                .map(|re| Word::new(self.word.clone(), re))
+        };
+        word.map_err(|e| {
+            Error::new(
+                ErrorKind::Other,
+                format!("{} is an invalid regex expression: {e}", self.word),
+            )
+        })
+    }
+
+    fn language_regex_searcher(&self, word: &Word, lang: &str) -> LanguageRegexSearcher {
+        LanguageRegexSearcher::new(self.dir.clone(), word.clone(), lang.to_string())
+            .search_paths(self.search_paths.clone())
+            .context(self.context)
+            .search_ignored(self.search_ignored)
+            .timeout(self.timeout)
+            .extra_rg_args(self.extra_rg_args.clone())
+            .include_comments(self.include_comments)
+            .max_matches(self.max_matches)
+            .file_order(self.file_order)
+            .ignore_globs(self.ignore_globs.clone())
+            .cache_dir(self.cache_dir.clone())
+    }
+
+    /// Returns the rank of each file in `matches` if the files are sorted by ripgrep.
+    fn file_ranks<'a>(
+        &self,
+        matches: impl IntoIterator<Item = &'a Match>,
+    ) -> Option<HashMap<String, usize>> {
+        if self.file_order.is_none() {
+            return None;
+        }
+        let mut file_ranks = HashMap::new();
+        for matched in matches {
+            let next_rank = file_ranks.len();
+            file_ranks
+                .entry(matched.path().into_owned())
+                .or_insert(next_rank);
+        }
+        Some(file_ranks)
+    }
+
+    /// Returns the command lines [`Self::search_usages`] would execute, without
+    /// executing them, which can be pasted into a terminal to diagnose the results.
+    pub fn command_strings(&self) -> Result<Vec<String>> {
+        let word = self.word()?;
+
+        match get_language(&self.extension) {
+            Some(lang) => Ok(self.language_regex_searcher(&word, lang).command_strings()),
+            None => Ok(vec![word_regex_command_string(&word.escaped(), self)]),
+        }
+    }
+
+    /// Returns a cursor over all the occurrences of the word for navigating through them.
+    pub fn occurrence_cursor(&self) -> Result<OccurrenceCursor> {
+        let word = self.word()?;
+
+        let occurrences = match get_language(&self.extension) {
+            Some(lang) => {
+                let comments = CommentSyntax::from_extension_or_language(&self.extension, lang);
+                self.language_regex_searcher(&word, lang).all(&comments).1
+            }
+            None => {
+                let SearchOutput { matches, truncated } =
+                    word_regex_search_with_extension(&word.escaped(), true, self)?;
+                Occurrences { matches, truncated }
+            }
+        };
+
+        Ok(occurrences.into())
+    }
+
     pub fn cli_usages(&self, usage_matcher: &UsageMatcher) -> Result<Usages> {
-        let usages: Usages = self.search_usages(false, usage_matcher)?.into();
+        let usages: Usages = self.search_usages(false, usage_matcher)?.usages.into();
         Ok(usages)
     }
 
@@ -96,26 +365,22 @@ impl RegexSearcher {
         &self,
         classify: bool,
         usage_matcher: &UsageMatcher,
-    ) -> Result<Vec<AddressableUsage>> {
+    ) -> Result<RegexUsages> {
         let Self {
-            word,
-            extension,
-            dir,
+            extension, context, ..
         } = self;
 
-        let re = regex::Regex::new(&format!("\\b{word}\\b")).map_err(|e| {
-            Error::new(
-                ErrorKind::Other,
-                format!("{word} is an invalid regex expression: {e}"),
-            )
-        })?;
-
-        let word = Word::new(word.clone(), re);
+        let word = self.word()?;
 
         let Some(lang) = get_language(extension) else {
             // Search the occurrences if no language detected.
-            let occurrences =
-                word_regex_search_with_extension(&word.raw, true, extension, dir.as_ref())?;
+            let SearchOutput {
+                matches: occurrences,
+                truncated,
+            } = word_regex_search_with_extension(&word.escaped(), true, self)?;
+            let (context_lines, occurrences): (Vec<_>, Vec<_>) =
+                occurrences.into_iter().partition(|m| m.is_context);
+            let file_ranks = self.file_ranks(&occurrences);
             let mut usages = occurrences
                 .into_iter()
                 .filter_map(|matched| {
@@ -124,14 +389,17 @@ impl RegexSearcher {
                         .map(|(line, indices)| RegexUsage::from_matched(&matched, line, indices))
                 })
                 .collect::<Vec<_>>();
-            usages.par_sort_unstable();
-            return Ok(usages.into_iter().map(Into::into).collect());
+            sort_usages(&mut usages, &word, file_ranks.as_ref());
+            let usages = insert_context_lines(usages, context_lines, *context, &word);
+            return Ok(RegexUsages {
+                usages: usages.into_iter().map(Into::into).collect(),
+                truncated,
+            });
         };
 
-        let lang_regex_searcher =
-            LanguageRegexSearcher::new(dir.clone(), word.clone(), lang.to_string());
+        let lang_regex_searcher = self.language_regex_searcher(&word, lang);
 
-        let comments = code_tools::language::get_line_comments(extension);
+        let comments = &CommentSyntax::from_extension_or_language(extension, lang);
 
         // render the results in group.
         if classify {
@@ -150,25 +418,183 @@ impl RegexSearcher {
         }
     }
 
+    /// Streams the usages to `sender` as soon as they are found, so that the first
+    /// results can be rendered without waiting for the slowest search.
+    ///
+    /// The definitions and the occurrences interleave in batches tagged by [`UsageSource`],
+    /// unlike [`Self::search_usages`], the definitions are not removed from the occurrences
+    /// and the context lines are not sent. Returns once all the searches finish or
+    /// `sender` is closed.
+    pub fn stream_usages(
+        &self,
+        usage_matcher: &UsageMatcher,
+        sender: UnboundedSender<UsageBatch>,
+    ) -> Result<()> {
+        let word = self.word()?;
+
+        let send = |source: UsageSource, kind: &str, matches: Vec<Match>| {
+            let usages = matches
+                .into_iter()
+                .filter(|matched| !matched.is_context)
+                .filter_map(|matched| {
+                    usage_matcher
+                        .match_jump_line(matched.build_jump_line(kind, &word))
+                        .map(|(line, indices)| {
+                            RegexUsage::from_matched(&matched, line, indices).into()
+                        })
+                })
+                .collect::<Vec<AddressableUsage>>();
+            usages.is_empty() || sender.send(UsageBatch { source, usages }).is_ok()
+        };
+
+        let Some(lang) = get_language(&self.extension) else {
+            return word_regex_stream_with_extension(&word.escaped(), self, |matches| {
+                send(UsageSource::Occurrence, "refs", matches)
+            });
+        };
+
+        let comments = CommentSyntax::from_extension_or_language(&self.extension, lang);
+        self.language_regex_searcher(&word, lang)
+            .stream_all(&comments, |source, matches| match source {
+                MatchSource::Definition(kind) => send(
+                    UsageSource::Definition(kind.as_ref().to_string()),
+                    kind.as_ref(),
+                    matches,
+                ),
+                MatchSource::Occurrence => send(UsageSource::Occurrence, "refs", matches),
+            });
+
+        Ok(())
+    }
+
+    /// Searches the lines containing all of `words`, `self.word` is ignored.
+    ///
+    /// The lines matching any word are searched first and then the lines missing any
+    /// word are dropped, the matches of every word in a line are highlighted.
+    pub fn search_all_words(
+        &self,
+        words: &[String],
+        usage_matcher: &UsageMatcher,
+    ) -> Result<RegexUsages> {
+        if words.is_empty() {
+            return Ok(RegexUsages::default());
+        }
+
+        let words = words
+            .iter()
+            .map(|word| {
+                regex::Regex::new(&format!("\\b{}\\b", regex::escape(word)))
+                    .map(|re| Word::new(word.clone(), re))
+                    .map_err(|e| {
+                        Error::new(
+                            ErrorKind::Other,
+                            format!("{word} is an invalid regex expression: {e}"),
+                        )
+                    })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let search_pattern = words
+            .iter()
+            .map(Word::escaped)
+            .collect::<Vec<_>>()
+            .join("|");
+        let SearchOutput { matches, truncated } =
+            word_regex_search_with_extension(&search_pattern, true, self)?;
+        let file_ranks = self.file_ranks(matches.iter().filter(|matched| !matched.is_context));
+        let mut usages = matches
+            .into_iter()
+            .filter(|matched| {
+                let line = matched.pattern();
+                !matched.is_context && words.iter().all(|word| word.re.is_match(&line))
+            })
+            .filter_map(|matched| {
+                usage_matcher
+                    .match_jump_line(matched.build_jump_line_all_submatches("refs"))
+                    .map(|(line, indices)| RegexUsage::from_matched(&matched, line, indices))
+            })
+            .collect::<Vec<_>>();
+
+        // None of the words is a qualified path, any of them works for sorting.
+        sort_usages(&mut usages, &words[0], file_ranks.as_ref());
+
+        Ok(RegexUsages {
+            usages: usages.into_iter().map(Into::into).collect(),
+            truncated,
+        })
+    }
+
+    /// Search the definitions only, the references are not searched.
+    ///
+    /// Returns an error if no language is detected from the extension.
+    pub fn search_definitions(&self, usage_matcher: &UsageMatcher) -> Result<RegexUsages> {
+        let word = self.word()?;
+
+        let lang = get_language(&self.extension).ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("Unknown language for the extension {}", self.extension),
+            )
+        })?;
+
+        let lang_regex_searcher = self.language_regex_searcher(&word, lang);
+
+        let comments = CommentSyntax::from_extension_or_language(&self.extension, lang);
+
+        let definitions = lang_regex_searcher.definitions_only(&comments);
+        // The definitions of each kind are searched separately, the files are ranked by
+        // their first appearance.
+        let file_ranks = self.file_ranks(definitions.defs.iter().flat_map(|def| &def.matches));
+        let truncated = definitions.truncated;
+
+        let mut regex_usages = definitions
+            .into_iter()
+            .flat_map(|DefinitionSearchResult { kind, matches }| {
+                matches
+                    .into_iter()
+                    .filter_map(|matched| {
+                        usage_matcher
+                            .match_jump_line(matched.build_jump_line(kind.as_ref(), &word))
+                            .map(|(line, indices)| {
+                                RegexUsage::from_matched(&matched, line, indices)
+                            })
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        sort_usages(&mut regex_usages, &word, file_ranks.as_ref());
+
+        Ok(RegexUsages {
+            usages: regex_usages.into_iter().map(Into::into).collect(),
+            truncated,
+        })
+    }
+
     /// Search the usages using the pre-defined regex matching rules.
     ///
     /// If the result from regex matching is empty, try the pure grep approach.
     fn regex_search(
         &self,
         lang_regex_searcher: LanguageRegexSearcher,
-        comments: &[String],
+        comments: &CommentSyntax,
         usage_matcher: &UsageMatcher,
-    ) -> Result<Vec<AddressableUsage>> {
-        let (definitions, occurrences) = lang_regex_searcher.all(comments);
+    ) -> Result<RegexUsages> {
+        let (definitions, mut occurrences) = lang_regex_searcher.all(comments);
+        let context_lines = occurrences.take_context_lines();
+        let truncated = definitions.truncated || occurrences.truncated;
 
-        let defs = definitions.flatten();
+        // The occurrences include all the positive definitions.
+        let file_ranks = self.file_ranks(&occurrences.matches);
 
         // There are some negative definitions we need to filter them out, e.g., the word
         // is a substring in some identifier but we consider every word is a valid identifier.
-        let positive_defs = defs
-            .iter()
-            .filter(|def| occurrences.contains(def))
-            .collect::<Vec<_>>();
+        //
+        // The definition lines are trimmed, hence the lines are compared by the position.
+        let occurrence_positions = occurrences.positions();
+
+        // References are the occurrences that are not in the definition set.
+        occurrences.remove_definitions(&definitions);
 
         let word = &lang_regex_searcher.word;
 
@@ -177,39 +603,36 @@ impl RegexSearcher {
             .flat_map(|DefinitionSearchResult { kind, matches }| {
                 matches
                     .into_iter()
-                    .filter_map(|matched| {
-                        if positive_defs.contains(&&matched) {
-                            usage_matcher
-                                .match_jump_line(matched.build_jump_line(kind.as_ref(), word))
-                                .map(|(line, indices)| {
-                                    RegexUsage::from_matched(&matched, line, indices)
-                                })
-                        } else {
-                            None
-                        }
+                    .filter(|matched| {
+                        occurrence_positions
+                            .contains(&(matched.path().into_owned(), matched.line_number()))
                     })
-                    .collect::<Vec<_>>()
-            })
-            .chain(
-                // references are the occurrences that are not in the definition set.
-                occurrences.into_iter().filter_map(|matched| {
-                    if !defs.contains(&matched) {
-                        let (kind, _) = resolve_reference_kind(matched.pattern(), &self.extension);
+                    .filter_map(|matched| {
                         usage_matcher
-                            .match_jump_line(matched.build_jump_line(kind, word))
+                            .match_jump_line(matched.build_jump_line(kind.as_ref(), word))
                             .map(|(line, indices)| {
                                 RegexUsage::from_matched(&matched, line, indices)
                             })
-                    } else {
-                        None
-                    }
-                }),
-            )
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .chain(occurrences.into_iter().filter_map(|matched| {
+                let (kind, _) = resolve_reference_kind(matched.pattern(), &self.extension);
+                usage_matcher
+                    .match_jump_line(matched.build_jump_line(kind, word))
+                    .map(|(line, indices)| RegexUsage::from_matched(&matched, line, indices))
+            }))
             .collect::<Vec<_>>();
 
         // Pure results by grepping the word.
         if regex_usages.is_empty() {
-            let lines = lang_regex_searcher.regexp_search(comments)?;
+            let SearchOutput {
+                matches: lines,
+                truncated,
+            } = lang_regex_searcher.regexp_search(comments)?;
+            let (context_lines, lines): (Vec<_>, Vec<_>) =
+                lines.into_iter().partition(|m| m.is_context);
+            let file_ranks = self.file_ranks(&lines);
             let mut grep_usages = lines
                 .into_par_iter()
                 .filter_map(|matched| {
@@ -218,15 +641,103 @@ impl RegexSearcher {
                         .map(|(line, indices)| RegexUsage::from_matched(&matched, line, indices))
                 })
                 .collect::<Vec<_>>();
-            grep_usages.par_sort_unstable();
-            return Ok(grep_usages.into_iter().map(Into::into).collect());
+            sort_usages(&mut grep_usages, word, file_ranks.as_ref());
+            let grep_usages = insert_context_lines(grep_usages, context_lines, self.context, word);
+            return Ok(RegexUsages {
+                usages: grep_usages.into_iter().map(Into::into).collect(),
+                truncated,
+            });
         }
 
-        regex_usages.par_sort_unstable();
-        Ok(regex_usages.into_iter().map(Into::into).collect())
+        sort_usages(&mut regex_usages, word, file_ranks.as_ref());
+        let regex_usages = insert_context_lines(regex_usages, context_lines, self.context, word);
+        Ok(RegexUsages {
+            usages: regex_usages.into_iter().map(Into::into).collect(),
+            truncated,
+        })
+    }
+}
+
+/// Sorts the usages, the usages of the full qualified path of `word` come first.
+///
+/// The files are kept in the order of `file_ranks` if the files are sorted by ripgrep,
+/// otherwise the usages are sorted by the priority and then the path.
+fn sort_usages(
+    usages: &mut [RegexUsage],
+    word: &Word,
+    file_ranks: Option<&HashMap<String, usize>>,
+) {
+    if word.qualified.is_some() {
+        usages.par_iter_mut().for_each(|usage| {
+            usage.qualified_match = word.is_qualified_match(&usage.line);
+        });
+    }
+    match file_ranks {
+        Some(file_ranks) => usages.par_sort_by_key(|usage| {
+            (
+                !usage.qualified_match,
+                file_ranks.get(&usage.path).copied().unwrap_or(usize::MAX),
+                usage.line_number,
+            )
+        }),
+        None => usages.par_sort_unstable(),
     }
 }
 
+/// Inserts the context lines right around the usage they belong to.
+///
+/// The context lines are tagged with the kind `context` so that they can be
+/// distinguished from the actual usages.
+fn insert_context_lines(
+    usages: Vec<RegexUsage>,
+    context_lines: Vec<Match>,
+    context: usize,
+    word: &Word,
+) -> Vec<RegexUsage> {
+    if context_lines.is_empty() {
+        return usages;
+    }
+
+    let mut context_map: HashMap<String, BTreeMap<usize, Match>> = HashMap::new();
+    for matched in context_lines {
+        context_map
+            .entry(matched.path().into_owned())
+            .or_default()
+            .insert(matched.line_number() as usize, matched);
+    }
+
+    let mut inserted = HashSet::new();
+    let mut context_usages = |path: &str, range: RangeInclusive<usize>| {
+        context_map
+            .get(path)
+            .into_iter()
+            .flat_map(|lines| lines.range(range))
+            .filter(|(line_number, _)| inserted.insert((path.to_string(), **line_number)))
+            .map(|(_, matched)| {
+                let (line, indices) = matched.build_jump_line("context", word);
+                RegexUsage::from_matched(matched, line, indices)
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let mut result = Vec::with_capacity(usages.len() * (1 + 2 * context));
+    for usage in usages {
+        let line_number = usage.line_number;
+        result.extend(context_usages(
+            &usage.path,
+            line_number.saturating_sub(context)..=line_number.saturating_sub(1),
+        ));
+        let path = usage.path.clone();
+        result.push(usage);
+        result.extend(context_usages(
+            &path,
+            line_number + 1..=line_number + context,
+        ));
+    }
+
+    result
+}
+
 // TODO: a new renderer for dumb jump
 fn render_classify(
     matches: Vec<Match>,
@@ -266,20 +777,238 @@ fn render_classify(
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_sort_usages_by_qualified_path() {
+        let usage = |path: &str, line: &str| RegexUsage {
+            line: line.into(),
+            path: path.into(),
+            ..Default::default()
+        };
+        let mut usages = vec![
+            usage("a.rs", "let map = HashMap::new();"),
+            usage("b.rs", "use std::collections::HashMap;"),
+        ];
+
+        sort_usages(&mut usages, &Word::from_qualified("HashMap").unwrap(), None);
+        assert_eq!(usages[0].path, "a.rs");
+
+        sort_usages(
+            &mut usages,
+            &Word::from_qualified("std::collections::HashMap").unwrap(),
+            None,
+        );
+        assert_eq!(usages[0].path, "b.rs");
+    }
+
+    #[test]
+    fn test_qualified_word() {
+        let searcher = RegexSearcher::new("a.b".into(), "py".into(), None);
+        let word = searcher.word().unwrap();
+        assert_eq!(word.raw, "a.b");
+        assert!(word.qualified.is_none());
+        assert!(word.re.is_match("x = a.b"));
+        assert!(!word.re.is_match("x = a_b"));
+
+        let word = searcher.qualified_word(true).word().unwrap();
+        assert_eq!(word.raw, "b");
+        assert_eq!(word.qualified.as_deref(), Some("a.b"));
+    }
+
+    #[test]
+    fn test_sort_usages_by_file_ranks() {
+        let usage = |path: &str, line_number: usize, pattern_priority: usize| RegexUsage {
+            path: path.into(),
+            line_number,
+            pattern_priority: pattern_priority.into(),
+            ..Default::default()
+        };
+        let mut usages = vec![
+            usage("a.rs", 3, 0),
+            usage("b.rs", 9, 2),
+            usage("b.rs", 2, 1),
+        ];
+
+        // b.rs is modified more recently than a.rs.
+        let file_ranks = HashMap::from([("b.rs".to_string(), 0), ("a.rs".to_string(), 1)]);
+        let word = Word::from_qualified("foo").unwrap();
+        sort_usages(&mut usages, &word, Some(&file_ranks));
+        assert_eq!(
+            usages
+                .iter()
+                .map(|usage| (usage.path.as_str(), usage.line_number))
+                .collect::<Vec<_>>(),
+            vec![("b.rs", 2), ("b.rs", 9), ("a.rs", 3)]
+        );
+    }
+
+    #[test]
+    fn test_search_all_words() {
+        if !*crate::tools::rg::RG_EXISTS {
+            return;
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("lib.rs"),
+            "fn parse() {}\nlet 配置 = config; parse(配置_file);\nlet config = load();\n",
+        )
+        .unwrap();
+
+        let regex_searcher =
+            RegexSearcher::new(String::new(), "rs".into(), Some(dir.path().into()));
+        let usages = regex_searcher
+            .search_all_words(&["parse".into(), "config".into()], &UsageMatcher::default())
+            .unwrap()
+            .usages;
+        assert_eq!(usages.len(), 1);
+        assert_eq!(usages[0].line_number, 2);
+        // The indices are char indices, the line contains the multi-byte chars before
+        // the highlighted words.
+        let chars = usages[0].line.chars().collect::<Vec<_>>();
+        let highlighted = usages[0]
+            .indices
+            .iter()
+            .map(|&i| chars[i])
+            .collect::<String>();
+        assert_eq!(highlighted, "configparse");
+    }
+
+    #[test]
+    fn test_search_all_words_file_order() {
+        if !*crate::tools::rg::RG_EXISTS {
+            return;
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let now = std::time::SystemTime::now();
+        for (file, age) in [("a.rs", 60), ("b.rs", 0)] {
+            let path = dir.path().join(file);
+            std::fs::write(&path, "let config = parse(config_file);\n").unwrap();
+            std::fs::File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(now - std::time::Duration::from_secs(age))
+                .unwrap();
+        }
+
+        let regex_searcher =
+            RegexSearcher::new(String::new(), "rs".into(), Some(dir.path().into()))
+                .file_order(Some(FileOrder::RecentlyModified));
+        let usages = regex_searcher
+            .search_all_words(&["parse".into(), "config".into()], &UsageMatcher::default())
+            .unwrap()
+            .usages;
+        assert_eq!(usages.len(), 2);
+        assert!(usages[0].path.ends_with("b.rs"));
+        assert!(usages[1].path.ends_with("a.rs"));
+    }
+
+    #[test]
+    fn test_stream_usages() {
+        if !*crate::tools::rg::RG_EXISTS {
+            return;
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("lib.rs"), "fn parse() {}\n").unwrap();
+        std::fs::write(dir.path().join("main.rs"), "fn main() {\n    parse();\n}\n").unwrap();
+
+        let regex_searcher =
+            RegexSearcher::new("parse".into(), "rs".into(), Some(dir.path().into()));
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        regex_searcher
+            .stream_usages(&UsageMatcher::default(), sender)
+            .unwrap();
+
+        let mut batches = Vec::new();
+        while let Ok(batch) = receiver.try_recv() {
+            batches.push(batch);
+        }
+        assert!(batches.iter().any(|batch| {
+            batch.source == UsageSource::Definition("function".into())
+                && batch
+                    .usages
+                    .iter()
+                    .all(|usage| usage.path.ends_with("lib.rs"))
+        }));
+        let occurrences = batches
+            .iter()
+            .filter(|batch| batch.source == UsageSource::Occurrence)
+            .map(|batch| batch.usages.len())
+            .sum::<usize>();
+        assert_eq!(occurrences, 2);
+    }
+
+    #[test]
+    fn test_search_usages_without_duplicate_definitions() {
+        if !*crate::tools::rg::RG_EXISTS {
+            return;
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("lib.rs"),
+            "mod inner {\n    pub fn parse() {}\n}\nfn main() {\n    inner::parse();\n}\n",
+        )
+        .unwrap();
+
+        let regex_searcher =
+            RegexSearcher::new("parse".into(), "rs".into(), Some(dir.path().into()))
+                .cache_dir(Some(dir.path().into()));
+        let usages = regex_searcher
+            .search_usages(false, &UsageMatcher::default())
+            .unwrap()
+            .usages;
+        // The indented definition is reported once as a definition, not as a reference.
+        assert_eq!(usages.len(), 2);
+        assert_eq!(usages[0].line_number, 2);
+        assert!(usages[0].line.starts_with("[rfunction]"));
+        assert_eq!(usages[1].line_number, 5);
+    }
+
+    #[test]
+    fn test_search_usages_truncated() {
+        if !*crate::tools::rg::RG_EXISTS {
+            return;
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("notes.zzz"), "foo\nfoo\nfoo\n").unwrap();
+
+        let regex_searcher =
+            RegexSearcher::new("foo".into(), "zzz".into(), Some(dir.path().into()))
+                .max_matches(Some(2));
+        let results = regex_searcher
+            .search_usages(false, &UsageMatcher::default())
+            .unwrap();
+        assert!(results.truncated);
+        assert_eq!(results.usages.len(), 2);
+
+        let results = regex_searcher
+            .max_matches(Some(3))
+            .search_usages(false, &UsageMatcher::default())
+            .unwrap();
+        assert!(!results.truncated);
+        assert_eq!(results.usages.len(), 3);
+    }
+
     #[test]
     fn test_regex_runner_language_keyword_ordering() {
-        let regex_searcher = RegexSearcher {
-            word: "clap#legacy#filter#async#dyn#start_filter_with_cache".into(),
-            extension: "vim".into(),
-            dir: std::env::current_dir()
+        let regex_searcher = RegexSearcher::new(
+            "clap#legacy#filter#async#dyn#start_filter_with_cache".into(),
+            "vim".into(),
+            std::env::current_dir()
                 .unwrap()
                 .parent()
                 .unwrap()
                 .parent()
                 .map(|path| path.to_path_buf()),
-        };
+        );
         // FIXME: somehow it's Err in CI https://github.com/liuchengxu/vim-clap/runs/6146828485?check_suite_focus=true
-        if let Ok(usages) = regex_searcher.search_usages(false, &UsageMatcher::default()) {
+        if let Ok(RegexUsages { usages, .. }) =
+            regex_searcher.search_usages(false, &UsageMatcher::default())
+        {
             assert!(usages[0]
                 .line
                 .contains("function! clap#legacy#filter#async#dyn#start_filter_with_cache"));
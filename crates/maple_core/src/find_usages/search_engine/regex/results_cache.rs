@@ -0,0 +1,288 @@
+//! Short-lived on-disk cache of the regex search results, so that the repeated
+//! searches of the same word do not have to run ripgrep again.
+//!
+//! The negative results, i.e., no definition is found, are cached as well but
+//! expire sooner.
+//!
+//! The cached results are discarded once any file in the results is modified. The
+//! files not in the results are not checked, the new matches in them show up once
+//! the cache expires.
+
+use super::definition::{Definitions, Occurrences, DEFINITION_RULES_VERSION};
+use super::executable_searcher::LanguageRegexSearcher;
+use crate::tools::rg::Match;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The cached results are discarded after this duration.
+const CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// The cached results without any definition are discarded after this duration.
+const NEGATIVE_CACHE_TTL: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedResults<T> {
+    /// Seconds since the Unix epoch when the results are cached.
+    created_at: u64,
+    /// Modification time in nanoseconds of each file in the results when they are cached.
+    files_mtime: Vec<(PathBuf, u128)>,
+    /// Whether no definition is found.
+    negative: bool,
+    results: T,
+}
+
+/// Search whose results are cached.
+#[derive(Debug, Clone, Copy, Hash)]
+enum SearchKind {
+    /// The definitions and the occurrences.
+    All,
+    /// The definitions only.
+    Definitions,
+}
+
+fn as_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Returns the cache file path of the search, `None` if the search directory is unknown.
+///
+/// The file is created in `searcher.cache_dir` if specified, otherwise in the project
+/// cache directory.
+fn cache_path(searcher: &LanguageRegexSearcher, kind: SearchKind) -> Option<PathBuf> {
+    let dir = searcher.dir.as_ref()?;
+    let key = utils::calculate_hash(&(
+        kind,
+        &searcher.word.raw,
+        dir,
+        &searcher.lang,
+        &searcher.search_paths,
+        searcher.context,
+        searcher.search_ignored,
+        (&searcher.extra_rg_args, &searcher.ignore_globs),
+        searcher.include_comments,
+        searcher.max_matches,
+        searcher.file_order,
+        *DEFINITION_RULES_VERSION,
+    ));
+    let filename = format!("regex_search_{key}.json");
+    match &searcher.cache_dir {
+        Some(cache_dir) => Some(cache_dir.join(filename)),
+        None => crate::datastore::generate_cache_file_path(filename).ok(),
+    }
+}
+
+/// Returns the modification time of `path` in nanoseconds, 0 if it does not exist.
+fn mtime_nanos(path: &Path) -> u128 {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|mtime| mtime.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_nanos())
+        .unwrap_or_default()
+}
+
+/// Returns the modification time of each file in `matches`, whose paths are relative
+/// to `dir` or absolute.
+fn files_mtime<'a>(dir: &Path, matches: impl Iterator<Item = &'a Match>) -> Vec<(PathBuf, u128)> {
+    let files = matches
+        .map(|matched| dir.join(matched.path().as_ref()))
+        .collect::<BTreeSet<_>>();
+    files
+        .into_iter()
+        .map(|file| {
+            let mtime = mtime_nanos(&file);
+            (file, mtime)
+        })
+        .collect()
+}
+
+fn load_from<T: DeserializeOwned>(path: &Path, now: u64) -> Option<T> {
+    let content = std::fs::read(path).ok()?;
+    let cached: CachedResults<T> = serde_json::from_slice(&content).ok()?;
+    let ttl = if cached.negative {
+        NEGATIVE_CACHE_TTL
+    } else {
+        CACHE_TTL
+    };
+    let fresh = now.saturating_sub(cached.created_at) < ttl.as_secs()
+        && cached
+            .files_mtime
+            .iter()
+            .all(|(file, mtime)| mtime_nanos(file) == *mtime);
+    fresh.then_some(cached.results)
+}
+
+/// Cache of the results of one search, created once before the search and used for
+/// both loading and storing the results.
+pub(super) struct SearchCache<'a> {
+    /// `None` if the search directory is unknown, in which case nothing is cached.
+    path: Option<PathBuf>,
+    dir: Option<&'a PathBuf>,
+    /// The results are not stored if any file in them is modified after the search starts.
+    started_at: SystemTime,
+}
+
+impl<'a> SearchCache<'a> {
+    fn new(searcher: &'a LanguageRegexSearcher, kind: SearchKind) -> Self {
+        Self {
+            path: cache_path(searcher, kind),
+            dir: searcher.dir.as_ref(),
+            started_at: SystemTime::now(),
+        }
+    }
+
+    /// Returns the cache of the definitions and the occurrences search.
+    pub(super) fn all(searcher: &'a LanguageRegexSearcher) -> Self {
+        Self::new(searcher, SearchKind::All)
+    }
+
+    /// Returns the cache of the definitions only search.
+    pub(super) fn definitions(searcher: &'a LanguageRegexSearcher) -> Self {
+        Self::new(searcher, SearchKind::Definitions)
+    }
+
+    /// Loads the results of the same search cached recently, `None` if the cache is
+    /// expired or any file in the results has been modified since.
+    pub(super) fn load<T: DeserializeOwned>(&self) -> Option<T> {
+        load_from(self.path.as_ref()?, as_secs(SystemTime::now()))
+    }
+
+    /// Caches the results of the search of [`Self::all`].
+    pub(super) fn store_all(&self, definitions: &Definitions, occurrences: &Occurrences) {
+        let matches = definitions
+            .defs
+            .iter()
+            .flat_map(|def| &def.matches)
+            .chain(&occurrences.matches);
+        self.store(&(definitions, occurrences), matches, definitions.is_empty());
+    }
+
+    /// Caches the results of the search of [`Self::definitions`].
+    pub(super) fn store_definitions(&self, definitions: &Definitions) {
+        self.store(
+            definitions,
+            definitions.defs.iter().flat_map(|def| &def.matches),
+            definitions.is_empty(),
+        );
+    }
+
+    fn store<'m, T: Serialize>(
+        &self,
+        results: &T,
+        matches: impl Iterator<Item = &'m Match>,
+        negative: bool,
+    ) {
+        let (Some(path), Some(dir)) = (&self.path, self.dir) else {
+            return;
+        };
+
+        let files_mtime = files_mtime(dir, matches);
+        let started_at = self
+            .started_at
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        if files_mtime.iter().any(|(_, mtime)| *mtime >= started_at) {
+            tracing::debug!("Files modified during the regex search, the results are not cached");
+            return;
+        }
+
+        let cached = CachedResults {
+            created_at: as_secs(self.started_at),
+            files_mtime,
+            negative,
+            results,
+        };
+        let result = serde_json::to_vec(&cached)
+            .map_err(Into::into)
+            .and_then(|buf| utils::write_atomically(path, &buf));
+        if let Err(err) = result {
+            tracing::debug!(?err, "Failed to cache the regex search results");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_from() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("regex_search.json");
+        let src = dir.path().join("lib.rs");
+        std::fs::write(&src, "fn foo() {}\n").unwrap();
+        let matched = crate::tools::rg::Match::try_from(
+            r#"{"type":"match","data":{"path":{"text":"lib.rs"},"lines":{"text":"fn foo() {}\n"},"line_number":1,"absolute_offset":0,"submatches":[{"match":{"text":"foo"},"start":3,"end":6}]}}"#,
+        )
+        .unwrap();
+        let files_mtime = files_mtime(dir.path(), std::iter::once(&matched));
+        assert_eq!(files_mtime, vec![(src.clone(), mtime_nanos(&src))]);
+        let cached = CachedResults {
+            created_at: 1000,
+            files_mtime,
+            negative: false,
+            results: (Definitions::default(), Occurrences::from(vec![matched])),
+        };
+        std::fs::write(&path, serde_json::to_vec(&cached).unwrap()).unwrap();
+
+        let (_, occurrences): (Definitions, Occurrences) = load_from(&path, 1010).unwrap();
+        assert_eq!(occurrences.len(), 1);
+        assert_eq!(occurrences.into_inner()[0].column(), 3);
+
+        // Expired.
+        assert!(
+            load_from::<(Definitions, Occurrences)>(&path, 1000 + CACHE_TTL.as_secs()).is_none()
+        );
+
+        // Files modified, even within the same second.
+        std::fs::File::options()
+            .write(true)
+            .open(&src)
+            .unwrap()
+            .set_modified(UNIX_EPOCH + Duration::from_nanos(mtime_nanos(&src) as u64 + 1))
+            .unwrap();
+        assert!(load_from::<(Definitions, Occurrences)>(&path, 1010).is_none());
+
+        // The negative results expire sooner.
+        let cached = CachedResults {
+            created_at: 1000,
+            files_mtime: Vec::new(),
+            negative: true,
+            results: Definitions::default(),
+        };
+        std::fs::write(&path, serde_json::to_vec(&cached).unwrap()).unwrap();
+        assert!(load_from::<Definitions>(&path, 1010).is_some());
+        assert!(load_from::<Definitions>(&path, 1000 + NEGATIVE_CACHE_TTL.as_secs()).is_none());
+    }
+
+    #[test]
+    fn test_skip_files_modified_during_search() {
+        let dir = tempfile::tempdir().unwrap();
+        let word =
+            crate::tools::rg::Word::new("foo".into(), regex::Regex::new("\\bfoo\\b").unwrap());
+        let searcher = LanguageRegexSearcher::new(Some(dir.path().into()), word, "rust".into())
+            .cache_dir(Some(dir.path().into()));
+        let matched = crate::tools::rg::Match::try_from(
+            r#"{"type":"match","data":{"path":{"text":"lib.rs"},"lines":{"text":"fn foo() {}\n"},"line_number":1,"absolute_offset":0,"submatches":[{"match":{"text":"foo"},"start":3,"end":6}]}}"#,
+        )
+        .unwrap();
+        let occurrences = Occurrences::from(vec![matched]);
+
+        let cache = SearchCache::all(&searcher);
+        // The file timestamps may lag behind the system time slightly.
+        std::thread::sleep(Duration::from_millis(50));
+        std::fs::write(dir.path().join("lib.rs"), "fn foo() {}\n").unwrap();
+        cache.store_all(&Definitions::default(), &occurrences);
+        assert!(cache.load::<(Definitions, Occurrences)>().is_none());
+
+        let cache = SearchCache::all(&searcher);
+        cache.store_all(&Definitions::default(), &occurrences);
+        assert!(cache.load::<(Definitions, Occurrences)>().is_some());
+    }
+}
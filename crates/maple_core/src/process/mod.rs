@@ -5,8 +5,11 @@ use crate::cache::{push_cache_digest, Digest};
 use crate::datastore::{generate_cache_file_path, CACHE_INFO_IN_MEMORY};
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Read};
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
 
 // TODO: make it configurable so that it can support powershell easier?
 // https://github.com/liuchengxu/vim-clap/issues/640
@@ -51,6 +54,188 @@ pub fn write_stdout_to_file<P: AsRef<Path>>(
     }
 }
 
+/// Executes the command and passes each line of its stdout to `on_line` as soon as
+/// it's printed, the child process is killed once `on_line` returns `false`, so that
+/// the rest of output is never read.
+///
+/// The child is killed as well if it does not exit within `timeout`, in which case an
+/// error of [`std::io::ErrorKind::TimedOut`] is returned. If the child exits with a
+/// failure and prints anything to stderr, the stderr is returned as an error.
+pub fn for_each_stdout_line(
+    cmd: &mut Command,
+    timeout: Option<Duration>,
+    mut on_line: impl FnMut(&[u8]) -> bool,
+) -> std::io::Result<()> {
+    let mut child = cmd.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::Other, "stdout is not captured"))?;
+    let stderr = child.stderr.take();
+
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    let stopped = AtomicBool::new(false);
+    let timed_out = AtomicBool::new(false);
+
+    let (read_result, status, stderr) = std::thread::scope(|s| {
+        // Drain stderr in the background, otherwise the child may block on a full pipe.
+        let stderr = s.spawn(move || {
+            let mut buf = Vec::new();
+            if let Some(mut stderr) = stderr {
+                let _ = stderr.read_to_end(&mut buf);
+            }
+            buf
+        });
+
+        // The child is waited in another thread, so that the timeout is enforced even
+        // if the child prints nothing for a long while.
+        let waiter = s.spawn(|| loop {
+            if let Some(status) = child.try_wait()? {
+                return Ok(status);
+            }
+            let expired = deadline.map_or(false, |deadline| Instant::now() >= deadline);
+            if expired {
+                timed_out.store(true, Ordering::SeqCst);
+            }
+            if expired || stopped.load(Ordering::SeqCst) {
+                let _ = child.kill();
+                return child.wait();
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        });
+
+        let mut read_result = Ok(());
+        for line in BufReader::new(stdout).split(b'\n') {
+            let keep_going = match line {
+                Ok(line) => on_line(&line),
+                Err(err) => {
+                    read_result = Err(err);
+                    false
+                }
+            };
+            if !keep_going {
+                stopped.store(true, Ordering::SeqCst);
+                break;
+            }
+        }
+
+        let status = waiter.join().unwrap_or_else(|_| {
+            Err(std::io::Error::new(
+                std::io::ErrorKind::Other,
+                "the thread waiting for the child panicked",
+            ))
+        });
+        (read_result, status, stderr.join().unwrap_or_default())
+    });
+
+    if timed_out.load(Ordering::SeqCst) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::TimedOut,
+            format!("Command {cmd:?} timed out after {timeout:?}"),
+        ));
+    }
+
+    read_result?;
+    let status = status?;
+    if !stopped.load(Ordering::SeqCst) && !status.success() && !stderr.is_empty() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::Other,
+            String::from_utf8_lossy(&stderr),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Quotes `arg` so that it's passed as a single literal argument through the shell
+/// used by [`shell_command`], i.e., `bash` on Unix and `cmd` on Windows.
+///
+/// This must be applied to any user-controlled value interpolated into a command string.
+pub fn shell_quote(arg: &str) -> String {
+    if cfg!(target_os = "windows") {
+        windows_shell_quote(arg)
+    } else {
+        posix_shell_quote(arg)
+    }
+}
+
+/// Quotes `arg` for a POSIX shell if it contains any character with special meaning.
+fn posix_shell_quote(arg: &str) -> String {
+    let is_plain = |c: char| c.is_ascii_alphanumeric() || "_-./=:,+@%".contains(c);
+    if !arg.is_empty() && arg.chars().all(is_plain) {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r#"'\''"#))
+    }
+}
+
+/// Quotes `arg` for `cmd /C` if it contains any character with special meaning.
+///
+/// `arg` is quoted following the rules of `CommandLineToArgvW` first, then all the
+/// metacharacters of `cmd`, including the quotes, are escaped with `^`.
+fn windows_shell_quote(arg: &str) -> String {
+    let is_plain = |c: char| c.is_ascii_alphanumeric() || "_-./=:,+@\\".contains(c);
+    if !arg.is_empty() && arg.chars().all(is_plain) {
+        return arg.to_string();
+    }
+
+    let mut quoted = String::from('"');
+    let mut backslashes = 0;
+    for c in arg.chars() {
+        if c == '\\' {
+            backslashes += 1;
+            continue;
+        }
+        // The backslashes are literal unless they precede a quote.
+        let escaped_backslashes = if c == '"' {
+            backslashes * 2 + 1
+        } else {
+            backslashes
+        };
+        quoted.push_str(&"\\".repeat(escaped_backslashes));
+        quoted.push(c);
+        backslashes = 0;
+    }
+    // The trailing backslashes precede the closing quote.
+    quoted.push_str(&"\\".repeat(backslashes * 2));
+    quoted.push('"');
+
+    quoted.chars().fold(String::new(), |mut escaped, c| {
+        if "()%!^\"<>&|".contains(c) {
+            escaped.push('^');
+        }
+        escaped.push(c);
+        escaped
+    })
+}
+
+/// Returns the command line of `cmd` which can be pasted into the shell of
+/// [`shell_command`] as is, i.e., `bash` on Unix and `cmd` on Windows, prefixed
+/// with `cd <dir> &&` (`cd /d <dir> &&` on Windows) if the working directory is specified.
+///
+/// The environment variables set on `cmd` are not included.
+pub fn command_string(cmd: &Command) -> String {
+    let command_line = std::iter::once(cmd.get_program())
+        .chain(cmd.get_args())
+        .map(|arg| shell_quote(&arg.to_string_lossy()))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let cd = if cfg!(target_os = "windows") {
+        "cd /d"
+    } else {
+        "cd"
+    };
+
+    match cmd.get_current_dir() {
+        Some(dir) => format!(
+            "{cd} {} && {command_line}",
+            shell_quote(&dir.to_string_lossy())
+        ),
+        None => command_line,
+    }
+}
+
 /// Converts [`std::process::Output`] to a Vec of String.
 ///
 /// Remove the last line if it's empty.
@@ -120,12 +305,10 @@ impl ShellCommand {
     // TODO: remove this.
     /// Caches the output into a tempfile and also writes the cache digest to the disk.
     pub fn write_cache(self, total: usize, cmd_stdout: &[u8]) -> std::io::Result<PathBuf> {
-        use std::io::Write;
-
         let cache_filename = utils::calculate_hash(&self);
         let cache_file = generate_cache_file_path(cache_filename.to_string())?;
 
-        std::fs::File::create(&cache_file)?.write_all(cmd_stdout)?;
+        utils::write_atomically(&cache_file, cmd_stdout)?;
 
         let digest = Digest::new(self, total, cache_file.clone());
 
@@ -134,3 +317,89 @@ impl ShellCommand {
         Ok(cache_file)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_for_each_stdout_line() {
+        let mut lines = Vec::new();
+        for_each_stdout_line(Command::new("seq").arg("3"), None, |line| {
+            lines.push(line.to_vec());
+            true
+        })
+        .unwrap();
+        assert_eq!(lines, vec![b"1".to_vec(), b"2".to_vec(), b"3".to_vec()]);
+
+        // The child printing endlessly is killed once the callback stops it.
+        let mut count = 0;
+        for_each_stdout_line(&mut Command::new("yes"), None, |_| {
+            count += 1;
+            count < 5
+        })
+        .unwrap();
+        assert_eq!(count, 5);
+
+        let err = for_each_stdout_line(
+            Command::new("sleep").arg("10"),
+            Some(Duration::from_millis(100)),
+            |_| true,
+        )
+        .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+
+        let err = for_each_stdout_line(Command::new("ls").arg("/nonexistent"), None, |_| true)
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::Other);
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(posix_shell_quote("--exclude=target"), "--exclude=target");
+        assert_eq!(posix_shell_quote(""), "''");
+        assert_eq!(posix_shell_quote("my project"), "'my project'");
+        assert_eq!(posix_shell_quote("it's"), r#"'it'\''s'"#);
+        assert_eq!(posix_shell_quote(r#"say "hi""#), r#"'say "hi"'"#);
+        assert_eq!(posix_shell_quote("$HOME; rm -rf ~"), "'$HOME; rm -rf ~'");
+        assert_eq!(posix_shell_quote(r"a\b"), r"'a\b'");
+
+        assert_eq!(windows_shell_quote(r"C:\Users\me"), r"C:\Users\me");
+        assert_eq!(windows_shell_quote(""), r#"^"^""#);
+        assert_eq!(windows_shell_quote("my project"), r#"^"my project^""#);
+        assert_eq!(windows_shell_quote("it's"), r#"^"it's^""#);
+        assert_eq!(windows_shell_quote(r#"say "hi""#), r#"^"say \^"hi\^"^""#);
+        assert_eq!(
+            windows_shell_quote("$HOME & del %PATH%"),
+            r#"^"$HOME ^& del ^%PATH^%^""#
+        );
+        assert_eq!(windows_shell_quote(r#"a\"b"#), r#"^"a\\\^"b^""#);
+        assert_eq!(windows_shell_quote(r"C:\my dir\"), r#"^"C:\my dir\\^""#);
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn test_command_string() {
+        let mut cmd = Command::new("rg");
+        cmd.args(["--json", "--regexp", "fn foo(", "-g", "*.rs", "it's"]);
+        assert_eq!(
+            command_string(&cmd),
+            r#"rg --json --regexp 'fn foo(' -g '*.rs' 'it'\''s'"#
+        );
+
+        cmd.current_dir("/tmp/my project");
+        assert!(command_string(&cmd).starts_with("cd '/tmp/my project' && rg --json"));
+    }
+
+    #[cfg(target_os = "windows")]
+    #[test]
+    fn test_command_string() {
+        let mut cmd = Command::new("rg");
+        cmd.args(["--regexp", "a&b"]).current_dir(r"C:\my project");
+        assert_eq!(
+            command_string(&cmd),
+            r#"cd /d ^"C:\my project^" && rg --regexp ^"a^&b^""#
+        );
+    }
+}
@@ -1,13 +1,66 @@
-use std::io::{BufRead, Lines};
+use std::io::{BufRead, Result};
 use subprocess::Exec;
 
+/// Buffer capacity for reading the output of [`Exec`].
+///
+/// We usually have a decent amount of RAM nowadays.
+pub const DEFAULT_BUFFER_CAPACITY: usize = 8 * 1024 * 1024;
+
+/// Returns an iterator of the output lines of `cmd`.
+///
+/// The invalid UTF-8 sequences are replaced with `U+FFFD` instead of dropping the line.
 #[inline]
-pub fn exec(cmd: Exec) -> std::io::Result<Lines<impl BufRead>> {
-    // We usually have a decent amount of RAM nowadays.
-    Ok(std::io::BufReader::with_capacity(
-        8 * 1024 * 1024,
-        cmd.stream_stdout()
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?,
-    )
-    .lines())
+pub fn exec(cmd: Exec) -> Result<impl Iterator<Item = Result<String>>> {
+    exec_with_capacity(cmd, DEFAULT_BUFFER_CAPACITY)
+}
+
+/// Same as [`exec`], but with the given buffer `capacity`.
+pub fn exec_with_capacity(
+    cmd: Exec,
+    capacity: usize,
+) -> Result<impl Iterator<Item = Result<String>>> {
+    Ok(exec_raw_lines(cmd, capacity)?
+        .map(|line| line.map(|line| String::from_utf8_lossy(&line).into_owned())))
+}
+
+/// Returns an iterator of the raw output lines of `cmd`, the decoding is left to the caller.
+///
+/// Same as [`BufRead::lines`], the line ending `\n` or `\r\n` is stripped.
+pub fn exec_raw_lines(cmd: Exec, capacity: usize) -> Result<impl Iterator<Item = Result<Vec<u8>>>> {
+    let stdout = cmd
+        .stream_stdout()
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
+    Ok(std::io::BufReader::with_capacity(capacity, stdout)
+        .split(b'\n')
+        .map(|line| {
+            line.map(|mut line| {
+                if line.last() == Some(&b'\r') {
+                    line.pop();
+                }
+                line
+            })
+        }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_exec_lossy_lines() {
+        let cmd = || Exec::cmd("printf").arg("foo\\r\\nb\\377r\\nbaz");
+
+        assert_eq!(
+            exec_raw_lines(cmd(), 16)
+                .unwrap()
+                .collect::<Result<Vec<_>>>()
+                .unwrap(),
+            vec![b"foo".to_vec(), b"b\xffr".to_vec(), b"baz".to_vec()]
+        );
+        assert_eq!(
+            exec(cmd()).unwrap().collect::<Result<Vec<_>>>().unwrap(),
+            vec!["foo", "b\u{FFFD}r", "baz"]
+        );
+    }
 }
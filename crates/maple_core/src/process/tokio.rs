@@ -1,6 +1,7 @@
 //! Wrapper of [`tokio::process::Command`].
 
 use crate::process::process_output;
+use std::ffi::OsStr;
 use std::path::Path;
 use tokio::process::Command;
 
@@ -61,6 +62,21 @@ impl TokioCommand {
         Self(shell_command(shell_cmd))
     }
 
+    /// Constructs a new instance of [`TokioCommand`] which executes `program` with `args`
+    /// directly instead of in a shell.
+    ///
+    /// Prefer this over [`Self::new`] unless the command needs the shell features like pipe,
+    /// the args are passed as is so that no quoting is required.
+    pub fn with_args<I, S>(program: impl AsRef<OsStr>, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        let mut cmd = Command::new(program);
+        cmd.args(args);
+        Self(cmd)
+    }
+
     pub async fn lines(&mut self) -> std::io::Result<Vec<String>> {
         // Calling `output()` or `spawn().wait_with_output()` directly does not
         // work for Vim.
@@ -103,5 +119,14 @@ mod tests {
                 .collect::<HashSet<String>>(),
             HashSet::from_iter(tokio_cmd.lines().await.unwrap().into_iter())
         );
+
+        let mut tokio_cmd = TokioCommand::with_args("ls", [std::env::current_dir().unwrap()]);
+        assert_eq!(
+            vec!["Cargo.toml", "src"]
+                .into_iter()
+                .map(Into::into)
+                .collect::<HashSet<String>>(),
+            HashSet::from_iter(tokio_cmd.lines().await.unwrap().into_iter())
+        );
     }
 }
@@ -1,6 +1,5 @@
 use crate::searcher::SearchContext;
 use crate::stdio_server::SearchProgressor;
-use dirs::Dirs;
 use filter::BestItems;
 use matcher::Matcher;
 use printer::Printer;
@@ -31,18 +30,11 @@ impl TagItem {
         let path_len = self.path.len() + 2;
         let mut adjustment = 0;
 
-        let mut home_path = PathBuf::new();
         let path = Path::new(&self.path);
-        let path = path.strip_prefix(cwd).unwrap_or({
-            path.strip_prefix(Dirs::base().home_dir())
-                .map(|path| {
-                    home_path.push("~");
-                    home_path = home_path.join(path);
-                    home_path.as_path()
-                })
-                .unwrap_or(path)
-        });
-        let path = path.display();
+        let path = match path.strip_prefix(cwd) {
+            Ok(relative_path) => relative_path.to_string_lossy(),
+            Err(_) => paths::to_home_relative(path),
+        };
 
         let path_label = if taken_width > winwidth {
             format!("[{path}]")
@@ -9,7 +9,7 @@ use crate::stdio_server::plugin::syntax::sublime::{
 use crate::stdio_server::provider::{read_dir_entries, Context, ProviderSource};
 use crate::stdio_server::vim::{preview_syntax, VimResult};
 use crate::tools::ctags::{current_context_tag, BufferTag};
-use paths::{expand_tilde, truncate_absolute_path};
+use paths::{build_abs_path, expand_tilde, truncate_absolute_path};
 use pattern::*;
 use serde::{Deserialize, Serialize};
 use std::io::{Error, ErrorKind, Result};
@@ -197,13 +197,13 @@ fn parse_preview_target(curline: String, ctx: &Context) -> Result<(PreviewTarget
     let mut line_content = None;
 
     let preview_target = match ctx.provider_id() {
-        "files" | "git_files" => PreviewTarget::StartOfFile(ctx.cwd.join(&curline)),
+        "files" | "git_files" => PreviewTarget::StartOfFile(build_abs_path(&ctx.cwd, &curline)),
         "recent_files" => PreviewTarget::StartOfFile(PathBuf::from(&curline)),
         "history" => {
             let path = if curline.starts_with('~') {
                 expand_tilde(curline)
             } else {
-                ctx.cwd.join(&curline)
+                build_abs_path(&ctx.cwd, &curline)
             };
             PreviewTarget::StartOfFile(path)
         }
@@ -214,8 +214,7 @@ fn parse_preview_target(curline: String, ctx: &Context) -> Result<(PreviewTarget
 
                 line_content.replace(cache_line.into());
 
-                let fpath = fpath.strip_prefix("./").unwrap_or(fpath);
-                let path = ctx.cwd.join(fpath);
+                let path = build_abs_path(&ctx.cwd, fpath);
 
                 Ok::<_, Error>((path, lnum))
             };
@@ -227,7 +226,7 @@ fn parse_preview_target(curline: String, ctx: &Context) -> Result<(PreviewTarget
         "dumb_jump" => {
             let (_def_kind, fpath, line_number, _col) =
                 extract_jump_line_info(&curline).ok_or_else(err)?;
-            let path = ctx.cwd.join(fpath);
+            let path = build_abs_path(&ctx.cwd, fpath);
             PreviewTarget::location_in_file(path, line_number)
         }
         "blines" => {
@@ -242,7 +241,7 @@ fn parse_preview_target(curline: String, ctx: &Context) -> Result<(PreviewTarget
         }
         "proj_tags" => {
             let (line_number, p) = extract_proj_tags(&curline).ok_or_else(err)?;
-            let path = ctx.cwd.join(p);
+            let path = build_abs_path(&ctx.cwd, p);
             PreviewTarget::location_in_file(path, line_number)
         }
         "commits" | "bcommits" => {
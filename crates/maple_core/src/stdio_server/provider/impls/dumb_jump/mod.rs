@@ -10,6 +10,7 @@ use crate::stdio_server::provider::{
 use crate::stdio_server::vim::VimResult;
 use crate::tools::ctags::{get_language, TagsGenerator, CTAGS_EXISTS};
 use crate::tools::gtags::GTAGS_EXISTS;
+use crate::tools::rg::{detect_language, language_extension};
 use filter::Query;
 use futures::Future;
 use itertools::Itertools;
@@ -286,11 +287,36 @@ impl DumbJumpProvider {
     }
 }
 
+/// Returns the extension of start buffer for searching the usages.
+///
+/// The language of a file without any known extension is detected from its shebang
+/// or modeline, in which case a typical extension of the detected language is returned.
+fn source_file_extension(ctx: &Context) -> std::io::Result<String> {
+    let extension = ctx.start_buffer_extension();
+    if extension
+        .as_ref()
+        .is_ok_and(|ext| crate::tools::rg::get_language(ext).is_some())
+    {
+        return extension.map(Into::into);
+    }
+
+    let path = &ctx.env.start_buffer_path;
+    let first_lines = utils::read_first_lines(path, 5)
+        .map(|lines| lines.collect::<Vec<_>>())
+        .unwrap_or_default();
+    let first_lines = first_lines.iter().map(String::as_str).collect::<Vec<_>>();
+
+    match detect_language(path, &first_lines).and_then(language_extension) {
+        Some(ext) => Ok(ext.into()),
+        None => extension.map(Into::into),
+    }
+}
+
 #[async_trait::async_trait]
 impl ClapProvider for DumbJumpProvider {
     async fn on_initialize(&mut self, ctx: &mut Context) -> ProviderResult<()> {
         let cwd = ctx.vim.working_dir().await?;
-        let source_file_extension = ctx.start_buffer_extension()?.to_string();
+        let source_file_extension = source_file_extension(ctx)?;
 
         tokio::task::spawn({
             let cwd = cwd.clone();
@@ -396,7 +422,7 @@ impl ClapProvider for DumbJumpProvider {
         let search_worker = SearchWorker {
             cwd,
             query_info: query_info.clone(),
-            source_file_extension: ctx.start_buffer_extension()?.to_string(),
+            source_file_extension: source_file_extension(ctx)?,
         };
         let search_results = self.start_search(search_worker, &query, query_info).await?;
 
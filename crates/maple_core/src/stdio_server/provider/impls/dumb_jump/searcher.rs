@@ -52,12 +52,9 @@ impl SearchWorker {
             usage_matcher,
             ..
         } = self.query_info;
-        let regex_searcher = RegexSearcher {
-            word: keyword,
-            extension: self.source_file_extension,
-            dir: Some(self.cwd.into()),
-        };
-        regex_searcher.search_usages(false, &usage_matcher)
+        RegexSearcher::new(keyword, self.source_file_extension, Some(self.cwd.into()))
+            .search_usages(false, &usage_matcher)
+            .map(|results| results.usages)
     }
 }
 
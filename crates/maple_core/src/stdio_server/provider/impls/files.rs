@@ -63,6 +63,9 @@ impl FilesProvider {
             control.kill_in_background();
         }
 
+        // Only the path part of `file:line` is matched.
+        let (path_query, _jump_target) = types::split_jump_target(&query);
+
         let matcher = ctx
             .matcher_builder()
             .match_scope(if self.args.name_only {
@@ -71,7 +74,7 @@ impl FilesProvider {
                 MatchScope::Full
             })
             .bonuses(vec![self.recent_files_bonus.clone()])
-            .build(Query::from(&query));
+            .build(Query::from(path_query));
 
         let new_control = {
             let stop_signal = Arc::new(AtomicBool::new(false));
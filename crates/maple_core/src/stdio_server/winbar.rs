@@ -68,11 +68,7 @@ pub async fn update_winbar(
                 }
                 None => winwidth,
             };
-            let path = if let Some(home) = dirs::Dirs::base().home_dir().to_str() {
-                path.replacen(home, "~", 1)
-            } else {
-                path
-            };
+            let path = paths::to_home_relative(std::path::Path::new(&path)).into_owned();
             winbar_items.push(("LineNr", shrink_text_to_fit(path, max_width)));
         }
     }
@@ -1,4 +1,5 @@
 use crate::tools::ctags::{BufferTag, CTAGS_HAS_JSON_FEATURE};
+use crate::tools::ctags_executable;
 use rayon::prelude::*;
 use std::io::Result;
 use std::ops::Deref;
@@ -35,13 +36,13 @@ const CONTEXT_SUPERSET: &[&str] = &[
 fn subprocess_cmd(file: impl AsRef<std::ffi::OsStr>, has_json: bool) -> SubprocessCommand {
     if has_json {
         // Redirect stderr otherwise the warning message might occur `ctags: Warning: ignoring null tag...`
-        SubprocessCommand::cmd("ctags")
+        SubprocessCommand::cmd(ctags_executable())
             .stderr(subprocess::NullFile)
             .arg("--fields=+n")
             .arg("--output-format=json")
             .arg(file)
     } else {
-        SubprocessCommand::cmd("ctags")
+        SubprocessCommand::cmd(ctags_executable())
             .stderr(subprocess::NullFile)
             .arg("--fields=+Kn")
             .arg("-f")
@@ -51,7 +52,7 @@ fn subprocess_cmd(file: impl AsRef<std::ffi::OsStr>, has_json: bool) -> Subproce
 }
 
 fn tokio_cmd(file: &Path, has_json: bool) -> TokioCommand {
-    let mut tokio_cmd = TokioCommand::new("ctags");
+    let mut tokio_cmd = TokioCommand::new(ctags_executable());
     if has_json {
         tokio_cmd
             .stderr(Stdio::null())
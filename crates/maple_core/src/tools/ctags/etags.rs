@@ -0,0 +1,48 @@
+use super::ProjectTag;
+use std::collections::BTreeMap;
+use std::io::Result;
+use std::path::Path;
+
+/// Serializes `tags` in the Emacs `TAGS` format.
+///
+/// Each file has a section starting with `\x0c\npath,size\n`, where `size` is the
+/// number of bytes of the tag records in this section.
+pub fn to_etags(tags: &[ProjectTag]) -> String {
+    let mut sections = BTreeMap::<&str, String>::new();
+    for tag in tags {
+        let records = sections.entry(tag.path()).or_default();
+        records.push_str(&tag.to_etags_entry());
+        records.push('\n');
+    }
+
+    sections
+        .into_iter()
+        .map(|(path, records)| format!("\x0c\n{path},{}\n{records}", records.len()))
+        .collect()
+}
+
+/// Writes `tags` to the Emacs `TAGS` file `path`.
+pub fn write_etags(tags: &[ProjectTag], path: impl AsRef<Path>) -> Result<()> {
+    utils::write_atomically(path, to_etags(tags).as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_etags() {
+        let tags = [
+            "foo\tsrc/b.rs\t/^fn foo() {}$/;\"\tfunction\tline:3",
+            "bar\tsrc/a.rs\t/^fn bar() {}$/;\"\tfunction\tline:9",
+            "baz\tsrc/b.rs\t/^const baz: i32 = 1;$/;\"\tconstant\tline:5",
+        ]
+        .map(|line| ProjectTag::from_tab_line(line).unwrap());
+
+        assert_eq!(
+            to_etags(&tags),
+            "\x0c\nsrc/a.rs,14\nfn bar\x7fbar\x019,\n\
+             \x0c\nsrc/b.rs,31\nfn foo\x7ffoo\x013,\nconst baz\x7fbaz\x015,\n"
+        );
+    }
+}
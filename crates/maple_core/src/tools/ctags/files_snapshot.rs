@@ -0,0 +1,182 @@
+use ignore::overrides::OverrideBuilder;
+use ignore::WalkBuilder;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{Error, ErrorKind, Result};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// Modification times of the files indexed by ctags, in nanoseconds.
+///
+/// Persisted alongside the ctags cache so that only the changed files
+/// have to be re-tagged next time.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct FilesSnapshot(BTreeMap<String, u128>);
+
+/// Changes between two [`FilesSnapshot`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SnapshotDiff {
+    /// Files which are newly added or modified.
+    pub changed: Vec<String>,
+    /// Files which no longer exist.
+    pub removed: Vec<String>,
+}
+
+impl SnapshotDiff {
+    pub fn is_empty(&self) -> bool {
+        self.changed.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Returns the modification time in nanoseconds, so that a file saved again within
+/// the same second is still told apart.
+fn mtime_nanos(metadata: &std::fs::Metadata) -> Option<u128> {
+    Some(
+        metadata
+            .modified()
+            .ok()?
+            .duration_since(UNIX_EPOCH)
+            .ok()?
+            .as_nanos(),
+    )
+}
+
+impl FilesSnapshot {
+    /// Captures the modification times of all the files under `dir`.
+    ///
+    /// The paths matching any pattern in `exclude` are skipped, same as the
+    /// `--exclude` option of ctags. Like `ctags -R`, the hidden files and the
+    /// files ignored by `.gitignore` are not skipped.
+    pub fn capture(dir: &Path, exclude: &[String]) -> Result<Self> {
+        let mut overrides = OverrideBuilder::new(dir);
+        for pattern in exclude {
+            overrides
+                .add(&format!("!{pattern}"))
+                .map_err(|e| Error::new(ErrorKind::Other, e.to_string()))?;
+        }
+        let overrides = overrides
+            .build()
+            .map_err(|e| Error::new(ErrorKind::Other, e.to_string()))?;
+
+        let files = WalkBuilder::new(dir)
+            .standard_filters(false)
+            .overrides(overrides)
+            .build()
+            .filter_map(|entry| {
+                let entry = entry.ok()?;
+                if !entry.file_type()?.is_file() {
+                    return None;
+                }
+                let modified = mtime_nanos(&entry.metadata().ok()?)?;
+                let path = entry.path().strip_prefix(dir).ok()?.to_str()?.to_string();
+                Some((path, modified))
+            })
+            .collect();
+
+        Ok(Self(files))
+    }
+
+    /// Captures the modification times of `files`, the paths relative to `dir`.
+    ///
+    /// The files which no longer exist are skipped.
+    pub fn capture_files(dir: &Path, files: impl IntoIterator<Item = String>) -> Self {
+        let files = files
+            .into_iter()
+            .filter_map(|path| {
+                let modified = mtime_nanos(&std::fs::metadata(dir.join(&path)).ok()?)?;
+                Some((path, modified))
+            })
+            .collect();
+
+        Self(files)
+    }
+
+    /// Returns the path of snapshot file for the cache file `cache_path`.
+    pub fn snapshot_path(cache_path: &Path) -> PathBuf {
+        cache_path.with_extension("mtimes.json")
+    }
+
+    /// Loads the snapshot stored for `cache_path`.
+    pub fn load(cache_path: &Path) -> Option<Self> {
+        let file = std::fs::File::open(Self::snapshot_path(cache_path)).ok()?;
+        serde_json::from_reader(std::io::BufReader::new(file)).ok()
+    }
+
+    /// Writes the snapshot for `cache_path` to the disk.
+    pub fn store(&self, cache_path: &Path) -> Result<()> {
+        utils::create_or_overwrite(
+            Self::snapshot_path(cache_path),
+            serde_json::to_string(self)?.as_bytes(),
+        )
+    }
+
+    /// Returns the changes from `self` to the `latest` snapshot.
+    pub fn diff(&self, latest: &Self) -> SnapshotDiff {
+        let changed = latest
+            .0
+            .iter()
+            .filter(|(path, modified)| self.0.get(*path) != Some(modified))
+            .map(|(path, _)| path.clone())
+            .collect();
+        let removed = self
+            .0
+            .keys()
+            .filter(|path| !latest.0.contains_key(*path))
+            .cloned()
+            .collect();
+        SnapshotDiff { changed, removed }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_diff() {
+        let old = FilesSnapshot(
+            [("a.rs", 1), ("b.rs", 1), ("c.rs", 1)]
+                .into_iter()
+                .map(|(p, t)| (p.to_string(), t))
+                .collect(),
+        );
+        let latest = FilesSnapshot(
+            [("a.rs", 1), ("b.rs", 2), ("d.rs", 1)]
+                .into_iter()
+                .map(|(p, t)| (p.to_string(), t))
+                .collect(),
+        );
+        assert_eq!(
+            old.diff(&latest),
+            SnapshotDiff {
+                changed: vec!["b.rs".to_string(), "d.rs".to_string()],
+                removed: vec!["c.rs".to_string()],
+            }
+        );
+        assert!(latest.diff(&latest).is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_capture() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("src")).unwrap();
+        std::fs::create_dir_all(dir.path().join("target")).unwrap();
+        std::fs::write(dir.path().join(".gitignore"), "ignored.rs\n").unwrap();
+        for file in ["src/lib.rs", "ignored.rs", "target/gen.rs"] {
+            std::fs::write(dir.path().join(file), "").unwrap();
+        }
+
+        let snapshot = FilesSnapshot::capture(dir.path(), &["target".into()]).unwrap();
+        assert_eq!(
+            snapshot.0.keys().collect::<Vec<_>>(),
+            [".gitignore", "ignored.rs", "src/lib.rs"]
+        );
+
+        let snapshot = FilesSnapshot::capture_files(
+            dir.path(),
+            ["src/lib.rs".to_string(), "removed.rs".to_string()],
+        );
+        assert_eq!(snapshot.0.keys().collect::<Vec<_>>(), ["src/lib.rs"]);
+    }
+}
@@ -0,0 +1,125 @@
+use super::ProjectTag;
+use std::collections::HashMap;
+
+/// Wildcard matching the tags of any language in [`KindMap`].
+const ANY_LANGUAGE: &str = "*";
+
+/// Normalizes the inconsistent kind names of ctags parsers across languages,
+/// e.g., `func` in Go and `function` in Rust.
+///
+/// The language is identified by the file extension, e.g., `go`, `rs`.
+#[derive(Debug, Clone, Default)]
+pub struct KindMap {
+    /// (language, kind) => display kind.
+    names: HashMap<(String, String), String>,
+    /// Display kinds in the order of priority, used for grouping the tags.
+    priority: Vec<String>,
+}
+
+impl KindMap {
+    /// Creates an instance of [`KindMap`] with the common normalizations, the types come
+    /// first, then the functions and the variables.
+    pub fn canonical() -> Self {
+        let mut kind_map = Self::default();
+        for (kind, display_kind) in [
+            ("func", "function"),
+            ("member", "method"),
+            ("typedef", "type"),
+            ("const", "constant"),
+            ("var", "variable"),
+        ] {
+            kind_map.insert(ANY_LANGUAGE, kind, display_kind);
+        }
+        kind_map.set_priority(
+            [
+                "class",
+                "struct",
+                "interface",
+                "trait",
+                "enum",
+                "type",
+                "function",
+                "method",
+                "constant",
+                "variable",
+            ]
+            .into_iter()
+            .map(Into::into)
+            .collect(),
+        );
+        kind_map
+    }
+
+    /// Displays `kind` of `language` as `display_kind`, `*` for any language.
+    pub fn insert(
+        &mut self,
+        language: impl Into<String>,
+        kind: impl Into<String>,
+        display_kind: impl Into<String>,
+    ) {
+        self.names
+            .insert((language.into(), kind.into()), display_kind.into());
+    }
+
+    /// Sets the display kinds in the order of priority, the kinds not in the list come last.
+    pub fn set_priority(&mut self, priority: Vec<String>) {
+        self.priority = priority;
+    }
+
+    /// Returns the display kind of `kind`, the language specific one is preferred.
+    pub fn display_kind<'a>(&'a self, language: Option<&str>, kind: &'a str) -> &'a str {
+        language
+            .and_then(|language| self.names.get(&(language.to_string(), kind.to_string())))
+            .or_else(|| {
+                self.names
+                    .get(&(ANY_LANGUAGE.to_string(), kind.to_string()))
+            })
+            .map(String::as_str)
+            .unwrap_or(kind)
+    }
+
+    /// Returns the priority of `display_kind`, the smaller the higher.
+    pub fn priority(&self, display_kind: &str) -> usize {
+        self.priority
+            .iter()
+            .position(|kind| kind == display_kind)
+            .unwrap_or(self.priority.len())
+    }
+
+    /// Groups the `tags` by the priority of display kind, the order within a group is kept.
+    pub fn sort_tags(&self, tags: &mut [ProjectTag]) {
+        tags.sort_by_cached_key(|tag| self.priority(tag.display_kind(self)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kind_map() {
+        let mut kind_map = KindMap::canonical();
+        kind_map.insert("py", "member", "field");
+
+        assert_eq!(kind_map.display_kind(Some("go"), "func"), "function");
+        assert_eq!(kind_map.display_kind(Some("rs"), "function"), "function");
+        assert_eq!(kind_map.display_kind(Some("py"), "member"), "field");
+        assert_eq!(kind_map.display_kind(None, "member"), "method");
+
+        let mut tags = [
+            "x\tsrc/a.go\t/^var x int$/;\"\tvar\tline:1",
+            "foo\tsrc/a.go\t/^func foo() {}$/;\"\tfunc\tline:2",
+            "m\tsrc/a.go\t/^m$/;\"\tmacro\tline:3",
+            "Foo\tsrc/a.go\t/^type Foo struct {}$/;\"\tstruct\tline:4",
+            "bar\tsrc/b.rs\t/^fn bar() {}$/;\"\tfunction\tline:5",
+        ]
+        .map(|line| ProjectTag::from_tab_line(line).unwrap());
+        kind_map.sort_tags(&mut tags);
+        assert_eq!(
+            tags.iter()
+                .map(|tag| tag.display_kind(&kind_map))
+                .collect::<Vec<_>>(),
+            vec!["struct", "function", "function", "variable", "macro"]
+        );
+    }
+}
@@ -1,25 +1,40 @@
 mod buffer_tag;
 mod context_tag;
+mod etags;
+mod files_snapshot;
+mod kind_map;
 mod project_tag;
 
-use crate::process::ShellCommand;
+use crate::process::{shell_quote, ShellCommand};
+use crate::tools::{ctags_executable, ToolError};
 use dirs::Dirs;
+use ignore::overrides::{Override, OverrideBuilder};
 use itertools::Itertools;
+use matcher::{MatchScope, MatcherBuilder, Query};
 use once_cell::sync::Lazy;
 use paths::AbsPathBuf;
 use rayon::prelude::*;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsStr;
 use std::hash::Hash;
-use std::io::{BufRead, BufReader, Error, ErrorKind, Result};
+use std::io::{BufRead, BufReader, Error, ErrorKind, Read, Result};
 use std::ops::Deref;
 use std::path::{Path, PathBuf};
+use std::process::{Child, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 use subprocess::{Exec, NullFile};
+use types::{CaseMatching, ClapItem, FuzzyText, MatchedItem};
 
 pub use self::buffer_tag::{BufferTag, BufferTagItem, Scope};
 pub use self::context_tag::{
     buffer_tag_items, buffer_tags_lines, current_context_tag, current_context_tag_async,
     fetch_buffer_tags,
 };
+pub use self::etags::{to_etags, write_etags};
+pub use self::files_snapshot::{FilesSnapshot, SnapshotDiff};
+pub use self::kind_map::KindMap;
 pub use self::project_tag::{ProjectTag, ProjectTagItem};
 
 pub const EXCLUDE: &str = ".git,*.json,node_modules,target,_build,build,dist";
@@ -40,26 +55,59 @@ pub static CTAGS_TAGS_DIR: Lazy<PathBuf> = Lazy::new(|| {
     tags_dir
 });
 
-pub static CTAGS_EXISTS: Lazy<bool> = Lazy::new(|| {
-    std::process::Command::new("ctags")
+/// Implementation of the ctags executable.
+///
+/// The kind names and the supported options differ a lot between the flavors, only
+/// universal-ctags is fully supported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CtagsFlavor {
+    Universal,
+    Exuberant,
+    /// ctags is found but the flavor is unrecognized.
+    Unknown,
+    /// No ctags executable.
+    NotFound,
+}
+
+impl CtagsFlavor {
+    /// Parses the flavor from the output of `ctags --version`.
+    fn from_version_output(stdout: &str) -> Self {
+        match stdout.lines().next() {
+            Some(line) if line.starts_with("Universal Ctags") => Self::Universal,
+            Some(line) if line.starts_with("Exuberant Ctags") => Self::Exuberant,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+static CTAGS_FLAVOR: Lazy<CtagsFlavor> = Lazy::new(|| {
+    let flavor = std::process::Command::new(ctags_executable())
         .arg("--version")
         .stderr(std::process::Stdio::inherit())
         .output()
-        .ok()
-        .and_then(|output| {
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            stdout
-                .split('\n')
-                .next()
-                .map(|line| line.starts_with("Universal Ctags"))
-        })
-        .unwrap_or(false)
+        .map(|output| CtagsFlavor::from_version_output(&String::from_utf8_lossy(&output.stdout)))
+        .unwrap_or(CtagsFlavor::NotFound);
+
+    if flavor == CtagsFlavor::Exuberant {
+        tracing::warn!(
+            "Exuberant Ctags detected, some features are limited, universal-ctags is recommended"
+        );
+    }
+
+    flavor
 });
 
+/// Returns the flavor of the ctags executable, the detection is only performed once.
+pub fn detect_ctags_flavor() -> CtagsFlavor {
+    *CTAGS_FLAVOR
+}
+
+pub static CTAGS_EXISTS: Lazy<bool> = Lazy::new(|| detect_ctags_flavor() == CtagsFlavor::Universal);
+
 /// If the ctags executable supports `--output-format=json`.
 pub static CTAGS_HAS_JSON_FEATURE: Lazy<bool> = Lazy::new(|| {
     fn detect_json_feature() -> std::io::Result<bool> {
-        let output = std::process::Command::new("ctags")
+        let output = std::process::Command::new(ctags_executable())
             .arg("--list-features")
             .stderr(std::process::Stdio::inherit())
             .output()?;
@@ -67,17 +115,28 @@ pub static CTAGS_HAS_JSON_FEATURE: Lazy<bool> = Lazy::new(|| {
         if stdout.split('\n').any(|x| x.starts_with("json")) {
             Ok(true)
         } else {
-            Err(Error::new(ErrorKind::Other, "ctags has no +json feature"))
+            Err(ToolError::NoJsonFeature.into())
         }
     }
 
     detect_json_feature().unwrap_or(false)
 });
 
+/// Returns an error if the ctags executable is not found or has no `+json` feature.
+pub fn ensure_has_json_support() -> std::result::Result<(), ToolError> {
+    if !*CTAGS_EXISTS {
+        Err(ToolError::CtagsMissing)
+    } else if !*CTAGS_HAS_JSON_FEATURE {
+        Err(ToolError::NoJsonFeature)
+    } else {
+        Ok(())
+    }
+}
+
 /// Used to specify the language when working with `readtags`.
 static LANG_MAPS: Lazy<HashMap<String, String>> = Lazy::new(|| {
     fn generate_lang_maps() -> Result<HashMap<String, String>> {
-        let output = std::process::Command::new("ctags")
+        let output = std::process::Command::new(ctags_executable())
             .arg("--list-maps")
             .stderr(std::process::Stdio::inherit())
             .output()?;
@@ -172,32 +231,46 @@ impl<'a, P: AsRef<Path> + Hash> TagsGenerator<'a, P> {
         tags_path
     }
 
-    /// Executes the command to generate the tags file.
-    pub fn generate_tags(&self) -> Result<()> {
+    /// Returns the arguments of ctags for generating the tags file.
+    ///
+    /// The arguments are passed to ctags directly without a shell, no quoting is needed.
+    fn ctags_args(&self) -> Vec<String> {
         // TODO: detect the languages by dir if not explicitly specified?
-        let languages_opt = self
+        let mut args = self
             .languages
-            .as_ref()
+            .iter()
             .map(|language| format!("--languages={language}"))
-            .unwrap_or_default();
+            .collect::<Vec<_>>();
 
-        let mut cmd = format!(
-            "ctags {} --kinds-all='{}' --fields='{}' --extras='{}' {} -f '{}' -R",
-            languages_opt,
-            self.kinds_all,
-            self.fields,
-            self.extras,
-            self.exclude_opt,
-            self.tags_path().display()
-        );
+        args.extend([
+            format!("--kinds-all={}", self.kinds_all),
+            format!("--fields={}", self.fields),
+            format!("--extras={}", self.extras),
+        ]);
+        args.extend(self.exclude_opt.split_whitespace().map(Into::into));
+        args.extend([
+            "-f".into(),
+            self.tags_path().display().to_string(),
+            "-R".into(),
+        ]);
 
         // pass the input files.
-        if !self.files.is_empty() {
-            cmd.push(' ');
-            cmd.push_str(&self.files.iter().map(|f| f.display()).join(" "));
-        }
+        args.extend(self.files.iter().map(|f| f.display().to_string()));
 
-        let exit_status = Exec::shell(&cmd)
+        args
+    }
+
+    /// Returns the command line of [`Self::generate_tags`] without executing it.
+    pub fn command_string(&self) -> String {
+        let mut cmd = std::process::Command::new(ctags_executable());
+        cmd.args(self.ctags_args()).current_dir(self.dir.as_ref());
+        crate::process::command_string(&cmd)
+    }
+
+    /// Executes the command to generate the tags file.
+    pub fn generate_tags(&self) -> Result<()> {
+        let exit_status = Exec::cmd(ctags_executable())
+            .args(&self.ctags_args())
             .stderr(NullFile) // ignore the line: ctags: warning...
             .cwd(self.dir.as_ref())
             .join()
@@ -211,119 +284,805 @@ impl<'a, P: AsRef<Path> + Hash> TagsGenerator<'a, P> {
     }
 }
 
+/// Returns the long name of a ctags kind letter, the long name is returned as is.
+///
+/// The kind letters are language-specific in universal-ctags, only the common ones are
+/// mapped here so that the kinds can be specified interchangeably:
+///
+/// | Letter | Long name   |
+/// | ------ | ----------- |
+/// | `c`    | class       |
+/// | `d`    | macro       |
+/// | `e`    | enumerator  |
+/// | `f`    | function    |
+/// | `g`    | enum        |
+/// | `i`    | interface   |
+/// | `m`    | method      |
+/// | `n`    | namespace   |
+/// | `p`    | prototype   |
+/// | `s`    | struct      |
+/// | `t`    | typedef     |
+/// | `u`    | union       |
+/// | `v`    | variable    |
+pub fn kind_long_name(kind: &str) -> &str {
+    match kind {
+        "c" => "class",
+        "d" => "macro",
+        "e" => "enumerator",
+        "f" => "function",
+        "g" => "enum",
+        "i" => "interface",
+        "m" => "method",
+        "n" => "namespace",
+        "p" => "prototype",
+        "s" => "struct",
+        "t" => "typedef",
+        "u" => "union",
+        "v" => "variable",
+        long_name => long_name,
+    }
+}
+
+/// Parses a line of ctags output, returns `None` if the kind of tag is not in `kinds`.
+fn parse_tag(line: &str, kinds: Option<&HashSet<String>>) -> Option<ProjectTag> {
+    ProjectTag::from_line(line).filter(|tag| {
+        kinds
+            .map(|kinds| kinds.contains(kind_long_name(tag.kind())))
+            .unwrap_or(true)
+    })
+}
+
+/// Parses the tags in the output of ctags.
+///
+/// Returns an error if none of the lines can be parsed while all kinds are wanted.
+fn parse_tags(stdout: &[u8], kinds: Option<&HashSet<String>>) -> Result<Vec<ProjectTag>> {
+    let tags = stdout
+        .par_split(|x| x == &b'\n')
+        .filter_map(|tag| {
+            std::str::from_utf8(tag)
+                .ok()
+                .and_then(|tag| parse_tag(tag, kinds))
+        })
+        .collect::<Vec<_>>();
+
+    if tags.is_empty() && kinds.is_none() {
+        let unparsed_line = stdout
+            .split(|x| x == &b'\n')
+            .map(String::from_utf8_lossy)
+            .find(|line| !line.trim().is_empty() && !line.starts_with("!_TAG_"));
+        if let Some(line) = unparsed_line {
+            return Err(ToolError::ParseFailed {
+                program: ctags_executable().into(),
+                line: line.into_owned(),
+            }
+            .into());
+        }
+    }
+
+    Ok(tags)
+}
+
+/// Executes `cmd` and collects its output, the error of spawning `cmd` is
+/// converted to [`ToolError::SpawnFailed`].
+fn spawn_output(cmd: &mut std::process::Command) -> Result<std::process::Output> {
+    cmd.output().map_err(|source| {
+        ToolError::SpawnFailed {
+            program: cmd.get_program().to_string_lossy().into_owned(),
+            source,
+        }
+        .into()
+    })
+}
+
+/// Formatted tag line in the ctags cache, only the tag name is matched.
+#[derive(Debug)]
+struct CachedTagLine {
+    line: String,
+    name_end: usize,
+}
+
+impl ClapItem for CachedTagLine {
+    fn raw_text(&self) -> &str {
+        &self.line
+    }
+
+    fn match_text(&self) -> &str {
+        &self.line[..self.name_end]
+    }
+
+    fn fuzzy_text(&self, _match_scope: MatchScope) -> Option<FuzzyText> {
+        Some(FuzzyText::new(self.match_text(), 0))
+    }
+}
+
+/// Where the ctags output comes from.
+#[derive(Debug)]
+enum TagsSource {
+    /// Run the ctags executable and read its stdout.
+    Command(std::process::Command),
+    /// Read the tags file generated out-of-band.
+    File(PathBuf),
+}
+
+/// Returns true if the path of `tag` is not filtered out by `path_filter`.
+fn is_path_accepted(path_filter: Option<&Override>, tag: &ProjectTag) -> bool {
+    path_filter
+        .map(|path_filter| !path_filter.matched(tag.path(), false).is_ignore())
+        .unwrap_or(true)
+}
+
+/// Returns the stdout of ctags, or an error carrying the stderr if ctags failed.
+///
+/// ctags may exit successfully with nothing in stdout, e.g., a language parser is missing,
+/// which is also treated as an error if anything is written to stderr.
+fn ctags_stdout(program: &OsStr, output: std::process::Output) -> Result<Vec<u8>> {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let stderr = stderr.trim();
+    if !output.status.success() || (output.stdout.is_empty() && !stderr.is_empty()) {
+        return Err(ToolError::ExecutionFailed {
+            program: program.to_string_lossy().into_owned(),
+            status: output.status,
+            stderr: stderr.to_string(),
+        }
+        .into());
+    }
+    Ok(output.stdout)
+}
+
+/// Returns a new command with the same program, args and working directory as `std_cmd`.
+fn copy_command(std_cmd: &std::process::Command) -> std::process::Command {
+    let mut cmd = std::process::Command::new(std_cmd.get_program());
+    cmd.args(std_cmd.get_args());
+    if let Some(dir) = std_cmd.get_current_dir() {
+        cmd.current_dir(dir);
+    }
+    cmd
+}
+
+/// Extra fields of the tags, passed to ctags as `--fields` and `--extras`.
+///
+/// Used by [`ProjectCtagsCommand::for_git_tracked`] and [`ProjectCtagsCommand::for_file`],
+/// the default fields populate the scope, signature and access of [`ProjectTag`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagFields {
+    /// Value of `--fields`, `+nksSa` by default:
+    ///
+    /// - `n`: line number.
+    /// - `k`: kind of tag.
+    /// - `s`: scope of tag, e.g., the struct a method belongs to.
+    /// - `S`: signature of the function.
+    /// - `a`: access of the class member, e.g., `public`.
+    pub fields: String,
+    /// Value of `--extras`, e.g., `+q` for the extra tags qualified by the scope,
+    /// the default of ctags is used if empty.
+    pub extras: String,
+}
+
+impl Default for TagFields {
+    fn default() -> Self {
+        Self {
+            fields: "+nksSa".into(),
+            extras: String::new(),
+        }
+    }
+}
+
+impl TagFields {
+    fn args(&self) -> Vec<String> {
+        let mut args = vec![format!("--fields={}", self.fields)];
+        if !self.extras.is_empty() {
+            args.push(format!("--extras={}", self.extras));
+        }
+        args
+    }
+}
+
 #[derive(Debug)]
 pub struct ProjectCtagsCommand {
-    std_cmd: std::process::Command,
+    source: TagsSource,
     shell_cmd: ShellCommand,
+    /// Extra fields passed to ctags, `None` for the fields in [`Self::tags_cmd`] only.
+    tag_fields: Option<TagFields>,
+    /// Only the tags of these kinds are kept, `None` for all kinds.
+    kinds: Option<HashSet<String>>,
+    /// Whether to sort the formatted tags and drop the duplicate ones.
+    sorted: bool,
+    /// Only the tags in the files matching these globs are kept, `None` for all files.
+    path_filter: Option<Override>,
 }
 
 impl ProjectCtagsCommand {
     pub const TAGS_CMD: &'static [&'static str] =
         &["ctags", "-R", "-x", "--output-format=json", "--fields=+n"];
 
+    /// Number of tags between two progress reports in [`Self::create_cache`].
+    pub const PROGRESS_INTERVAL: usize = 1000;
+
+    /// Interval of checking the cancellation while ctags is running in [`Self::create_cache`].
+    const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
     const BASE_TAGS_CMD: &'static str = "ctags -R -x --output-format=json --fields=+n";
 
+    /// Used when ctags is not compiled with `+json`, the tags are written to stdout
+    /// in the classic tab-separated format.
+    pub const LEGACY_TAGS_CMD: &'static [&'static str] = &["ctags", "-R", "-f", "-", "--fields=+n"];
+
+    const BASE_LEGACY_TAGS_CMD: &'static str = "ctags -R -f - --fields=+n";
+
+    /// Returns the ctags command arguments supported by the ctags executable.
+    pub fn tags_cmd() -> &'static [&'static str] {
+        if *CTAGS_HAS_JSON_FEATURE {
+            Self::TAGS_CMD
+        } else {
+            Self::LEGACY_TAGS_CMD
+        }
+    }
+
     /// Creates an instance of [`ProjectCtagsCommand`].
     pub fn new(std_cmd: std::process::Command, shell_cmd: ShellCommand) -> Self {
-        Self { std_cmd, shell_cmd }
+        Self {
+            source: TagsSource::Command(std_cmd),
+            shell_cmd,
+            tag_fields: None,
+            kinds: None,
+            sorted: false,
+            path_filter: None,
+        }
     }
 
     pub fn with_cwd(cwd: PathBuf) -> Self {
-        let mut std_cmd = std::process::Command::new(Self::TAGS_CMD[0]);
-        std_cmd.current_dir(&cwd).args(&Self::TAGS_CMD[1..]).args(
+        let tags_cmd = Self::tags_cmd();
+        let mut std_cmd = std::process::Command::new(ctags_executable());
+        std_cmd.current_dir(&cwd).args(&tags_cmd[1..]).args(
             EXCLUDE
                 .split(',')
                 .map(|exclude| format!("--exclude={exclude}")),
         );
+        let base_tags_cmd = if *CTAGS_HAS_JSON_FEATURE {
+            Self::BASE_TAGS_CMD
+        } else {
+            Self::BASE_LEGACY_TAGS_CMD
+        };
         let shell_cmd = ShellCommand::new(
-            format!("{} {}", Self::BASE_TAGS_CMD, DEFAULT_EXCLUDE_OPT.deref()),
+            format!("{base_tags_cmd} {}", DEFAULT_EXCLUDE_OPT.deref()),
             cwd,
         );
         Self::new(std_cmd, shell_cmd)
     }
 
+    /// Creates an instance of [`ProjectCtagsCommand`] which only tags `file`, e.g., for
+    /// the document symbols.
+    ///
+    /// The tags are kept in the source order and never cached, use
+    /// [`Self::formatted_tags_stream`] instead of the `create_cache*` methods.
+    pub fn for_file(file: PathBuf, tag_fields: &TagFields) -> Self {
+        let dir = file.parent().map(Path::to_path_buf).unwrap_or_default();
+        let mut std_cmd = std::process::Command::new(ctags_executable());
+        std_cmd
+            .args(Self::tags_cmd()[1..].iter().filter(|arg| **arg != "-R"))
+            .args(tag_fields.args())
+            .arg("--sort=no")
+            .arg(&file);
+        let shell_cmd = ShellCommand::new(crate::process::command_string(&std_cmd), dir);
+        let mut ctags_cmd = Self::new(std_cmd, shell_cmd);
+        ctags_cmd.tag_fields = Some(tag_fields.clone());
+        ctags_cmd
+    }
+
+    /// Creates an instance of [`ProjectCtagsCommand`] which only indexes the files
+    /// tracked by git, i.e., `git ls-files | ctags -L -`.
+    ///
+    /// Falls back to [`Self::with_cwd`] if `cwd` is not a git repo.
+    pub fn for_git_tracked(cwd: PathBuf, tag_fields: &TagFields) -> Self {
+        let mut ctags_cmd = if utils::is_git_repo(&cwd) {
+            let command = Self::git_tracked_command(&[], Some(tag_fields));
+            let mut std_cmd = crate::process::shell_command(&command);
+            std_cmd.current_dir(&cwd);
+            let shell_cmd = ShellCommand::new(command, cwd);
+            Self::new(std_cmd, shell_cmd)
+        } else {
+            let mut ctags_cmd = Self::with_cwd(cwd);
+            if let TagsSource::Command(std_cmd) = &mut ctags_cmd.source {
+                std_cmd.args(tag_fields.args());
+            }
+            for arg in tag_fields.args() {
+                ctags_cmd.shell_cmd.command.push(' ');
+                ctags_cmd.shell_cmd.command.push_str(&shell_quote(&arg));
+            }
+            ctags_cmd
+        };
+        ctags_cmd.tag_fields = Some(tag_fields.clone());
+        ctags_cmd
+    }
+
+    const GIT_LS_FILES: &'static str = "git ls-files";
+
+    /// Returns the shell command indexing the git-tracked files matching `pathspecs`.
+    fn git_tracked_command(pathspecs: &[String], tag_fields: Option<&TagFields>) -> String {
+        let pathspecs = if pathspecs.is_empty() {
+            String::new()
+        } else {
+            format!(
+                " -- {}",
+                pathspecs.iter().map(|spec| shell_quote(spec)).join(" ")
+            )
+        };
+        format!(
+            "{}{pathspecs} | {} -L - {}",
+            Self::GIT_LS_FILES,
+            shell_quote(ctags_executable()),
+            Self::git_tracked_ctags_args(tag_fields).join(" ")
+        )
+    }
+
+    /// Returns the ctags args of [`Self::git_tracked_command`], excluding the list of files.
+    fn git_tracked_ctags_args(tag_fields: Option<&TagFields>) -> Vec<String> {
+        Self::tags_cmd()[1..]
+            .iter()
+            .filter(|arg| **arg != "-R")
+            .map(|arg| arg.to_string())
+            .chain(tag_fields.map(TagFields::args).unwrap_or_default())
+            .chain(EXCLUDE.split(',').map(|x| format!("--exclude={x}")))
+            .collect()
+    }
+
+    /// Returns true if only the files tracked by git are indexed.
+    fn is_git_tracked(&self) -> bool {
+        matches!(self.source, TagsSource::Command(_))
+            && self.shell_cmd.command.starts_with(Self::GIT_LS_FILES)
+    }
+
+    /// Creates an instance of [`ProjectCtagsCommand`] reading the existing `tags_file`
+    /// instead of running ctags.
+    ///
+    /// Both the JSON and the legacy tab-separated format are supported.
+    pub fn from_tags_file(tags_file: PathBuf) -> Self {
+        let dir = tags_file
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        let shell_cmd = ShellCommand::new(
+            format!("cat {}", shell_quote(&tags_file.to_string_lossy())),
+            dir,
+        );
+        Self {
+            source: TagsSource::File(tags_file),
+            shell_cmd,
+            tag_fields: None,
+            kinds: None,
+            sorted: false,
+            path_filter: None,
+        }
+    }
+
+    /// Keeps only the tags of given `kinds`, all kinds are kept if `kinds` is empty.
+    ///
+    /// Both the kind letters and the long names are accepted, see [`kind_long_name`].
+    pub fn set_kinds(&mut self, kinds: Vec<String>) {
+        if kinds.is_empty() {
+            self.kinds = None;
+            return;
+        }
+
+        let kinds = kinds
+            .iter()
+            .map(|kind| kind_long_name(kind).to_string())
+            .collect::<HashSet<_>>();
+
+        // The cache is scoped to the kinds.
+        self.shell_cmd
+            .command
+            .push_str(&format!(" --kinds={}", kinds.iter().sorted().join(",")));
+
+        self.kinds = Some(kinds);
+    }
+
+    /// Keeps only the tags in the files matching any glob in `include` and none in `exclude`,
+    /// the globs are relative to the working directory, e.g., `src/**`, `**/tests/**`.
+    ///
+    /// The globs are applied to ctags itself where possible so that the excluded files are
+    /// not parsed at all, i.e., the pathspecs of `git ls-files` for the git-tracked files
+    /// and `--exclude` for the recursive mode, and then to the path of each tag.
+    pub fn set_globs(&mut self, include: &[String], exclude: &[String]) -> Result<()> {
+        if include.is_empty() && exclude.is_empty() {
+            return Ok(());
+        }
+
+        let invalid_glob = |e: ignore::Error| Error::new(ErrorKind::InvalidInput, e.to_string());
+        let mut overrides = OverrideBuilder::new(&self.shell_cmd.dir);
+        for glob in include {
+            overrides.add(glob).map_err(invalid_glob)?;
+        }
+        for glob in exclude {
+            overrides.add(&format!("!{glob}")).map_err(invalid_glob)?;
+        }
+        self.path_filter = Some(overrides.build().map_err(invalid_glob)?);
+
+        let is_git_tracked = self.is_git_tracked();
+        if let TagsSource::Command(std_cmd) = &mut self.source {
+            if is_git_tracked {
+                let pathspecs = include
+                    .iter()
+                    .map(|glob| format!(":(glob){glob}"))
+                    .chain(exclude.iter().map(|glob| format!(":(glob,exclude){glob}")))
+                    .collect::<Vec<_>>();
+                let mut git_tracked_cmd = crate::process::shell_command(
+                    &Self::git_tracked_command(&pathspecs, self.tag_fields.as_ref()),
+                );
+                git_tracked_cmd.current_dir(&self.shell_cmd.dir);
+                *std_cmd = git_tracked_cmd;
+            } else {
+                std_cmd.args(exclude.iter().map(|glob| format!("--exclude={glob}")));
+            }
+        }
+
+        // The cache is scoped to the globs.
+        self.shell_cmd.command.push_str(&format!(
+            " --include={} --exclude={}",
+            include.join(","),
+            exclude.join(",")
+        ));
+
+        Ok(())
+    }
+
+    /// Sorts the formatted tags by name, path and line, the duplicate lines are removed.
+    ///
+    /// The tags are streamed in the order of ctags output by default.
+    pub fn set_sorted(&mut self, sorted: bool) {
+        if sorted && !self.sorted {
+            // The cache is scoped to the sorting.
+            self.shell_cmd.command.push_str(" --sort=yes");
+        }
+        self.sorted = sorted;
+    }
+
     /// Parallel version of [`formatted_lines`].
     pub fn par_formatted_lines(&mut self) -> Result<Vec<String>> {
-        self.std_cmd.output().map(|output| {
-            output
-                .stdout
-                .par_split(|x| x == &b'\n')
-                .filter_map(|tag| {
-                    if let Ok(tag) = serde_json::from_slice::<ProjectTag>(tag) {
-                        Some(tag.format_proj_tag())
-                    } else {
-                        None
-                    }
-                })
-                .collect::<Vec<_>>()
-        })
+        let stdout = self.stdout()?;
+        let path_filter = self.path_filter.as_ref();
+        let tags = parse_tags(&stdout, self.kinds.as_ref())?
+            .into_iter()
+            .filter(|tag| is_path_accepted(path_filter, tag))
+            .collect::<Vec<_>>();
+
+        if self.sorted {
+            return Ok(project_tag::format_sorted(tags));
+        }
+
+        Ok(tags
+            .into_par_iter()
+            .map(|tag| tag.format_proj_tag(false))
+            .collect())
+    }
+
+    /// Returns the program producing the tags, or the tags file if any.
+    fn program(&self) -> String {
+        match &self.source {
+            TagsSource::Command(std_cmd) => std_cmd.get_program().to_string_lossy().into_owned(),
+            TagsSource::File(path) => path.display().to_string(),
+        }
+    }
+
+    /// Returns the command line producing the tags without executing it.
+    pub fn command_string(&self) -> String {
+        match &self.source {
+            TagsSource::Command(std_cmd) => crate::process::command_string(std_cmd),
+            TagsSource::File(_) => self.shell_cmd.command.clone(),
+        }
     }
 
     pub fn stdout(&mut self) -> Result<Vec<u8>> {
-        let stdout = self.std_cmd.output()?.stdout;
-        Ok(stdout)
+        self.read_stdout()
+    }
+
+    /// Returns the whole ctags output, or an error if ctags failed.
+    fn read_stdout(&self) -> Result<Vec<u8>> {
+        match &self.source {
+            TagsSource::Command(std_cmd) => {
+                let mut cmd = copy_command(std_cmd);
+                let output = spawn_output(&mut cmd)?;
+                ctags_stdout(cmd.get_program(), output)
+            }
+            TagsSource::File(tags_file) => std::fs::read(tags_file),
+        }
     }
 
     /// Returns an iterator of raw line of ctags output.
+    ///
+    /// ctags is run to completion beforehand, so that a failed ctags is reported as an
+    /// error instead of a truncated or empty list of lines.
     pub fn lines(&self) -> Result<impl Iterator<Item = String>> {
-        let exec_cmd = Exec::cmd(self.std_cmd.get_program())
-            .args(self.std_cmd.get_args().collect::<Vec<_>>().as_slice());
-        Ok(BufReader::new(
-            exec_cmd
-                .stream_stdout()
-                .map_err(|err| Error::new(ErrorKind::Other, err.to_string()))?,
-        )
-        .lines()
-        .map_while(Result::ok))
+        let stdout = self.read_stdout()?;
+        Ok(std::io::Cursor::new(stdout).lines().map_while(Result::ok))
     }
 
-    /// Returns an iterator of tag line in a formatted form.
-    fn formatted_tags_iter(&self) -> Result<impl Iterator<Item = String>> {
-        Ok(self.lines()?.filter_map(|tag| {
-            if let Ok(tag) = serde_json::from_str::<ProjectTag>(&tag) {
-                Some(tag.format_proj_tag())
-            } else {
-                None
-            }
-        }))
+    /// Returns an iterator of the formatted tags in the order of the ctags output.
+    pub fn formatted_tags_stream(&self) -> Result<impl Iterator<Item = String>> {
+        Ok(self.tags_iter()?.map(|tag| tag.format_proj_tag(false)))
     }
 
     pub fn tag_item_iter(&self) -> Result<impl Iterator<Item = ProjectTagItem>> {
-        Ok(self.lines()?.filter_map(|tag| {
-            if let Ok(tag) = serde_json::from_str::<ProjectTag>(&tag) {
-                Some(tag.into_project_tag_item())
-            } else {
-                None
-            }
+        Ok(self.tags_iter()?.map(ProjectTag::into_project_tag_item))
+    }
+
+    /// Returns an iterator of the parsed tags filtered by the kinds and the globs.
+    fn tags_iter(&self) -> Result<impl Iterator<Item = ProjectTag>> {
+        let kinds = self.kinds.clone();
+        let path_filter = self.path_filter.clone();
+        Ok(self.lines()?.filter_map(move |tag| {
+            parse_tag(&tag, kinds.as_ref())
+                .filter(|tag| is_path_accepted(path_filter.as_ref(), tag))
         }))
     }
 
+    /// Returns the `--exclude` patterns passed to ctags.
+    fn exclude_patterns(&self) -> Vec<String> {
+        match &self.source {
+            TagsSource::Command(std_cmd) => std_cmd
+                .get_args()
+                .filter_map(|arg| arg.to_str()?.strip_prefix("--exclude=").map(Into::into))
+                .collect(),
+            TagsSource::File(_) => Vec::new(),
+        }
+    }
+
+    /// Captures the files indexed by ctags, `None` if the tags are read from a tags file.
+    fn files_snapshot(&self) -> Option<FilesSnapshot> {
+        self.capture_files_snapshot().ok().flatten()
+    }
+
+    /// Captures the files indexed by ctags, i.e., the files listed by `git ls-files` for
+    /// the git-tracked files, otherwise all the files walked by `ctags -R`.
+    fn capture_files_snapshot(&self) -> Result<Option<FilesSnapshot>> {
+        match self.source {
+            TagsSource::Command(_) if self.is_git_tracked() => {
+                let mut cmd = std::process::Command::new("git");
+                cmd.args(["ls-files", "-z"])
+                    .current_dir(&self.shell_cmd.dir);
+                let output = spawn_output(&mut cmd)?;
+                if !output.status.success() {
+                    return Err(ToolError::ExecutionFailed {
+                        program: "git".into(),
+                        status: output.status,
+                        stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+                    }
+                    .into());
+                }
+                let files = output
+                    .stdout
+                    .split(|b| *b == 0)
+                    .filter(|file| !file.is_empty())
+                    .map(|file| String::from_utf8_lossy(file).into_owned());
+                Ok(Some(FilesSnapshot::capture_files(
+                    &self.shell_cmd.dir,
+                    files,
+                )))
+            }
+            TagsSource::Command(_) => {
+                FilesSnapshot::capture(&self.shell_cmd.dir, &self.exclude_patterns()).map(Some)
+            }
+            TagsSource::File(_) => Ok(None),
+        }
+    }
+
+    /// Returns the command tagging `files` only, with the same ctags executable and args as
+    /// the full run, `None` if the tags are read from a tags file.
+    fn incremental_command(&self, files: &[String]) -> Option<std::process::Command> {
+        let TagsSource::Command(std_cmd) = &self.source else {
+            return None;
+        };
+        let mut cmd = if self.is_git_tracked() {
+            let mut cmd = std::process::Command::new(ctags_executable());
+            cmd.args(Self::git_tracked_ctags_args(self.tag_fields.as_ref()))
+                .current_dir(&self.shell_cmd.dir);
+            cmd
+        } else {
+            let mut cmd = std::process::Command::new(std_cmd.get_program());
+            cmd.args(std_cmd.get_args().filter(|arg| *arg != "-R"));
+            cmd.current_dir(
+                std_cmd
+                    .get_current_dir()
+                    .unwrap_or(self.shell_cmd.dir.as_path()),
+            );
+            cmd
+        };
+        cmd.args(files);
+        Some(cmd)
+    }
+
     /// Returns a tuple of (total, cache_path) if the cache exists.
+    ///
+    /// The cache is considered outdated if any indexed file has been changed since. Note
+    /// that checking it stats all the indexed files, i.e., walks the whole tree or runs
+    /// `git ls-files`, use it when deciding whether to refresh the cache only.
     pub fn ctags_cache(&self) -> Option<(usize, PathBuf)> {
-        self.shell_cmd
-            .cache_digest()
-            .map(|digest| (digest.total, digest.cached_path))
+        let digest = self.shell_cmd.cache_digest()?;
+
+        if let Some(snapshot) = FilesSnapshot::load(&digest.cached_path) {
+            if self
+                .files_snapshot()
+                .map(|latest| !snapshot.diff(&latest).is_empty())
+                .unwrap_or(false)
+            {
+                return None;
+            }
+        }
+
+        Some((digest.total, digest.cached_path))
+    }
+
+    /// Stores the files snapshot taken before running ctags for the cache.
+    fn store_files_snapshot(snapshot: Option<FilesSnapshot>, cache_path: &Path) {
+        if let Some(snapshot) = snapshot {
+            if let Err(e) = snapshot.store(cache_path) {
+                tracing::error!(error = ?e, "Failed to store the ctags files snapshot");
+            }
+        }
+    }
+
+    /// Spawns the ctags process or opens the tags file, returns the reader of the tags
+    /// and the ctags process if any, whose stderr is piped.
+    fn spawn_reader(&self) -> Result<(Box<dyn Read + Send>, Option<Child>)> {
+        match &self.source {
+            TagsSource::Command(std_cmd) => {
+                let mut cmd = copy_command(std_cmd);
+                cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+                let mut child = cmd.spawn().map_err(|source| ToolError::SpawnFailed {
+                    program: std_cmd.get_program().to_string_lossy().into_owned(),
+                    source,
+                })?;
+                let stdout = child.stdout.take().expect("stdout is piped; qed");
+                Ok((Box::new(stdout), Some(child)))
+            }
+            TagsSource::File(tags_file) => Ok((Box::new(std::fs::File::open(tags_file)?), None)),
+        }
+    }
+
+    /// Waits for `child` to exit, kills it as soon as `cancelled` is set.
+    ///
+    /// Returns the exit status of `child`, `None` if it's killed.
+    fn kill_on_cancel(mut child: Child, cancelled: &AtomicBool) -> Result<Option<ExitStatus>> {
+        loop {
+            if cancelled.load(Ordering::SeqCst) {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Ok(None);
+            }
+            match child.try_wait()? {
+                Some(exit_status) => return Ok(Some(exit_status)),
+                None => std::thread::sleep(Self::CANCEL_POLL_INTERVAL),
+            }
+        }
     }
 
     /// Runs the command and writes the cache to the disk.
-    #[allow(unused)]
-    fn create_cache(&self) -> Result<(usize, PathBuf)> {
-        let mut total = 0usize;
-        let mut formatted_tags_iter = self.formatted_tags_iter()?.inspect(|_x| {
-            total += 1;
-        });
-        let lines = formatted_tags_iter.join("\n");
+    ///
+    /// `progress` is called with the number of tags processed so far every
+    /// [`Self::PROGRESS_INTERVAL`] tags.
+    ///
+    /// Once `cancelled` is set, the ctags process is killed and [`ToolError::Cancelled`]
+    /// is returned. The cache is written only after all the tags are collected and ctags
+    /// exits successfully, hence nothing is persisted on cancellation or failure.
+    pub fn create_cache(
+        &self,
+        progress: Option<&mut dyn FnMut(usize)>,
+        cancelled: &AtomicBool,
+    ) -> Result<(usize, PathBuf)> {
+        self.create_cache_with_stats(progress, cancelled)
+            .map(|(total, cache_path, _kind_counts)| (total, cache_path))
+    }
+
+    /// Same as [`Self::create_cache`], but also returns the number of tags per kind,
+    /// e.g., `{"function": 420, "struct": 88}`.
+    ///
+    /// The kinds are counted before deduplicating the sorted tags.
+    pub fn create_cache_with_stats(
+        &self,
+        progress: Option<&mut dyn FnMut(usize)>,
+        cancelled: &AtomicBool,
+    ) -> Result<(usize, PathBuf, HashMap<String, usize>)> {
+        let snapshot = self.files_snapshot();
 
+        let (tags, kind_counts) = self.collect_tags(progress, cancelled)?;
+
+        let lines = if self.sorted {
+            project_tag::format_sorted(tags)
+        } else {
+            tags.into_iter()
+                .map(|tag| tag.format_proj_tag(false))
+                .collect()
+        };
+
+        let total = lines.len();
         let cache_path = self
             .shell_cmd
             .clone()
-            .write_cache(total, lines.as_bytes())?;
+            .write_cache(total, lines.join("\n").as_bytes())?;
 
-        Ok((total, cache_path))
+        Self::store_files_snapshot(snapshot, &cache_path);
+
+        Ok((total, cache_path, kind_counts))
+    }
+
+    /// Collects the tags and the number of tags per kind for [`Self::create_cache_with_stats`].
+    fn collect_tags(
+        &self,
+        mut progress: Option<&mut dyn FnMut(usize)>,
+        cancelled: &AtomicBool,
+    ) -> Result<(Vec<ProjectTag>, HashMap<String, usize>)> {
+        let (reader, child) = self.spawn_reader()?;
+
+        let (tags, kind_counts, exit) = std::thread::scope(|s| {
+            let exit = child.map(|mut child| {
+                // Drain stderr in the background, otherwise ctags may block on a full pipe.
+                let stderr = child.stderr.take();
+                let stderr = s.spawn(move || {
+                    let mut buf = Vec::new();
+                    if let Some(mut stderr) = stderr {
+                        let _ = stderr.read_to_end(&mut buf);
+                    }
+                    String::from_utf8_lossy(&buf).trim().to_string()
+                });
+                let exit_status = s.spawn(|| Self::kill_on_cancel(child, cancelled));
+                (exit_status, stderr)
+            });
+
+            let mut tags = Vec::new();
+            let mut kind_counts = HashMap::new();
+            // The reader is dropped once the loop ends, which also stops ctags if the
+            // output is not consumed to the end.
+            for line in BufReader::new(reader).lines().map_while(Result::ok) {
+                if cancelled.load(Ordering::SeqCst) {
+                    break;
+                }
+                let Some(tag) = parse_tag(&line, self.kinds.as_ref())
+                    .filter(|tag| is_path_accepted(self.path_filter.as_ref(), tag))
+                else {
+                    continue;
+                };
+                *kind_counts
+                    .entry(kind_long_name(tag.kind()).to_string())
+                    .or_insert(0) += 1;
+                tags.push(tag);
+                if tags.len() % Self::PROGRESS_INTERVAL == 0 {
+                    if let Some(progress) = progress.as_mut() {
+                        progress(tags.len());
+                    }
+                }
+            }
+
+            let exit = exit.map(|(exit_status, stderr)| {
+                (
+                    exit_status.join().unwrap_or(Ok(None)),
+                    stderr.join().unwrap_or_default(),
+                )
+            });
+            (tags, kind_counts, exit)
+        });
+
+        if cancelled.load(Ordering::SeqCst) {
+            return Err(ToolError::Cancelled.into());
+        }
+
+        if let Some((exit_status, stderr)) = exit {
+            if let Some(status) = exit_status?.filter(|status| !status.success()) {
+                return Err(ToolError::ExecutionFailed {
+                    program: self.program(),
+                    status,
+                    stderr,
+                }
+                .into());
+            }
+        }
+
+        Ok((tags, kind_counts))
     }
 
     /// Parallel version of `create_cache`.
     pub fn par_create_cache(&mut self) -> Result<(usize, PathBuf)> {
+        let snapshot = self.files_snapshot();
+
         // TODO: do not store all the output in memory and redirect them to a file directly.
         let lines = self.par_formatted_lines()?;
         let total = lines.len();
@@ -334,9 +1093,115 @@ impl ProjectCtagsCommand {
             .clone()
             .write_cache(total, lines.as_bytes())?;
 
+        Self::store_files_snapshot(snapshot, &cache_path);
+
         Ok((total, cache_path))
     }
 
+    /// Updates the cache by only re-tagging the files modified since the last run,
+    /// the tags of deleted files are purged from the cache.
+    ///
+    /// Falls back to [`Self::par_create_cache`] if no previous cache is available
+    /// or the tags are sorted.
+    pub fn create_cache_incremental(&mut self) -> Result<(usize, PathBuf)> {
+        if matches!(self.source, TagsSource::File(_)) || self.sorted {
+            return self.par_create_cache();
+        }
+
+        let cache_path = self.shell_cmd.cache_file_path()?;
+        let Some(snapshot) = FilesSnapshot::load(&cache_path).filter(|_| cache_path.exists())
+        else {
+            return self.par_create_cache();
+        };
+
+        let Some(latest) = self.capture_files_snapshot()? else {
+            return self.par_create_cache();
+        };
+        let diff = snapshot.diff(&latest);
+
+        let stale_files = diff
+            .changed
+            .iter()
+            .chain(diff.removed.iter())
+            .map(String::as_str)
+            .collect::<HashSet<_>>();
+
+        let mut lines = utils::read_lines(&cache_path)?
+            .map_while(Result::ok)
+            .filter(|line| {
+                pattern::extract_proj_tags(line)
+                    .map(|(_, path)| !stale_files.contains(path))
+                    .unwrap_or(false)
+            })
+            .collect::<Vec<_>>();
+
+        if !diff.changed.is_empty() {
+            // Tag the changed files only.
+            let mut cmd = self
+                .incremental_command(&diff.changed)
+                .expect("tags are generated by ctags; qed");
+            let output = spawn_output(&mut cmd)?;
+            let tags = parse_tags(
+                &ctags_stdout(cmd.get_program(), output)?,
+                self.kinds.as_ref(),
+            )?;
+            let path_filter = self.path_filter.as_ref();
+            lines.extend(
+                tags.into_par_iter()
+                    .filter(|tag| is_path_accepted(path_filter, tag))
+                    .map(|tag| tag.format_proj_tag(false))
+                    .collect::<Vec<_>>(),
+            );
+        }
+
+        let total = lines.len();
+        let cache_path = self
+            .shell_cmd
+            .clone()
+            .write_cache(total, lines.join("\n").as_bytes())?;
+
+        Self::store_files_snapshot(Some(latest), &cache_path);
+
+        Ok((total, cache_path))
+    }
+
+    /// Filters the lines in the ctags cache by matching `query` against the tag name only,
+    /// the matched items are sorted by the rank in descending order.
+    ///
+    /// Returns an error if the cache does not exist, whether the cache is outdated is not
+    /// checked as it's too expensive for every query.
+    pub fn filter_cached(
+        &self,
+        query: &str,
+        case_matching: CaseMatching,
+    ) -> Result<Vec<MatchedItem>> {
+        let cache_path = self
+            .shell_cmd
+            .cache_digest()
+            .map(|digest| digest.cached_path)
+            .ok_or_else(|| Error::new(ErrorKind::NotFound, "ctags cache not found"))?;
+
+        let matcher = MatcherBuilder::new()
+            .match_scope(MatchScope::TagName)
+            .case_matching(case_matching)
+            .build(Query::from(query));
+
+        let mut matched_items = utils::read_lines(cache_path)?
+            .map_while(Result::ok)
+            .filter_map(|line| {
+                let name_end = pattern::extract_proj_tags_name(&line)?.len();
+                Some(CachedTagLine { line, name_end })
+            })
+            .collect::<Vec<_>>()
+            .into_par_iter()
+            .filter_map(|item| matcher.match_item(Arc::new(item)))
+            .collect::<Vec<_>>();
+
+        matched_items.par_sort_unstable_by(|a, b| b.rank.cmp(&a.rank));
+
+        Ok(matched_items)
+    }
+
     pub async fn execute_and_write_cache(mut self) -> Result<Vec<String>> {
         let lines = self.par_formatted_lines()?;
 
@@ -364,3 +1229,380 @@ pub fn trim_pattern(pattern: &str) -> &str {
 
     pattern.trim()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_ctags_stdout() {
+        use std::os::unix::process::ExitStatusExt;
+        use std::process::{ExitStatus, Output};
+
+        let output = |code, stdout: &[u8], stderr: &[u8]| Output {
+            status: ExitStatus::from_raw(code << 8),
+            stdout: stdout.to_vec(),
+            stderr: stderr.to_vec(),
+        };
+
+        let program = OsStr::new("ctags");
+        assert_eq!(
+            ctags_stdout(program, output(0, b"tag", b"")).unwrap(),
+            b"tag"
+        );
+        assert_eq!(ctags_stdout(program, output(0, b"", b"")).unwrap(), b"");
+        assert_eq!(
+            ctags_stdout(program, output(0, b"tag", b"warning")).unwrap(),
+            b"tag"
+        );
+
+        let err = ctags_stdout(program, output(1, b"", b"ctags: Unknown language\n")).unwrap_err();
+        assert!(err.to_string().ends_with("ctags: Unknown language"));
+        assert!(matches!(
+            ToolError::from_io_error(&err),
+            Some(ToolError::ExecutionFailed { .. })
+        ));
+        assert!(ctags_stdout(program, output(0, b"", b"ctags: Unknown language")).is_err());
+    }
+
+    #[test]
+    fn test_ctags_flavor() {
+        let universal = "Universal Ctags 6.0.0, Copyright (C) 2015-2022 Universal Ctags Team\n";
+        assert_eq!(
+            CtagsFlavor::from_version_output(universal),
+            CtagsFlavor::Universal
+        );
+        let exuberant = "Exuberant Ctags 5.8, Copyright (C) 1996-2009 Darren Hiebert\n";
+        assert_eq!(
+            CtagsFlavor::from_version_output(exuberant),
+            CtagsFlavor::Exuberant
+        );
+        assert_eq!(
+            CtagsFlavor::from_version_output("ctags (GNU Emacs 29.1)\n"),
+            CtagsFlavor::Unknown
+        );
+    }
+
+    #[test]
+    fn test_set_globs() {
+        let dir = std::env::temp_dir();
+        let shell_cmd = ShellCommand::new("ctags -R".into(), dir.clone());
+        let mut ctags_cmd =
+            ProjectCtagsCommand::new(std::process::Command::new(ctags_executable()), shell_cmd);
+        ctags_cmd
+            .set_globs(&["src/**".into()], &["**/tests/**".into()])
+            .unwrap();
+
+        assert_eq!(ctags_cmd.exclude_patterns(), vec!["**/tests/**"]);
+        assert!(ctags_cmd
+            .shell_cmd
+            .command
+            .ends_with(" --include=src/** --exclude=**/tests/**"));
+
+        let tag = |path: &str| {
+            ProjectTag::from_tab_line(&format!(
+                "foo\t{path}\t/^fn foo() {{}}$/;\"\tfunction\tline:1"
+            ))
+            .unwrap()
+        };
+        let path_filter = ctags_cmd.path_filter.as_ref();
+        assert!(is_path_accepted(path_filter, &tag("src/lib.rs")));
+        assert!(!is_path_accepted(path_filter, &tag("src/tests/foo.rs")));
+        assert!(!is_path_accepted(path_filter, &tag("benches/foo.rs")));
+
+        assert!(ctags_cmd.set_globs(&["src/{a".into()], &[]).is_err());
+    }
+
+    #[test]
+    fn test_parse_tags() {
+        let stdout = b"!_TAG_FILE_FORMAT\t2\t/extended format/\nExec\tsrc/exec.rs\t/^pub struct Exec {$/;\"\tstruct\tline:10\n";
+        assert_eq!(parse_tags(stdout, None).unwrap().len(), 1);
+
+        let kinds = HashSet::from(["function".to_string()]);
+        assert!(parse_tags(stdout, Some(&kinds)).unwrap().is_empty());
+        assert!(parse_tags(b"", None).unwrap().is_empty());
+
+        let err = parse_tags(b"ctags: Warning: garbage\n", None).unwrap_err();
+        assert!(matches!(
+            ToolError::from_io_error(&err),
+            Some(ToolError::ParseFailed { .. })
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_create_cache_cancelled() {
+        let dir = tempfile::tempdir().unwrap();
+
+        // A fake ctags emitting a tag every 10ms endlessly.
+        let mut std_cmd = std::process::Command::new("sh");
+        std_cmd.args([
+            "-c",
+            r#"while true; do printf 'Exec\tsrc/exec.rs\t/^pub struct Exec {$/;"\tstruct\tline:10\n'; sleep 0.01; done"#,
+        ]);
+        let shell_cmd = ShellCommand::new("test_create_cache_cancelled".into(), dir.path().into());
+        let cache_path = shell_cmd.cache_file_path().unwrap();
+        let ctags_cmd = ProjectCtagsCommand::new(std_cmd, shell_cmd);
+
+        let cancelled = AtomicBool::new(false);
+        let err = std::thread::scope(|s| {
+            s.spawn(|| {
+                std::thread::sleep(Duration::from_millis(200));
+                cancelled.store(true, Ordering::SeqCst);
+            });
+            ctags_cmd.create_cache(None, &cancelled)
+        })
+        .unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::Interrupted);
+        assert!(matches!(
+            ToolError::from_io_error(&err),
+            Some(ToolError::Cancelled)
+        ));
+        assert!(!cache_path.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_create_cache_failed() {
+        let dir = tempfile::tempdir().unwrap();
+
+        // A fake ctags printing a tag and then failing.
+        let mut std_cmd = std::process::Command::new("sh");
+        std_cmd.args([
+            "-c",
+            r#"printf 'Exec\tsrc/exec.rs\t/^pub struct Exec {$/;"\tstruct\tline:10\n'; echo 'ctags: Unknown language' >&2; exit 1"#,
+        ]);
+        let shell_cmd = ShellCommand::new("test_create_cache_failed".into(), dir.path().into());
+        let cache_path = shell_cmd.cache_file_path().unwrap();
+        let ctags_cmd = ProjectCtagsCommand::new(std_cmd, shell_cmd);
+
+        let err = ctags_cmd
+            .create_cache(None, &AtomicBool::new(false))
+            .unwrap_err();
+        match ToolError::from_io_error(&err) {
+            Some(ToolError::ExecutionFailed { status, stderr, .. }) => {
+                assert_eq!(status.code(), Some(1));
+                assert_eq!(stderr, "ctags: Unknown language");
+            }
+            _ => panic!("unexpected error: {err:?}"),
+        }
+        assert!(!cache_path.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_tags_stream_failed() {
+        let new_ctags_cmd = || {
+            let mut std_cmd = std::process::Command::new("sh");
+            std_cmd.args([
+                "-c",
+                r#"printf 'Exec\tsrc/exec.rs\t/^pub struct Exec {$/;"\tstruct\tline:10\n'; echo 'ctags: Unknown language' >&2; exit 1"#,
+            ]);
+            let shell_cmd =
+                ShellCommand::new("test_tags_stream_failed".into(), std::env::temp_dir());
+            ProjectCtagsCommand::new(std_cmd, shell_cmd)
+        };
+
+        let is_execution_failed = |err: &Error| {
+            matches!(
+                ToolError::from_io_error(err),
+                Some(ToolError::ExecutionFailed { stderr, .. }) if stderr == "ctags: Unknown language"
+            )
+        };
+
+        assert!(is_execution_failed(&new_ctags_cmd().stdout().unwrap_err()));
+        assert!(new_ctags_cmd()
+            .lines()
+            .is_err_and(|e| is_execution_failed(&e)));
+        assert!(new_ctags_cmd()
+            .formatted_tags_stream()
+            .is_err_and(|e| is_execution_failed(&e)));
+        assert!(new_ctags_cmd()
+            .tag_item_iter()
+            .is_err_and(|e| is_execution_failed(&e)));
+    }
+
+    #[test]
+    fn test_collect_tags() {
+        let dir = tempfile::tempdir().unwrap();
+        let tags_file = dir.path().join("tags");
+        std::fs::write(
+            &tags_file,
+            "!_TAG_FILE_FORMAT\t2\t/extended format/\n\
+            Exec\tsrc/exec.rs\t/^pub struct Exec {$/;\"\tstruct\tline:10\n\
+            new\tsrc/exec.rs\t/^    pub fn new() {$/;\"\tf\tline:20\n\
+            run\tsrc/exec.rs\t/^    pub fn run() {$/;\"\tfunction\tline:30\n",
+        )
+        .unwrap();
+
+        let ctags_cmd = ProjectCtagsCommand::from_tags_file(tags_file);
+        let (tags, kind_counts) = ctags_cmd
+            .collect_tags(None, &AtomicBool::new(false))
+            .unwrap();
+
+        assert_eq!(tags.len(), 3);
+        assert_eq!(
+            kind_counts,
+            HashMap::from([("struct".to_string(), 1), ("function".to_string(), 2)])
+        );
+    }
+
+    #[test]
+    fn test_incremental_command() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut std_cmd = std::process::Command::new("/opt/bin/ctags");
+        std_cmd
+            .current_dir(dir.path())
+            .args(["-R", "-x", "--output-format=json", "--fields=+nksSa"])
+            .args(["--exclude=target", "--languages=Rust"]);
+        let shell_cmd =
+            ShellCommand::new(crate::process::command_string(&std_cmd), dir.path().into());
+        let ctags_cmd = ProjectCtagsCommand::new(std_cmd, shell_cmd);
+
+        let cmd = ctags_cmd
+            .incremental_command(&["src/lib.rs".into(), "src/main.rs".into()])
+            .unwrap();
+        assert_eq!(cmd.get_program(), "/opt/bin/ctags");
+        assert_eq!(
+            cmd.get_args().collect::<Vec<_>>(),
+            [
+                "-x",
+                "--output-format=json",
+                "--fields=+nksSa",
+                "--exclude=target",
+                "--languages=Rust",
+                "src/lib.rs",
+                "src/main.rs"
+            ]
+        );
+        assert_eq!(cmd.get_current_dir(), Some(dir.path()));
+
+        let ctags_cmd = ProjectCtagsCommand::with_cwd(dir.path().into());
+        let cmd = ctags_cmd
+            .incremental_command(&["src/lib.rs".into()])
+            .unwrap();
+        assert_eq!(cmd.get_program(), ctags_executable());
+
+        let ctags_cmd = ProjectCtagsCommand::from_tags_file(dir.path().join("tags"));
+        assert!(ctags_cmd
+            .incremental_command(&["src/lib.rs".into()])
+            .is_none());
+    }
+
+    #[test]
+    fn test_incremental_command_tag_fields() {
+        let dir = tempfile::tempdir().unwrap();
+        let tag_fields = TagFields {
+            fields: "+nks".into(),
+            extras: "+q".into(),
+        };
+        let has_tag_fields = |cmd: std::process::Command| {
+            let args = cmd.get_args().collect::<Vec<_>>();
+            args.contains(&OsStr::new("--fields=+nks")) && args.contains(&OsStr::new("--extras=+q"))
+        };
+
+        // Not a git repo, falls back to `ctags -R`.
+        let ctags_cmd = ProjectCtagsCommand::for_git_tracked(dir.path().into(), &tag_fields);
+        assert!(!ctags_cmd.is_git_tracked());
+        assert!(has_tag_fields(
+            ctags_cmd
+                .incremental_command(&["src/lib.rs".into()])
+                .unwrap()
+        ));
+
+        let command = ProjectCtagsCommand::git_tracked_command(&[], Some(&tag_fields));
+        let mut std_cmd = crate::process::shell_command(&command);
+        std_cmd.current_dir(dir.path());
+        let mut ctags_cmd =
+            ProjectCtagsCommand::new(std_cmd, ShellCommand::new(command, dir.path().into()));
+        ctags_cmd.tag_fields = Some(tag_fields);
+        assert!(ctags_cmd.is_git_tracked());
+        let cmd = ctags_cmd
+            .incremental_command(&["src/lib.rs".into()])
+            .unwrap();
+        assert_eq!(cmd.get_program(), ctags_executable());
+        assert!(has_tag_fields(cmd));
+    }
+
+    #[test]
+    fn test_tag_fields() {
+        assert_eq!(TagFields::default().args(), ["--fields=+nksSa"]);
+
+        let tag_fields = TagFields {
+            fields: "+nks".into(),
+            extras: "+q".into(),
+        };
+        assert_eq!(tag_fields.args(), ["--fields=+nks", "--extras=+q"]);
+
+        let command = ProjectCtagsCommand::git_tracked_command(&[], Some(&tag_fields));
+        assert!(command.contains(" --fields=+nks --extras=+q --exclude="));
+        let command = ProjectCtagsCommand::git_tracked_command(&[], None);
+        assert!(!command.contains("--extras"));
+    }
+
+    #[test]
+    fn test_for_file() {
+        if !*CTAGS_EXISTS {
+            return;
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("lib.rs");
+        std::fs::write(&file, "fn zeta() {}\nfn alpha() {}\nstruct Beta;\n").unwrap();
+
+        let ctags_cmd = ProjectCtagsCommand::for_file(file, &TagFields::default());
+        let names = ctags_cmd
+            .formatted_tags_stream()
+            .unwrap()
+            .filter_map(|line| Some(pattern::extract_proj_tags_name(&line)?.to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["zeta", "alpha", "Beta"]);
+        assert!(ctags_cmd.ctags_cache().is_none());
+    }
+
+    #[test]
+    fn test_ctags_args() {
+        let mut tags_generator = TagsGenerator::with_dir("/tmp/My Project");
+        tags_generator.set_languages("Rust".into());
+        let args = tags_generator.ctags_args();
+        assert_eq!(
+            &args[..4],
+            [
+                "--languages=Rust",
+                "--kinds-all=*",
+                "--fields=*",
+                "--extras=*"
+            ]
+        );
+        assert!(args.contains(&"--exclude=node_modules".to_string()));
+        assert_eq!(args.last().map(String::as_str), Some("-R"));
+
+        let command = tags_generator.command_string();
+        assert!(
+            command.starts_with("cd '/tmp/My Project' && ctags --languages=Rust '--kinds-all=*'")
+        );
+    }
+
+    #[test]
+    fn test_cached_tag_line_matches_name_only() {
+        let matcher = MatcherBuilder::new()
+            .match_scope(MatchScope::TagName)
+            .build(Query::from("'lib"));
+        let item = |line: &str| {
+            let name_end = pattern::extract_proj_tags_name(line).unwrap().len();
+            Arc::new(CachedTagLine {
+                line: line.to_string(),
+                name_end,
+            })
+        };
+
+        let line = "new:42                         [method@src/lib.rs]            fn new() {";
+        assert!(matcher.match_item(item(line)).is_none());
+
+        let line = "load_lib:7                     [function@src/main.rs]         fn load_lib() {";
+        let matched_item = matcher.match_item(item(line)).unwrap();
+        assert_eq!(matched_item.indices, vec![5, 6, 7]);
+    }
+}
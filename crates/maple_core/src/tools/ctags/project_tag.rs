@@ -1,6 +1,9 @@
+use super::KindMap;
 use matcher::MatchScope;
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
+use std::path::Path;
 use types::{ClapItem, FuzzyText};
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
@@ -10,13 +13,264 @@ pub struct ProjectTag {
     pattern: String,
     line: usize,
     kind: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    scope: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    signature: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    access: Option<String>,
 }
 
 impl ProjectTag {
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn kind(&self) -> &str {
+        &self.kind
+    }
+
+    /// Returns the access modifier of the symbol, e.g., `public`, `private`.
+    pub fn access(&self) -> Option<&str> {
+        self.access.as_deref()
+    }
+
+    /// Parses a line of ctags output, the JSON format is tried first and then
+    /// the legacy tab-separated format.
+    pub fn from_line(line: &str) -> Option<Self> {
+        serde_json::from_str(line)
+            .ok()
+            .or_else(|| Self::from_tab_line(line))
+    }
+
+    /// Parses a line in the classic tab-separated tags file format.
+    ///
+    /// name<TAB>path<TAB>pattern;"<TAB>kind<TAB>line:N<TAB>[scope_kind:scope]...
+    pub fn from_tab_line(line: &str) -> Option<Self> {
+        // Pseudo tags, e.g., `!_TAG_FILE_FORMAT`.
+        if line.starts_with("!_TAG_") {
+            return None;
+        }
+
+        let (name, rest) = line.split_once('\t')?;
+        let (path, rest) = rest.split_once('\t')?;
+
+        // The pattern itself may contain tabs, the extension fields start after the last `;"`.
+        let (pattern, extension_fields) = match rest.rsplit_once(";\"") {
+            Some((pattern, fields)) => (pattern, fields),
+            None => (rest, ""),
+        };
+
+        let mut tag = Self {
+            name: name.to_string(),
+            path: path.to_string(),
+            pattern: pattern.to_string(),
+            line: pattern.parse().unwrap_or_default(),
+            kind: String::new(),
+            scope: None,
+            signature: None,
+            access: None,
+        };
+
+        for field in extension_fields.split('\t').filter(|f| !f.is_empty()) {
+            match field.split_once(':') {
+                Some(("line", v)) => tag.line = v.parse().ok()?,
+                Some(("kind", v)) => tag.kind = v.to_string(),
+                Some(("signature", v)) => tag.signature = Some(v.to_string()),
+                Some(("access", v)) => tag.access = Some(v.to_string()),
+                Some((
+                    "language" | "roles" | "file" | "end" | "typeref" | "inherits" | "extras"
+                    | "nth" | "properties" | "template",
+                    _,
+                )) => {}
+                // `scope_kind:scope`, e.g., `struct:Foo`.
+                Some((_scope_kind, scope)) => {
+                    if tag.scope.is_none() {
+                        tag.scope = Some(scope.to_string());
+                    }
+                }
+                // The bare field without the key is the kind.
+                None => tag.kind = field.to_string(),
+            }
+        }
+
+        Some(tag)
+    }
+
+    /// Returns the jump target `(line, column)` of the symbol.
+    ///
+    /// The column is the 0-based byte offset of `name` in the source line described
+    /// by `pattern`, defaulting to 0 if not found.
+    pub fn jump_target(&self) -> (usize, usize) {
+        (self.line, self.column())
+    }
+
+    /// Same as [`Self::jump_target`], but the column is in UTF-16 code units as LSP expects.
+    pub fn jump_target_utf16(&self) -> (usize, usize) {
+        let column = utils::byte_col_to_utf16(&self.source_line(), self.column());
+        (self.line, column)
+    }
+
+    /// Returns the current 1-based line number of the symbol in `file_contents`, which
+    /// may have been edited since the tags were generated.
+    ///
+    /// The line matching `pattern` closest to the indexed `line` is picked, the indexed
+    /// `line` is returned if the pattern is not found, `None` if `line` is unknown too.
+    pub fn resolve_line(&self, file_contents: &str) -> Option<usize> {
+        let indexed_line = (self.line > 0).then_some(self.line);
+
+        // The pattern is the line number itself in the legacy tags, e.g., `42`.
+        if self.pattern.parse::<usize>().is_ok() {
+            return indexed_line;
+        }
+
+        let source_line = self.source_line();
+        let anchored_start = self.pattern.starts_with("/^");
+        // ctags omits `$` if the source line is truncated.
+        let anchored_end = self.pattern.ends_with("$/");
+        let is_match = |line: &str| match (anchored_start, anchored_end) {
+            (true, true) => line == source_line,
+            (true, false) => line.starts_with(&source_line),
+            (false, true) => line.ends_with(&source_line),
+            (false, false) => line.contains(&source_line),
+        };
+
+        file_contents
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| is_match(line))
+            .map(|(idx, _)| idx + 1)
+            .min_by_key(|line_number| line_number.abs_diff(self.line))
+            .or(indexed_line)
+    }
+
+    /// Returns the source line described by `pattern`.
+    fn source_line(&self) -> String {
+        // /^pattern$/
+        let pattern = self
+            .pattern
+            .strip_prefix("/^")
+            .or_else(|| self.pattern.strip_prefix('/'))
+            .unwrap_or(&self.pattern);
+        let pattern = pattern
+            .strip_suffix("$/")
+            .or_else(|| pattern.strip_suffix('/'))
+            .unwrap_or(pattern);
+
+        // ctags escapes `/` and `\` in the pattern.
+        let mut source_line = String::with_capacity(pattern.len());
+        let mut chars = pattern.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => source_line.extend(chars.next()),
+                c => source_line.push(c),
+            }
+        }
+        source_line
+    }
+
+    fn column(&self) -> usize {
+        let source_line = self.source_line();
+
+        let is_word_char = |c: char| c.is_alphanumeric() || c == '_';
+
+        source_line
+            .match_indices(&self.name)
+            .find(|(idx, _)| {
+                let before = source_line[..*idx].chars().next_back();
+                let after = source_line[idx + self.name.len()..].chars().next();
+                !before.map(is_word_char).unwrap_or(false)
+                    && !after.map(is_word_char).unwrap_or(false)
+            })
+            .map(|(idx, _)| idx)
+            .unwrap_or(0)
+    }
+
+    /// Returns the record of tag in the Emacs `TAGS` file, without the trailing newline.
+    ///
+    /// The record is `text\x7fname\x01line,offset`, `text` is the source line up to the
+    /// end of `name`. The byte offset of line is unknown and left empty, Emacs locates
+    /// the tag by the line number then.
+    pub fn to_etags_entry(&self) -> String {
+        let source_line = self.source_line();
+        let column = self.column();
+        let text = source_line
+            .get(..column + self.name.len())
+            .filter(|text| text.ends_with(&self.name))
+            .unwrap_or(&source_line);
+        format!("{text}\x7f{}\x01{},", self.name, self.line)
+    }
+
+    /// Returns the symbol name qualified with its scope and signature if any.
+    ///
+    /// `foo` => `Scope::foo(x: i32) -> bool`
+    fn qualified_name(&self) -> String {
+        let mut qualified_name = match self.scope {
+            Some(ref scope) => format!("{scope}::{}", self.name),
+            None => self.name.clone(),
+        };
+        if let Some(ref signature) = self.signature {
+            qualified_name.push_str(signature);
+        }
+        qualified_name
+    }
+
+    /// Returns the kind of tag normalized by `kind_map`.
+    pub fn display_kind<'a>(&'a self, kind_map: &'a KindMap) -> &'a str {
+        let language = Path::new(&self.path)
+            .extension()
+            .and_then(|ext| ext.to_str());
+        kind_map.display_kind(language, &self.kind)
+    }
+
     /// Builds the line for displaying the tag info.
-    pub fn format_proj_tag(&self) -> String {
-        let name_lnum = format!("{}:{}", self.name, self.line);
-        let kind = format!("[{}@{}]", self.kind, self.path);
+    ///
+    /// The kind-specific icon is prepended if `enable_icon` is true.
+    pub fn format_proj_tag(&self, enable_icon: bool) -> String {
+        self.format_with_kind(enable_icon, &self.kind, None)
+    }
+
+    /// Same as [`Self::format_proj_tag`], but the kind is normalized by `kind_map`.
+    pub fn format_proj_tag_with(&self, enable_icon: bool, kind_map: &KindMap) -> String {
+        self.format_with_kind(enable_icon, self.display_kind(kind_map), None)
+    }
+
+    /// Same as [`Self::format_proj_tag`], but the path is truncated to fit in
+    /// `max_path_width` if specified, the full path is kept otherwise.
+    pub fn format_proj_tag_with_path_width(
+        &self,
+        enable_icon: bool,
+        max_path_width: Option<usize>,
+    ) -> String {
+        self.format_with_kind(enable_icon, &self.kind, max_path_width)
+    }
+
+    /// Returns the path truncated to at most `max_path_width` if possible.
+    ///
+    /// The home directory is folded to `~` and the middle components are replaced
+    /// with `...` when the path is too long.
+    fn display_path(&self, max_path_width: Option<usize>) -> Cow<'_, str> {
+        match max_path_width {
+            Some(max_len) if self.path.len() > max_len => {
+                paths::truncate_absolute_path(&self.path, max_len)
+            }
+            _ => Cow::Borrowed(&self.path),
+        }
+    }
+
+    fn format_with_kind(
+        &self,
+        enable_icon: bool,
+        kind: &str,
+        max_path_width: Option<usize>,
+    ) -> String {
+        let name_lnum = if enable_icon {
+            let icon = icon::kind_icon(kind).unwrap_or(icon::DEFAULT_ICON);
+            format!("{icon} {}:{}", self.qualified_name(), self.line)
+        } else {
+            format!("{}:{}", self.qualified_name(), self.line)
+        };
+        let kind = format!("[{kind}@{}]", self.display_path(max_path_width));
         let pattern = super::trim_pattern(&self.pattern);
         format!(
             "{text:<text_width$} {kind:<kind_width$} {pattern}",
@@ -28,7 +282,7 @@ impl ProjectTag {
     }
 
     pub fn into_project_tag_item(self) -> ProjectTagItem {
-        let output_text = self.format_proj_tag();
+        let output_text = self.format_proj_tag(false);
         ProjectTagItem {
             name: self.name,
             kind: self.kind,
@@ -37,6 +291,25 @@ impl ProjectTag {
     }
 }
 
+/// Formats the `tags` sorted by name, path and line, the duplicate lines are removed.
+pub(super) fn format_sorted(tags: Vec<ProjectTag>) -> Vec<String> {
+    let mut tags = tags
+        .into_par_iter()
+        .map(|tag| {
+            let line = tag.format_proj_tag(false);
+            (tag, line)
+        })
+        .collect::<Vec<_>>();
+
+    tags.par_sort_unstable_by(|(a, a_line), (b, b_line)| {
+        (&a.name, &a.path, a.line, a_line).cmp(&(&b.name, &b.path, b.line, b_line))
+    });
+
+    let mut lines = tags.into_iter().map(|(_, line)| line).collect::<Vec<_>>();
+    lines.dedup();
+    lines
+}
+
 #[derive(Debug)]
 pub struct ProjectTagItem {
     pub name: String,
@@ -77,8 +350,152 @@ mod tests {
                 path: "crates/maple_cli/src/cmd/exec.rs".into(),
                 pattern: "/^pub struct Exec {$/".into(),
                 line: 10,
-                kind: "struct".into()
+                kind: "struct".into(),
+                scope: None,
+                signature: None,
+                access: None,
             }
         );
     }
+
+    #[test]
+    fn test_parse_tab_line() {
+        let line =
+            "Exec\tcrates/maple_cli/src/cmd/exec.rs\t/^pub struct Exec {$/;\"\tstruct\tline:10";
+        let json_line = r#"{"_type": "tag", "name": "Exec", "path": "crates/maple_cli/src/cmd/exec.rs", "pattern": "/^pub struct Exec {$/", "line": 10, "kind": "struct"}"#;
+        assert_eq!(
+            ProjectTag::from_line(line).unwrap(),
+            ProjectTag::from_line(json_line).unwrap()
+        );
+
+        let line = "with_dir\tsrc/mod.rs\t/^    pub fn with_dir(dir: P) -> Self {$/;\"\tmethod\tline:150\timplementation:TagsGenerator";
+        let tag = ProjectTag::from_line(line).unwrap();
+        assert_eq!(tag.line, 150);
+        assert_eq!(tag.kind, "method");
+        assert_eq!(tag.scope.as_deref(), Some("TagsGenerator"));
+
+        assert!(ProjectTag::from_line("!_TAG_FILE_FORMAT\t2\t/extended format/").is_none());
+        assert!(
+            ProjectTag::from_line(r#"{"_type": "ptag", "name": "JSON_OUTPUT_VERSION"}"#).is_none()
+        );
+
+        // `;"` inside the pattern.
+        let line = "SEP\tsrc/lib.rs\t/^const SEP: &str = \";\";$/;\"\tconstant\tline:3";
+        let tag = ProjectTag::from_tab_line(line).unwrap();
+        assert_eq!(tag.pattern, "/^const SEP: &str = \";\";$/");
+        assert_eq!(tag.kind, "constant");
+        assert_eq!(tag.line, 3);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_format_proj_tag_with_path_width() {
+        let line = "main\t/media/xlc/src/github.com/vim-clap/crates/cli/src/main.rs\t/^fn main() {$/;\"\tfunction\tline:1";
+        let tag = ProjectTag::from_tab_line(line).unwrap();
+        assert_eq!(
+            tag.format_proj_tag_with_path_width(false, None),
+            tag.format_proj_tag(false)
+        );
+
+        let formatted = tag.format_proj_tag_with_path_width(false, Some(40));
+        let path = formatted
+            .split_once("[function@")
+            .and_then(|(_, rest)| rest.split_once(']'))
+            .map(|(path, _)| path)
+            .unwrap();
+        assert!(path.len() <= 40);
+        assert!(path.starts_with("/media/xlc/..."));
+        assert!(path.ends_with("src/main.rs"));
+    }
+
+    #[test]
+    fn test_jump_target() {
+        let line =
+            "new\tsrc/lib.rs\t/^    let p = \"\\/\"; fn renew() { new() }$/;\"\tfunction\tline:7";
+        let tag = ProjectTag::from_tab_line(line).unwrap();
+        assert_eq!(tag.jump_target(), (7, 30));
+
+        let line = "missing\tsrc/lib.rs\t/^fn foo() {}$/;\"\tfunction\tline:1";
+        let tag = ProjectTag::from_tab_line(line).unwrap();
+        assert_eq!(tag.jump_target(), (1, 0));
+
+        let line = "new\tsrc/lib.rs\t/^    let 🦀 = new();$/;\"\tfunction\tline:3";
+        let tag = ProjectTag::from_tab_line(line).unwrap();
+        assert_eq!(tag.jump_target(), (3, 15));
+        assert_eq!(tag.jump_target_utf16(), (3, 13));
+    }
+
+    #[test]
+    fn test_resolve_line() {
+        let line = "foo\tsrc/lib.rs\t/^fn foo() {}$/;\"\tfunction\tline:2";
+        let tag = ProjectTag::from_tab_line(line).unwrap();
+
+        // Two lines were inserted above since indexing.
+        let contents = "use std::io;\n\n// foo\nfn foo() {}\n";
+        assert_eq!(tag.resolve_line(contents), Some(4));
+
+        // The closest one to the indexed line is picked.
+        let contents = "\n\nfn foo() {}\n\n\n\nfn foo() {}\n";
+        assert_eq!(tag.resolve_line(contents), Some(3));
+
+        // Not an exact match of the anchored pattern.
+        let contents = "pub fn foo() {}\n";
+        assert_eq!(tag.resolve_line(contents), Some(2));
+
+        let line = "foo\tsrc/lib.rs\t/^fn foo(a: usize,/;\"\tfunction\tline:1";
+        let tag = ProjectTag::from_tab_line(line).unwrap();
+        assert_eq!(
+            tag.resolve_line("\r\nfn foo(a: usize, b: usize) {}\r\n"),
+            Some(2)
+        );
+    }
+
+    #[test]
+    fn test_format_proj_tag_with_scope_and_signature() {
+        let data = r#"{"_type": "tag", "name": "new", "path": "src/lib.rs", "pattern": "/^    pub fn new(x: i32) -> bool {$/", "line": 42, "kind": "method", "scope": "Foo", "scopeKind": "implementation", "signature": "(x: i32) -> bool", "access": "public"}"#;
+        let tag: ProjectTag = serde_json::from_str(data).unwrap();
+        assert_eq!(tag.scope.as_deref(), Some("Foo"));
+        assert_eq!(tag.access.as_deref(), Some("public"));
+        let formatted = tag.format_proj_tag(false);
+        assert!(formatted.starts_with("Foo::new(x: i32) -> bool:42 "));
+        assert!(formatted.contains("[method@src/lib.rs]"));
+        assert!(formatted.ends_with("pub fn new(x: i32) -> bool {"));
+
+        let mut kind_map = KindMap::default();
+        kind_map.insert("rs", "method", "function");
+        assert!(tag
+            .format_proj_tag_with(false, &kind_map)
+            .contains("[function@src/lib.rs]"));
+
+        let iconized = tag.format_proj_tag(true);
+        let icon = icon::kind_icon("method").unwrap_or(icon::DEFAULT_ICON);
+        assert!(iconized.starts_with(&format!("{icon} Foo::new(x: i32) -> bool:42")));
+    }
+
+    #[test]
+    fn test_format_sorted() {
+        let tags = [
+            "foo\tsrc/b.rs\t/^fn foo() {}$/;\"\tfunction\tline:3",
+            "bar\tsrc/a.rs\t/^fn bar() {}$/;\"\tfunction\tline:9",
+            "foo\tsrc/a.rs\t/^fn foo() {}$/;\"\tfunction\tline:20",
+            "foo\tsrc/a.rs\t/^fn foo() {}$/;\"\tfunction\tline:4",
+            "bar\tsrc/a.rs\t/^fn bar() {}$/;\"\tfunction\tline:9",
+        ]
+        .into_iter()
+        .map(|line| ProjectTag::from_tab_line(line).unwrap())
+        .collect::<Vec<_>>();
+
+        let names_lines = format_sorted(tags)
+            .iter()
+            .map(|line| line.split_whitespace().next().unwrap().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(names_lines, vec!["bar:9", "foo:4", "foo:20", "foo:3"]);
+    }
+
+    #[test]
+    fn test_to_etags_entry() {
+        let line = "new\tsrc/lib.rs\t/^    pub fn new() -> Self {$/;\"\tmethod\tline:42";
+        let tag = ProjectTag::from_tab_line(line).unwrap();
+        assert_eq!(tag.to_etags_entry(), "    pub fn new\x7fnew\x0142,");
+    }
 }
@@ -1,6 +1,12 @@
+use crate::process::ShellCommand;
+use crate::tools::ToolError;
 use dirs::Dirs;
+use itertools::Itertools;
 use once_cell::sync::Lazy;
-use std::path::PathBuf;
+use rayon::prelude::*;
+use std::io::{BufRead, Cursor, Result};
+use std::path::{Path, PathBuf, MAIN_SEPARATOR};
+use std::process::Command;
 
 pub static GTAGS_EXISTS: Lazy<bool> = Lazy::new(|| gtags_executable_exists().unwrap_or(false));
 
@@ -28,3 +34,229 @@ fn gtags_executable_exists() -> std::io::Result<bool> {
         ))
     }
 }
+
+/// Returns the directory for GTAGS, GRTAGS, GPATH of `project_root`, e.g.,
+///
+/// `~/.local/share/vimclap/gtags/project_root`
+pub fn gtags_db_path(project_root: &Path) -> PathBuf {
+    let mut db_path = GTAGS_DIR.to_path_buf();
+    db_path.push(
+        project_root
+            .display()
+            .to_string()
+            .replace(MAIN_SEPARATOR, "_"),
+    );
+    db_path
+}
+
+/// Tag parsed from the output of `global -x`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GtagsTag {
+    pub name: String,
+    pub line: usize,
+    pub path: String,
+    pub pattern: String,
+}
+
+impl GtagsTag {
+    /// Parses the line of `global -x` output.
+    ///
+    /// run               101 crates/maple_cli/src/app.rs pub async fn run(self) -> Result<()> {
+    pub fn from_global_line(line: &str) -> Option<Self> {
+        let (name, rest) = line.split_once(char::is_whitespace)?;
+        let (line_number, rest) = rest.trim_start().split_once(char::is_whitespace)?;
+        let (path, pattern) = rest
+            .trim_start()
+            .split_once(' ')
+            .unwrap_or((rest.trim_start(), ""));
+        Some(Self {
+            name: name.to_string(),
+            line: line_number.parse().ok()?,
+            path: path.to_string(),
+            pattern: pattern.to_string(),
+        })
+    }
+
+    /// Builds the line for displaying the tag info, same format with the project tags.
+    pub fn format_gtags_tag(&self, kind: &str) -> String {
+        let name_lnum = format!("{}:{}", self.name, self.line);
+        let kind = format!("[{kind}@{}]", self.path);
+        format!(
+            "{text:<text_width$} {kind:<kind_width$} {pattern}",
+            text = name_lnum,
+            text_width = 30,
+            kind = kind,
+            kind_width = 30,
+            pattern = self.pattern.trim(),
+        )
+    }
+}
+
+/// Wrapper of the `global -x` command, mirroring [`crate::tools::ctags::ProjectCtagsCommand`].
+#[derive(Debug)]
+pub struct GtagsCommand {
+    std_cmd: Command,
+    shell_cmd: ShellCommand,
+    /// `def` or `ref`.
+    kind: &'static str,
+}
+
+impl GtagsCommand {
+    /// Creates an instance of [`GtagsCommand`].
+    pub fn new(std_cmd: Command, shell_cmd: ShellCommand, kind: &'static str) -> Self {
+        Self {
+            std_cmd,
+            shell_cmd,
+            kind,
+        }
+    }
+
+    fn global(project_root: &Path, args: &[&str]) -> (Command, ShellCommand) {
+        let mut std_cmd = Command::new("global");
+        std_cmd
+            .current_dir(project_root)
+            .env("GTAGSROOT", project_root)
+            .env("GTAGSDBPATH", gtags_db_path(project_root))
+            .args(args);
+        let shell_cmd = ShellCommand::new(
+            format!("global {}", args.join(" ")),
+            project_root.to_path_buf(),
+        );
+        (std_cmd, shell_cmd)
+    }
+
+    /// Lists all the definitions in `project_root`.
+    pub fn with_cwd(project_root: PathBuf) -> Self {
+        let (std_cmd, shell_cmd) = Self::global(&project_root, &["-x", ".*"]);
+        Self::new(std_cmd, shell_cmd, "def")
+    }
+
+    /// Lists the references of `keyword` in `project_root`.
+    ///
+    /// `keyword` follows `--`, so that it's never parsed as an option.
+    pub fn references(project_root: PathBuf, keyword: &str) -> Self {
+        let (std_cmd, shell_cmd) = Self::global(&project_root, &["-x", "-r", "--", keyword]);
+        Self::new(std_cmd, shell_cmd, "ref")
+    }
+
+    /// Runs the command and returns its stdout.
+    ///
+    /// Returns an error if `global` fails, e.g., the GTAGS database does not exist.
+    fn stdout(&mut self) -> Result<Vec<u8>> {
+        let output = self
+            .std_cmd
+            .output()
+            .map_err(|source| ToolError::SpawnFailed {
+                program: "global".into(),
+                source,
+            })?;
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stderr = stderr.trim();
+        if !output.status.success() || (output.stdout.is_empty() && !stderr.is_empty()) {
+            return Err(ToolError::ExecutionFailed {
+                program: "global".into(),
+                status: output.status,
+                stderr: stderr.into(),
+            }
+            .into());
+        }
+        Ok(output.stdout)
+    }
+
+    /// Returns an iterator of the formatted tags in the order of the `global` output.
+    pub fn formatted_tags_stream(&mut self) -> Result<impl Iterator<Item = String>> {
+        let kind = self.kind;
+        Ok(Cursor::new(self.stdout()?)
+            .lines()
+            .map_while(Result::ok)
+            .filter_map(move |line| {
+                GtagsTag::from_global_line(&line).map(|tag| tag.format_gtags_tag(kind))
+            }))
+    }
+
+    /// Returns the formatted tag lines, parsed in parallel.
+    pub fn par_formatted_lines(&mut self) -> Result<Vec<String>> {
+        let stdout = self.stdout()?;
+        let kind = self.kind;
+        Ok(stdout
+            .par_split(|x| x == &b'\n')
+            .filter_map(|line| {
+                std::str::from_utf8(line)
+                    .ok()
+                    .and_then(GtagsTag::from_global_line)
+                    .map(|tag| tag.format_gtags_tag(kind))
+            })
+            .collect())
+    }
+
+    /// Returns a tuple of (total, cache_path) if the cache exists.
+    pub fn gtags_cache(&self) -> Option<(usize, PathBuf)> {
+        self.shell_cmd
+            .cache_digest()
+            .map(|digest| (digest.total, digest.cached_path))
+    }
+
+    /// Runs the command and writes the cache to the disk.
+    ///
+    /// Nothing is cached if `global` fails.
+    pub fn create_cache(&mut self) -> Result<(usize, PathBuf)> {
+        let lines = self.formatted_tags_stream()?.collect::<Vec<_>>();
+        self.write_cache(lines)
+    }
+
+    /// Parallel version of [`Self::create_cache`].
+    pub fn par_create_cache(&mut self) -> Result<(usize, PathBuf)> {
+        let lines = self.par_formatted_lines()?;
+        self.write_cache(lines)
+    }
+
+    fn write_cache(&self, lines: Vec<String>) -> Result<(usize, PathBuf)> {
+        let total = lines.len();
+        let lines = lines.into_iter().join("\n");
+
+        let cache_path = self
+            .shell_cmd
+            .clone()
+            .write_cache(total, lines.as_bytes())?;
+
+        Ok((total, cache_path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_global_failure_is_not_cached() {
+        if !*GTAGS_EXISTS {
+            return;
+        }
+
+        // No GTAGS database is created for the empty directory.
+        let dir = tempfile::tempdir().unwrap();
+        let mut gtags_cmd = GtagsCommand::references(dir.path().to_path_buf(), "--help");
+        assert!(gtags_cmd.shell_cmd.command.ends_with("-- --help"));
+
+        let err = gtags_cmd.create_cache().unwrap_err();
+        assert!(matches!(
+            ToolError::from_io_error(&err),
+            Some(ToolError::ExecutionFailed { .. })
+        ));
+        assert!(gtags_cmd.gtags_cache().is_none());
+    }
+
+    #[test]
+    fn test_parse_global_line() {
+        let line = "run               101 crates/maple_cli/src/app.rs pub async fn run(self) -> Result<()> {";
+        assert_eq!(
+            GtagsTag::from_global_line(line).unwrap(),
+            GtagsTag {
+                name: "run".into(),
+                line: 101,
+                path: "crates/maple_cli/src/app.rs".into(),
+                pattern: "pub async fn run(self) -> Result<()> {".into(),
+            }
+        );
+    }
+}
@@ -2,3 +2,104 @@ pub mod ctags;
 pub mod git;
 pub mod gtags;
 pub mod rg;
+
+use once_cell::sync::OnceCell;
+
+static RG_EXECUTABLE: OnceCell<String> = OnceCell::new();
+static CTAGS_EXECUTABLE: OnceCell<String> = OnceCell::new();
+
+/// Overrides the ripgrep executable, e.g., `rg14` or an absolute path.
+///
+/// It has to be set before running any ripgrep command, the executable is
+/// returned back as the error if it has been resolved already.
+pub fn set_rg_executable(executable: String) -> Result<(), String> {
+    RG_EXECUTABLE.set(executable)
+}
+
+/// Returns the ripgrep executable, `rg` in PATH by default.
+pub fn rg_executable() -> &'static str {
+    RG_EXECUTABLE.get_or_init(|| "rg".into())
+}
+
+/// Overrides the ctags executable, e.g., `uctags` or an absolute path.
+///
+/// It has to be set before running any ctags command, the executable is
+/// returned back as the error if it has been resolved already.
+pub fn set_ctags_executable(executable: String) -> Result<(), String> {
+    CTAGS_EXECUTABLE.set(executable)
+}
+
+/// Returns the ctags executable, `ctags` in PATH by default.
+pub fn ctags_executable() -> &'static str {
+    CTAGS_EXECUTABLE.get_or_init(|| "ctags".into())
+}
+
+/// Error of running the external tools, e.g., ctags.
+///
+/// It's usually wrapped in [`std::io::Error`], use [`ToolError::from_io_error`]
+/// to tell the specific error for showing a tailored hint.
+#[derive(Debug, thiserror::Error)]
+pub enum ToolError {
+    #[error("ctags executable not found, universal-ctags is required")]
+    CtagsMissing,
+    #[error("ctags executable has no +json feature")]
+    NoJsonFeature,
+    #[error("failed to spawn {program}: {source}")]
+    SpawnFailed {
+        program: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("failed to parse the output of {program}: {line}")]
+    ParseFailed { program: String, line: String },
+    #[error("{program} failed ({status}): {stderr}")]
+    ExecutionFailed {
+        program: String,
+        status: std::process::ExitStatus,
+        stderr: String,
+    },
+    #[error("cancelled by the user")]
+    Cancelled,
+}
+
+impl ToolError {
+    /// Returns the [`ToolError`] wrapped in `error` if any.
+    pub fn from_io_error(error: &std::io::Error) -> Option<&Self> {
+        error.get_ref().and_then(|e| e.downcast_ref::<Self>())
+    }
+}
+
+impl From<ToolError> for std::io::Error {
+    fn from(error: ToolError) -> Self {
+        let kind = match &error {
+            ToolError::CtagsMissing => std::io::ErrorKind::NotFound,
+            ToolError::SpawnFailed { source, .. } => source.kind(),
+            ToolError::Cancelled => std::io::ErrorKind::Interrupted,
+            ToolError::NoJsonFeature
+            | ToolError::ParseFailed { .. }
+            | ToolError::ExecutionFailed { .. } => std::io::ErrorKind::Other,
+        };
+        Self::new(kind, error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tool_error_in_io_error() {
+        let error: std::io::Error = ToolError::NoJsonFeature.into();
+        assert!(matches!(
+            ToolError::from_io_error(&error),
+            Some(ToolError::NoJsonFeature)
+        ));
+        assert_eq!(error.to_string(), "ctags executable has no +json feature");
+
+        let error: std::io::Error = ToolError::CtagsMissing.into();
+        assert_eq!(error.kind(), std::io::ErrorKind::NotFound);
+
+        let error = std::io::Error::new(std::io::ErrorKind::Other, "other");
+        assert!(ToolError::from_io_error(&error).is_none());
+    }
+}
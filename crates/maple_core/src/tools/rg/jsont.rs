@@ -1,7 +1,7 @@
 use super::stats::Stats;
 use base64::engine::general_purpose::STANDARD;
 use base64::Engine;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
 
 #[derive(Deserialize, Debug, Clone)]
@@ -26,13 +26,16 @@ pub struct End {
     pub stats: Stats,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Match {
     pub path: Data,
     pub lines: Data,
     pub line_number: Option<u64>,
     pub absolute_offset: u64,
     pub submatches: Vec<SubMatch>,
+    /// Whether this is a context line around the actual match.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub is_context: bool,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -44,7 +47,27 @@ pub struct Context {
     pub submatches: Vec<SubMatch>,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+impl From<Context> for Match {
+    fn from(context: Context) -> Self {
+        let Context {
+            path,
+            lines,
+            line_number,
+            absolute_offset,
+            submatches,
+        } = context;
+        Self {
+            path,
+            lines,
+            line_number,
+            absolute_offset,
+            submatches,
+            is_context: true,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct SubMatch {
     #[serde(rename = "match")]
     pub m: Data,
@@ -61,14 +84,14 @@ pub struct SubMatch {
 /// The happy path is valid UTF-8, which streams right through as-is, since
 /// it is natively supported by JSON. When invalid UTF-8 is found, then it is
 /// represented as arbitrary bytes and base64 encoded.
-#[derive(Debug, Clone, Hash, PartialEq, Eq, Deserialize)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum Data {
     Text {
         text: String,
     },
     Bytes {
-        #[serde(deserialize_with = "from_base64")]
+        #[serde(serialize_with = "to_base64", deserialize_with = "from_base64")]
         bytes: Vec<u8>,
     },
 }
@@ -82,6 +105,13 @@ impl Data {
     }
 }
 
+fn to_base64<S>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    serializer.serialize_str(&STANDARD.encode(bytes))
+}
+
 fn from_base64<'de, D>(deserializer: D) -> Result<Vec<u8>, D::Error>
 where
     D: serde::Deserializer<'de>,
@@ -4,7 +4,8 @@ mod stats;
 
 use crate::cache::Digest;
 use crate::process::ShellCommand;
-use once_cell::sync::Lazy;
+use icon::Icon;
+use once_cell::sync::{Lazy, OnceCell};
 use std::borrow::Cow;
 use std::collections::HashMap;
 use std::convert::TryFrom;
@@ -13,10 +14,10 @@ use std::path::{Path, PathBuf};
 use std::process::Command;
 use utils::display_width;
 
-pub use self::jsont::{Match, Message, SubMatch};
+pub use self::jsont::{Data, Match, Message, SubMatch};
 
 pub static RG_EXISTS: Lazy<bool> = Lazy::new(|| {
-    std::process::Command::new("rg")
+    std::process::Command::new(super::rg_executable())
         .arg("--version")
         .stdout(std::process::Stdio::null())
         .status()
@@ -50,12 +51,114 @@ pub fn get_language(file_extension: &str) -> Option<&&str> {
     RG_LANGUAGE_EXT_TABLE.get(file_extension)
 }
 
+/// Returns the file globs of the ripgrep language `lang`.
+pub fn get_language_globs(lang: &str) -> Option<&'static [&'static str]> {
+    default_types::DEFAULT_TYPES
+        .iter()
+        .find_map(|(name, globs)| (*name == lang).then_some(*globs))
+}
+
+/// Returns a file extension of the ripgrep language `lang`, e.g., `py` for `py`.
+pub fn language_extension(lang: &str) -> Option<&'static str> {
+    get_language_globs(lang)?.iter().find_map(|glob| {
+        glob.strip_prefix("*.")
+            .filter(|ext| ext.chars().all(|c| c.is_ascii_alphanumeric()))
+    })
+}
+
+/// Interpreters and Vim filetypes whose ripgrep language has a different name.
+const LANGUAGE_ALIASES: &[(&str, &str)] = &[
+    ("bash", "sh"),
+    ("dash", "sh"),
+    ("javascript", "js"),
+    ("node", "js"),
+    ("python", "py"),
+    ("typescript", "ts"),
+    ("zsh", "sh"),
+];
+
+/// Converts the name of interpreter or Vim filetype to the ripgrep language.
+fn to_rg_language(name: &str) -> Option<&'static str> {
+    LANGUAGE_ALIASES
+        .iter()
+        .find_map(|(alias, lang)| (*alias == name).then_some(*lang))
+        .or_else(|| {
+            default_types::DEFAULT_TYPES
+                .iter()
+                .find_map(|(lang, _)| (*lang == name).then_some(*lang))
+        })
+}
+
+/// Detects the language from the shebang line, e.g., `#!/usr/bin/env python3`.
+fn shebang_language(line: &str) -> Option<&'static str> {
+    let mut args = line.strip_prefix("#!")?.split_whitespace();
+    let mut program = args.next()?.rsplit('/').next()?;
+    if program == "env" {
+        program = args.find(|arg| !arg.starts_with('-') && !arg.contains('='))?;
+    }
+    // python3.11 => python
+    to_rg_language(program.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.'))
+}
+
+/// Detects the language from the Vim modeline, e.g., `# vim: ft=ruby`.
+fn modeline_language(line: &str) -> Option<&'static str> {
+    let (_, options) = ["vim:", "vi:", "ex:"].iter().find_map(|marker| {
+        line.match_indices(marker)
+            .find(|(idx, _)| {
+                line[..*idx]
+                    .chars()
+                    .next_back()
+                    .filter(|c| !c.is_whitespace())
+                    .is_none()
+            })
+            .map(|(idx, _)| line.split_at(idx + marker.len()))
+    })?;
+    options
+        .split(|c: char| c.is_whitespace() || c == ':')
+        .find_map(|option| {
+            option
+                .strip_prefix("ft=")
+                .or_else(|| option.strip_prefix("filetype="))
+        })
+        .and_then(to_rg_language)
+}
+
+/// Detects the ripgrep language of file `path` given its first lines.
+///
+/// The shebang and Vim modeline take precedence over the file extension, so that
+/// the extensionless scripts can be recognized.
+pub fn detect_language(path: &Path, first_lines: &[&str]) -> Option<&'static str> {
+    first_lines
+        .first()
+        .and_then(|line| shebang_language(line))
+        .or_else(|| first_lines.iter().find_map(|line| modeline_language(line)))
+        .or_else(|| {
+            path.extension()
+                .and_then(|ext| ext.to_str())
+                .and_then(get_language)
+                .copied()
+        })
+}
+
+/// Separators between the segments of a qualified path, e.g., `std::collections::HashMap`.
+const QUALIFIED_PATH_SEPARATORS: &[&str] = &["::", ".", "/"];
+
+/// Splits the qualified path `path` into segments.
+fn path_segments(path: &str) -> impl Iterator<Item = &str> {
+    path.split("::")
+        .flat_map(|segment| segment.split(['.', '/']))
+}
+
 /// Word represents the input query around by word boundries.
 #[derive(Clone, Debug)]
 pub struct Word {
     pub raw: String,
     pub len: usize,
     pub re: regex::Regex,
+    /// Full path if the word is the last segment of a qualified path.
+    pub qualified: Option<String>,
+    /// Case-insensitive version of `re`, compiled on the first use.
+    case_insensitive_re: OnceCell<regex::Regex>,
 }
 
 impl Word {
@@ -64,12 +167,142 @@ impl Word {
             len: re_word.len(),
             raw: re_word,
             re,
+            qualified: None,
+            case_insensitive_re: OnceCell::new(),
         }
     }
 
+    /// Constructs a word from a possibly qualified path, e.g., `std::collections::HashMap`,
+    /// `os.path.join` or `lodash/debounce`.
+    ///
+    /// The last segment is searched as `raw`, the full path is kept in `qualified`
+    /// for ranking the usages of the full path higher.
+    pub fn from_qualified(path: &str) -> Result<Word, regex::Error> {
+        let tail_start = QUALIFIED_PATH_SEPARATORS
+            .iter()
+            .filter_map(|sep| path.rfind(sep).map(|idx| idx + sep.len()))
+            .max()
+            .unwrap_or(0);
+        let tail = &path[tail_start..];
+
+        // Not a qualified path if either the tail or all the qualifiers are empty, e.g., `foo.`.
+        let is_qualified = !tail.is_empty()
+            && path_segments(&path[..tail_start]).any(|segment| !segment.is_empty());
+        let raw = if is_qualified { tail } else { path };
+
+        let re = regex::Regex::new(&format!("\\b{}\\b", regex::escape(raw)))?;
+        Ok(Self {
+            qualified: is_qualified.then(|| path.to_string()),
+            ..Self::new(raw.to_string(), re)
+        })
+    }
+
+    /// Returns the segments of the qualified path before the word, empty if not qualified.
+    pub fn qualifiers(&self) -> Vec<&str> {
+        self.qualified
+            .as_deref()
+            .and_then(|qualified| qualified.strip_suffix(self.raw.as_str()))
+            .map(|qualifiers| {
+                path_segments(qualifiers)
+                    .filter(|segment| !segment.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Returns `true` if `line` contains the full qualified path of the word.
+    pub fn is_qualified_match(&self, line: &str) -> bool {
+        self.qualified
+            .as_deref()
+            .map_or(false, |qualified| line.contains(qualified))
+    }
+
     pub fn find(&self, line: &str) -> Option<usize> {
         self.re.find(line).map(|mat| mat.start())
     }
+
+    /// Returns the byte range of the word in `text`, falling back to the case-insensitive
+    /// search as ripgrep may have matched the word in a different case, e.g., with the
+    /// smart case or the case-insensitive definition regexp.
+    pub fn find_range(&self, text: &str) -> Option<Range<usize>> {
+        self.re
+            .find(text)
+            .or_else(|| {
+                self.case_insensitive_re
+                    .get_or_try_init(|| {
+                        regex::RegexBuilder::new(self.re.as_str())
+                            .case_insensitive(true)
+                            .build()
+                    })
+                    .ok()?
+                    .find(text)
+            })
+            .map(|mat| mat.range())
+    }
+
+    /// Returns the raw word with the regex metacharacters escaped, so that it can
+    /// be interpolated into a regex pattern as a literal.
+    ///
+    /// The escaped word is valid in both the default regex and PCRE2 syntax of ripgrep.
+    pub fn escaped(&self) -> String {
+        regex::escape(&self.raw)
+    }
+}
+
+/// Returns the extra chars that can be part of an identifier in the ripgrep language `lang`,
+/// besides the alphanumeric chars and `_`.
+fn extra_word_chars(lang: &str) -> &'static [char] {
+    match lang {
+        "css" | "less" | "sass" => &['-'],
+        "clojure" | "elisp" | "lisp" => &['-', '?', '!', '*'],
+        "js" | "ts" | "php" => &['$'],
+        _ => &[],
+    }
+}
+
+/// Extracts the symbol at the byte column `col` of `line` given the ripgrep language `lang`.
+///
+/// Returns the word and its byte range in `line`, `None` if `col` is not on a word char.
+///
+/// `foo::bar` => `bar` if the cursor is on `bar` in Rust, the separators like `.` and `::`
+/// always delimit the words, whereas `-` is a word char in CSS and Lisp.
+pub fn extract_word_at(line: &str, col: usize, lang: &str) -> Option<(Word, Range<usize>)> {
+    let extra_chars = extra_word_chars(lang);
+    let is_word_char = |c: char| c.is_alphanumeric() || c == '_' || extra_chars.contains(&c);
+
+    let cursor_char = line.get(col..)?.chars().next()?;
+    if !is_word_char(cursor_char) {
+        return None;
+    }
+
+    let start = line[..col]
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| is_word_char(*c))
+        .last()
+        .map(|(idx, _)| idx)
+        .unwrap_or(col);
+    let end = line[col..]
+        .char_indices()
+        .find(|(_, c)| !is_word_char(*c))
+        .map(|(idx, _)| col + idx)
+        .unwrap_or(line.len());
+
+    let raw = &line[start..end];
+    // `\b` does not match around the non-word chars, e.g., `$` in `$foo`.
+    let boundary = |c: Option<char>| match c {
+        Some(c) if c.is_alphanumeric() || c == '_' => "\\b",
+        _ => "",
+    };
+    let re = regex::Regex::new(&format!(
+        "{}{}{}",
+        boundary(raw.chars().next()),
+        regex::escape(raw),
+        boundary(raw.chars().next_back())
+    ))
+    .ok()?;
+
+    Some((Word::new(raw.to_string(), re), start..end))
 }
 
 #[inline]
@@ -84,14 +317,22 @@ impl SubMatch {
 
     // FIXME find the word in non-utf8?
     pub fn match_indices_for_dumb_jump(&self, offset: usize, search_word: &Word) -> Range<usize> {
-        // The text in SubMatch is not exactly the search word itself in some cases,
-        // we need to first find the offset of search word in the SubMatch text manually.
-        match search_word.find(&self.m.text()) {
-            Some(search_word_offset) => {
-                let start = self.start + search_word_offset;
-                range(start, start + search_word.len, offset)
-            }
-            None => Default::default(),
+        let text = self.m.text();
+
+        // The submatch reported by ripgrep is the word itself, whatever its case.
+        if text.len() == search_word.len && text.eq_ignore_ascii_case(&search_word.raw) {
+            return range(self.start, self.end, offset);
+        }
+
+        // The text in SubMatch is not exactly the search word itself in some cases, e.g.,
+        // the whole definition, we need to locate the search word in the SubMatch text.
+        match search_word.find_range(&text) {
+            Some(word_range) => range(
+                self.start + word_range.start,
+                self.start + word_range.end,
+                offset,
+            ),
+            None => range(self.start, self.end, offset),
         }
     }
 }
@@ -120,10 +361,22 @@ impl Match {
         self.line_number.unwrap_or_default()
     }
 
+    /// Returns the byte column of the first submatch.
     pub fn column(&self) -> usize {
         self.submatches.first().map(|x| x.start).unwrap_or_default()
     }
 
+    /// Returns a tuple of (path, line_number, column) for jumping to the first submatch.
+    pub fn jump_target(&self) -> (Cow<str>, u64, usize) {
+        (self.path(), self.line_number(), self.column())
+    }
+
+    /// Same as [`Self::jump_target`], but the column is in UTF-16 code units as LSP expects.
+    pub fn jump_target_utf16(&self) -> (Cow<str>, u64, usize) {
+        let column = utils::byte_col_to_utf16(&self.lines.text(), self.column());
+        (self.path(), self.line_number(), column)
+    }
+
     /// Returns true if the text line starts with `pat`.
     pub fn line_starts_with(&self, pat: &str) -> bool {
         self.lines.text().trim_start().starts_with(pat)
@@ -174,8 +427,20 @@ impl Match {
     /// Returns a pair of the formatted `String` and the offset of origin match indices.
     ///
     /// The formatted String is same with the output line using rg's -vimgrep option.
-    fn grep_line_format(&self, enable_icon: bool) -> (String, usize) {
-        let path = self.path();
+    ///
+    /// The path is truncated to fit in `max_path_width` if specified.
+    fn grep_line_format(
+        &self,
+        enable_icon: bool,
+        max_path_width: Option<usize>,
+    ) -> (String, usize) {
+        let full_path = self.path();
+        let path = match max_path_width {
+            Some(max_len) if full_path.len() > max_len => {
+                paths::truncate_absolute_path(&full_path, max_len)
+            }
+            _ => Cow::Borrowed(full_path.as_ref()),
+        };
         let line_number = self.line_number();
         let column = self.column();
         let pattern = self.pattern();
@@ -186,7 +451,7 @@ impl Match {
             path.len() + display_width(line_number as usize) + display_width(column) + 3;
 
         let formatted_line = if enable_icon {
-            let icon = icon::file_icon(&path);
+            let icon = icon::file_icon(&full_path);
             offset += icon.len_utf8() + 1;
             format!("{icon} {path}:{line_number}:{column}:{pattern}")
         } else {
@@ -197,7 +462,18 @@ impl Match {
     }
 
     pub fn build_grep_line(&self, enable_icon: bool) -> (String, Vec<usize>) {
-        let (formatted, offset) = self.grep_line_format(enable_icon);
+        let (formatted, offset) = self.grep_line_format(enable_icon, None);
+        let indices = self.match_indices(offset);
+        (formatted, indices)
+    }
+
+    /// Returns the canonical line `path:line:column:text` for displaying the match and the
+    /// byte indices of the matched ranges in the line for highlighting.
+    ///
+    /// The file icon is prepended if `icon` is enabled. The path is truncated to fit in
+    /// `max_path_width` if specified, the full path is kept otherwise.
+    pub fn display_line(&self, icon: Icon, max_path_width: Option<usize>) -> (String, Vec<usize>) {
+        let (formatted, offset) = self.grep_line_format(icon.icon_kind().is_some(), max_path_width);
         let indices = self.match_indices(offset);
         (formatted, indices)
     }
@@ -243,6 +519,27 @@ impl Match {
         (formatted, indices)
     }
 
+    /// Same as [`Self::build_jump_line`], but all the submatches are highlighted, e.g., the
+    /// matches of multiple words.
+    ///
+    /// The submatch offsets reported by rg are in bytes, the returned indices are char
+    /// indices into the formatted line.
+    pub fn build_jump_line_all_submatches(&self, kind: &str) -> (String, Vec<usize>) {
+        let (formatted, offset) = self.jump_line_format(kind);
+        let indices = self
+            .submatches
+            .iter()
+            .filter_map(|s| {
+                let byte_range = s.match_indices(offset);
+                let start = formatted.get(..byte_range.start)?.chars().count();
+                let len = formatted.get(byte_range)?.chars().count();
+                Some(start..start + len)
+            })
+            .flatten()
+            .collect();
+        (formatted, indices)
+    }
+
     fn jump_line_format_bare(&self) -> (String, usize) {
         let line_number = self.line_number();
         let column = self.column();
@@ -332,3 +629,138 @@ pub fn refresh_cache(dir: impl AsRef<Path>) -> std::io::Result<Digest> {
 pub fn rg_shell_command<P: AsRef<Path>>(dir: P) -> ShellCommand {
     ShellCommand::new(RG_EXEC_CMD.into(), PathBuf::from(dir.as_ref()))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jump_target() {
+        let line = r#"{"type":"match","data":{"path":{"text":"src/lib.rs"},"lines":{"text":"    let foo = bar();\n"},"line_number":7,"absolute_offset":9,"submatches":[{"match":{"text":"bar"},"start":14,"end":17}]}}"#;
+        let matched = Match::try_from(line).unwrap();
+        assert_eq!(matched.jump_target(), ("src/lib.rs".into(), 7, 14));
+        assert_eq!(matched.jump_target_utf16(), ("src/lib.rs".into(), 7, 14));
+
+        let line = r#"{"type":"match","data":{"path":{"text":"src/lib.rs"},"lines":{"text":"    let 🦀 = bar();\n"},"line_number":7,"absolute_offset":9,"submatches":[{"match":{"text":"bar"},"start":15,"end":18}]}}"#;
+        let matched = Match::try_from(line).unwrap();
+        assert_eq!(matched.jump_target(), ("src/lib.rs".into(), 7, 15));
+        assert_eq!(matched.jump_target_utf16(), ("src/lib.rs".into(), 7, 13));
+    }
+
+    #[test]
+    fn test_display_line() {
+        let line = r#"{"type":"match","data":{"path":{"text":"/media/xlc/src/github.com/vim-clap/crates/cli/src/main.rs"},"lines":{"text":"fn main() {\n"},"line_number":3,"absolute_offset":9,"submatches":[{"match":{"text":"main"},"start":3,"end":7}]}}"#;
+        let matched = Match::try_from(line).unwrap();
+
+        let (formatted, indices) = matched.display_line(Icon::Null, None);
+        assert_eq!(
+            formatted,
+            "/media/xlc/src/github.com/vim-clap/crates/cli/src/main.rs:3:3:fn main() {"
+        );
+        assert_eq!(indices, matched.build_grep_line(false).1);
+
+        let (formatted, indices) = matched.display_line(Icon::Null, Some(40));
+        assert_eq!(
+            formatted,
+            "/media/xlc/.../crates/cli/src/main.rs:3:3:fn main() {"
+        );
+        let highlighted = indices.iter().map(|&i| formatted.as_bytes()[i] as char);
+        assert_eq!(highlighted.collect::<String>(), "main");
+    }
+
+    #[test]
+    fn test_mixed_case_match_indices() {
+        let word = Word::new("users".into(), regex::Regex::new("\\busers\\b").unwrap());
+
+        let line = r#"{"type":"match","data":{"path":{"text":"schema.sql"},"lines":{"text":"CREATE TABLE Users (\n"},"line_number":1,"absolute_offset":0,"submatches":[{"match":{"text":"CREATE TABLE Users"},"start":0,"end":18}]}}"#;
+        let matched = Match::try_from(line).unwrap();
+        let indices = matched.match_indices_for_dumb_jump(0, &word);
+        assert_eq!(indices, (13..18).collect::<Vec<_>>());
+
+        let line = r#"{"type":"match","data":{"path":{"text":"query.sql"},"lines":{"text":"SELECT * FROM USERS;\n"},"line_number":3,"absolute_offset":9,"submatches":[{"match":{"text":"USERS"},"start":14,"end":19}]}}"#;
+        let matched = Match::try_from(line).unwrap();
+        let indices = matched.match_indices_for_dumb_jump(2, &word);
+        assert_eq!(indices, (16..21).collect::<Vec<_>>());
+
+        // The case-insensitive regex is compiled once and reused.
+        let case_insensitive_re = word.case_insensitive_re.get().unwrap() as *const _;
+        assert_eq!(word.find_range("Users"), Some(0..5));
+        assert!(std::ptr::eq(
+            word.case_insensitive_re.get().unwrap(),
+            case_insensitive_re
+        ));
+    }
+
+    #[test]
+    fn test_word_from_qualified() {
+        let word = Word::from_qualified("std::collections::HashMap").unwrap();
+        assert_eq!(word.raw, "HashMap");
+        assert_eq!(word.qualified.as_deref(), Some("std::collections::HashMap"));
+        assert_eq!(word.qualifiers(), vec!["std", "collections"]);
+        assert!(word.is_qualified_match("use std::collections::HashMap;"));
+        assert!(!word.is_qualified_match("let map = HashMap::new();"));
+
+        let word = Word::from_qualified("os.path.join").unwrap();
+        assert_eq!(word.raw, "join");
+        assert_eq!(word.qualifiers(), vec!["os", "path"]);
+
+        let word = Word::from_qualified("lodash/debounce").unwrap();
+        assert_eq!(word.raw, "debounce");
+
+        for unqualified in ["foo", "foo.", ".foo"] {
+            let word = Word::from_qualified(unqualified).unwrap();
+            assert_eq!(word.raw, unqualified);
+            assert!(word.qualified.is_none());
+            assert!(word.qualifiers().is_empty());
+        }
+    }
+
+    #[test]
+    fn test_extract_word_at() {
+        let extract = |line: &str, col: usize, lang: &str| {
+            extract_word_at(line, col, lang).map(|(word, range)| (word.raw, range))
+        };
+
+        let line = "    let f = std::fs::read_to_string(path)?;";
+        assert_eq!(extract(line, 18, "rust"), Some(("fs".into(), 17..19)));
+        assert_eq!(
+            extract(line, 25, "rust"),
+            Some(("read_to_string".into(), 21..35))
+        );
+        assert_eq!(extract(line, 19, "rust"), None);
+
+        let line = ".nav-bar > .menu-item { color: red; }";
+        assert_eq!(extract(line, 3, "css"), Some(("nav-bar".into(), 1..8)));
+        assert_eq!(extract(line, 3, "rust"), Some(("nav".into(), 1..4)));
+
+        let line = "(defun string-to-list (s) nil)";
+        let (word, range) = extract_word_at(line, 10, "lisp").unwrap();
+        assert_eq!(word.raw, "string-to-list");
+        assert_eq!(range, 7..21);
+        assert_eq!(word.find(line), Some(7));
+    }
+
+    #[test]
+    fn test_detect_language() {
+        let path = Path::new("scripts/build");
+        assert_eq!(
+            detect_language(path, &["#!/usr/bin/env python3", "import os"]),
+            Some("py")
+        );
+        assert_eq!(detect_language(path, &["#!/bin/bash -e"]), Some("sh"));
+        assert_eq!(
+            detect_language(path, &["require 'json'", "# vim: ft=ruby"]),
+            Some("ruby")
+        );
+        assert_eq!(
+            detect_language(path, &["/* vim: set filetype=javascript : */"]),
+            Some("js")
+        );
+        assert_eq!(detect_language(path, &["nothing"]), None);
+        assert_eq!(
+            detect_language(Path::new("src/lib.rs"), &["// avim: ft=ruby"]),
+            Some("rust")
+        );
+        assert_eq!(language_extension("py"), Some("py"));
+    }
+}
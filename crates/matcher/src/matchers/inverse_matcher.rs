@@ -1,13 +1,29 @@
-use types::InverseTerm;
+use types::{CaseMatching, InverseTerm};
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone)]
 pub struct InverseMatcher {
     inverse_terms: Vec<InverseTerm>,
+    case_matching: CaseMatching,
+}
+
+impl Default for InverseMatcher {
+    fn default() -> Self {
+        Self::new(Vec::new())
+    }
 }
 
 impl InverseMatcher {
+    /// Creates a new matcher, the inverse terms are matched case-sensitively.
     pub fn new(inverse_terms: Vec<InverseTerm>) -> Self {
-        Self { inverse_terms }
+        Self {
+            inverse_terms,
+            case_matching: CaseMatching::Respect,
+        }
+    }
+
+    pub fn with_case_matching(mut self, case_matching: CaseMatching) -> Self {
+        self.case_matching = case_matching;
+        self
     }
 
     pub fn inverse_terms(&self) -> &[InverseTerm] {
@@ -17,8 +33,13 @@ impl InverseMatcher {
     /// Returns `true` if any inverse matching is satisfied, which means the item should be
     /// ignored.
     pub fn match_any(&self, match_text: &str) -> bool {
+        self.find_any(match_text).is_some()
+    }
+
+    /// Returns the first inverse term satisfied by `match_text`.
+    pub fn find_any(&self, match_text: &str) -> Option<&InverseTerm> {
         self.inverse_terms
             .iter()
-            .any(|inverse_term| inverse_term.exact_matched(match_text))
+            .find(|inverse_term| inverse_term.matched_with_case(match_text, self.case_matching))
     }
 }
@@ -1,5 +1,4 @@
 use dirs::Dirs;
-use itertools::Itertools;
 use serde::de::Error as DeserializeError;
 use serde::{Deserialize, Deserializer, Serialize};
 use std::borrow::Cow;
@@ -117,73 +116,264 @@ impl std::fmt::Display for AbsPathBuf {
 }
 
 /// Expands `~` if any.
+///
+/// Both `~` and `~user` are supported, `~user` is only expanded on Unix and the path
+/// is left unchanged if the home directory of `user` can not be found.
 pub fn expand_tilde(path: impl AsRef<str>) -> PathBuf {
     static HOME_PREFIX: OnceLock<String> = OnceLock::new();
 
-    if let Some(stripped) = path
-        .as_ref()
-        .strip_prefix(HOME_PREFIX.get_or_init(|| format!("~{MAIN_SEPARATOR}")))
+    let path = path.as_ref();
+
+    if let Some(stripped) =
+        path.strip_prefix(HOME_PREFIX.get_or_init(|| format!("~{MAIN_SEPARATOR}")))
     {
         Dirs::base().home_dir().join(stripped)
+    } else if path == "~" {
+        Dirs::base().home_dir().to_path_buf()
+    } else if let Some(expanded) = path.strip_prefix('~').and_then(expand_user_home) {
+        expanded
     } else {
-        path.as_ref().into()
+        path.into()
+    }
+}
+
+/// Expands `user/rest` of `~user/rest` to the path under the home directory of `user`.
+#[cfg(unix)]
+fn expand_user_home(user_path: &str) -> Option<PathBuf> {
+    let (user, rest) = user_path
+        .split_once(MAIN_SEPARATOR)
+        .unwrap_or((user_path, ""));
+    if user.is_empty() {
+        return None;
+    }
+    let home_dir = user_home_dir(user)?;
+    if rest.is_empty() {
+        Some(home_dir)
+    } else {
+        Some(home_dir.join(rest))
+    }
+}
+
+#[cfg(not(unix))]
+fn expand_user_home(_user_path: &str) -> Option<PathBuf> {
+    None
+}
+
+/// Returns the home directory of `user` in the passwd database.
+///
+/// `getpwnam_r` is used instead of reading `/etc/passwd` so that the users resolved
+/// through NSS, e.g., LDAP, sssd, are found too.
+#[cfg(unix)]
+fn user_home_dir(user: &str) -> Option<PathBuf> {
+    use std::ffi::{CStr, CString, OsStr};
+    use std::os::unix::ffi::OsStrExt;
+
+    const MAX_BUF_SIZE: usize = 1024 * 1024;
+
+    let name = CString::new(user).ok()?;
+    let mut buf = vec![0 as libc::c_char; 1024];
+
+    loop {
+        // SAFETY: `passwd` is a plain C struct, all zeros is a valid value.
+        let mut passwd: libc::passwd = unsafe { std::mem::zeroed() };
+        let mut result = std::ptr::null_mut();
+        // SAFETY: all the pointers are valid during the call and `buf.len()` is the
+        // actual size of `buf`.
+        let code = unsafe {
+            libc::getpwnam_r(
+                name.as_ptr(),
+                &mut passwd,
+                buf.as_mut_ptr(),
+                buf.len(),
+                &mut result,
+            )
+        };
+
+        if code == libc::ERANGE && buf.len() < MAX_BUF_SIZE {
+            buf.resize(buf.len() * 2, 0);
+            continue;
+        }
+
+        if code != 0 || result.is_null() || passwd.pw_dir.is_null() {
+            return None;
+        }
+
+        // SAFETY: `pw_dir` points to a nul-terminated string in `buf`, which is alive.
+        let home_dir = unsafe { CStr::from_ptr(passwd.pw_dir) };
+        let home_dir = OsStr::from_bytes(home_dir.to_bytes());
+        return (!home_dir.is_empty()).then(|| PathBuf::from(home_dir));
+    }
+}
+
+/// Returns the byte length of the root of `path`, e.g., `/`, `C:\`, `\\server\share\`.
+fn root_len(path: &str) -> usize {
+    let bytes = path.as_bytes();
+    if bytes.len() >= 3
+        && bytes[0].is_ascii_alphabetic()
+        && bytes[1] == b':'
+        && (bytes[2] == b'\\' || bytes[2] == b'/')
+    {
+        3
+    } else if let Some(unc_path) = path.strip_prefix(r"\\") {
+        let server_share_len = unc_path
+            .splitn(3, '\\')
+            .take(2)
+            .map(|component| component.len() + 1)
+            .sum::<usize>();
+        (server_share_len + 2).min(path.len())
+    } else if path.starts_with(MAIN_SEPARATOR) {
+        1
+    } else {
+        0
+    }
+}
+
+/// Returns the rest of `path` relative to `dir` if `path` is under `dir`.
+fn strip_dir<'a>(path: &'a str, dir: &str) -> Option<&'a str> {
+    let rest = path.strip_prefix(dir.trim_end_matches(MAIN_SEPARATOR))?;
+    if rest.is_empty() {
+        Some(rest)
+    } else {
+        rest.strip_prefix(MAIN_SEPARATOR)
+    }
+}
+
+/// Folds the home directory of `path` to `~`, the path is returned as is otherwise.
+///
+/// /home/xlc/src/github.com/liuchengxu/vim-clap => ~/src/github.com/liuchengxu/vim-clap
+pub fn to_home_relative(path: &Path) -> Cow<'_, str> {
+    let path = path.to_string_lossy();
+    let home_relative = Dirs::base()
+        .home_dir()
+        .to_str()
+        .and_then(|home_dir| strip_dir(&path, home_dir))
+        .map(|rest| {
+            if rest.is_empty() {
+                String::from("~")
+            } else {
+                format!("~{MAIN_SEPARATOR}{rest}")
+            }
+        });
+    home_relative.map(Into::into).unwrap_or(path)
+}
+
+/// Strips `base` off `path` if `path` is under `base`, otherwise falls back to
+/// [`to_home_relative`].
+///
+/// /home/xlc/proj/src/foo.rs => src/foo.rs, given base /home/xlc/proj
+pub fn relative_to<'a>(path: &'a Path, base: &Path) -> Cow<'a, str> {
+    let relative = match (path.to_str(), base.to_str()) {
+        (Some(path), Some(base)) => strip_dir(path, base),
+        _ => None,
+    };
+    match relative {
+        Some("") => ".".into(),
+        Some(rest) => rest.into(),
+        None => to_home_relative(path),
+    }
+}
+
+/// Folds `path` using [`relative_to`] and then truncates it to fit in `max_len`.
+pub fn truncate_relative_path<'a>(path: &'a Path, base: &Path, max_len: usize) -> Cow<'a, str> {
+    match relative_to(path, base) {
+        Cow::Borrowed(relative) => truncate_absolute_path(relative, max_len),
+        Cow::Owned(relative) => truncate_absolute_path(&relative, max_len)
+            .into_owned()
+            .into(),
     }
 }
 
 // /home/xlc/.rustup/toolchains/stable-x86_64-unknown-linux-gnu/lib/rustlib/src/rust/library/alloc/src/string.rs
 pub fn truncate_absolute_path(abs_path: &str, max_len: usize) -> Cow<'_, str> {
+    // Strip the verbatim prefix on Windows, e.g., `\\?\C:\`, `\\?\UNC\server\share\`.
+    if let Some(unc_path) = abs_path.strip_prefix(r"\\?\UNC\") {
+        let unc_path = format!(r"\\{unc_path}");
+        return truncate_path(&unc_path, max_len).into_owned().into();
+    }
+    let abs_path = abs_path.strip_prefix(r"\\?\").unwrap_or(abs_path);
+
+    truncate_path(abs_path, max_len)
+}
+
+/// Returns the largest char boundary of `s` not greater than `index`.
+fn floor_char_boundary(s: &str, index: usize) -> usize {
+    if index >= s.len() {
+        return s.len();
+    }
+    (0..=index)
+        .rev()
+        .find(|&i| s.is_char_boundary(i))
+        .unwrap_or(0)
+}
+
+/// Returns the smallest char boundary of `s` not less than `index`.
+fn ceil_char_boundary(s: &str, index: usize) -> usize {
+    (index..s.len())
+        .find(|&i| s.is_char_boundary(i))
+        .unwrap_or(s.len())
+}
+
+/// Replaces the leading components of `tail` with `...` to save at least `gap` bytes.
+fn hide_leading_components(tail: &str, gap: usize) -> Option<String> {
+    let mut to_hide = 0usize;
+    for component in tail.split(MAIN_SEPARATOR) {
+        if to_hide > gap + 2 {
+            let mut tail = tail.to_string();
+            tail.replace_range(..floor_char_boundary(&tail, to_hide - 1), "...");
+            return Some(tail);
+        } else {
+            to_hide += component.len() + 1;
+        }
+    }
+    None
+}
+
+fn truncate_path(abs_path: &str, max_len: usize) -> Cow<'_, str> {
     if abs_path.len() > max_len {
         let gap = abs_path.len() - max_len;
 
         if let Some(home_dir) = Dirs::base().home_dir().to_str() {
-            if abs_path.starts_with(home_dir) {
+            if let Some(relative_home_path) = strip_dir(abs_path, home_dir) {
                 // ~/.rustup/toolchains/stable-x86_64-unknown-linux-gnu/lib/rustlib/src/rust/library/alloc/src/string.rs
                 if home_dir.len() > gap {
-                    return abs_path.replacen(home_dir, "~", 1).into();
+                    return to_home_relative(Path::new(abs_path));
                 }
 
                 // ~/.rustup/.../github.com/paritytech/substrate/frame/system/src/lib.rs
-                let relative_home_path = &abs_path.trim_start_matches(home_dir)[1..];
                 if let Some((head, tail)) = relative_home_path.split_once(MAIN_SEPARATOR) {
-                    let mut to_hide = 0usize;
-                    for component in tail.split(MAIN_SEPARATOR) {
-                        if to_hide > gap + 2 {
-                            let mut tail = tail.to_string();
-                            tail.replace_range(..to_hide - 1, "...");
-                            return format!("~{MAIN_SEPARATOR}{head}{MAIN_SEPARATOR}{tail}").into();
-                        } else {
-                            to_hide += component.len() + 1;
-                        }
+                    if let Some(tail) = hide_leading_components(tail, gap) {
+                        return format!("~{MAIN_SEPARATOR}{head}{MAIN_SEPARATOR}{tail}").into();
                     }
                 }
             } else {
-                let top = abs_path.splitn(8, MAIN_SEPARATOR).collect::<Vec<_>>();
-                if let Some(last) = top.last() {
-                    if let Some((_head, tail)) = last.split_once(MAIN_SEPARATOR) {
-                        let mut to_hide = 0usize;
-                        for component in tail.split(MAIN_SEPARATOR) {
-                            if to_hide > gap + 2 {
-                                let mut tail = tail.to_string();
-                                tail.replace_range(..to_hide - 1, "...");
-                                let head = top
-                                    .iter()
-                                    .take(top.len() - 1)
-                                    .join(MAIN_SEPARATOR.to_string().as_str());
-                                return format!("{head}{MAIN_SEPARATOR}{tail}").into();
-                            } else {
-                                to_hide += component.len() + 1;
-                            }
-                        }
+                // The root (`/`, `C:\`, `\\server\share\`) and the first two components are
+                // always kept.
+                //
+                // /media/xlc/.../substrate/bin/node/cli/src/command_helper.rs
+                let (root, relative_path) = abs_path.split_at(root_len(abs_path));
+                let mut components = relative_path.splitn(3, MAIN_SEPARATOR);
+                if let (Some(first), Some(second), Some(tail)) =
+                    (components.next(), components.next(), components.next())
+                {
+                    if let Some(tail) = hide_leading_components(tail, gap) {
+                        return format!(
+                            "{root}{first}{MAIN_SEPARATOR}{second}{MAIN_SEPARATOR}{tail}"
+                        )
+                        .into();
                     }
                 }
             }
         } else {
-            // Truncate the left of absolute path string.
+            // Truncate the left of absolute path string, the root is always kept.
             // ../stable-x86_64-unknown-linux-gnu/lib/rustlib/src/rust/library/alloc/src/string.rs
-            if let Some((offset, _)) = abs_path.char_indices().nth(abs_path.len() - max_len + 2) {
-                let mut abs_path = abs_path.to_string();
-                abs_path.replace_range(..offset, "..");
-                return abs_path.into();
+            let offset = ceil_char_boundary(abs_path, abs_path.len() - max_len + 2);
+            if offset < abs_path.len() {
+                let root_len = root_len(abs_path);
+                if offset > root_len {
+                    let mut abs_path = abs_path.to_string();
+                    abs_path.replace_range(root_len..offset, "..");
+                    return abs_path.into();
+                }
             }
         }
     }
@@ -191,6 +381,70 @@ pub fn truncate_absolute_path(abs_path: &str, max_len: usize) -> Cow<'_, str> {
     abs_path.into()
 }
 
+/// Truncates the file `name` to at most `max_len` chars by replacing the middle part with
+/// `…`, the extension and a leading chunk are preserved.
+///
+/// very_long_generated_code_impl.rs => very_long…code_impl.rs
+pub fn truncate_filename(name: &str, max_len: usize) -> Cow<'_, str> {
+    let name_len = name.chars().count();
+    if name_len <= max_len {
+        return name.into();
+    }
+
+    let (stem, extension) = match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => (stem, &name[stem.len()..]),
+        _ => (name, ""),
+    };
+
+    // One char for `…`.
+    let stem_budget = max_len.saturating_sub(extension.chars().count() + 1);
+
+    if stem_budget < 2 {
+        // Not enough space to keep the extension.
+        let mut truncated = name
+            .chars()
+            .take(max_len.saturating_sub(1))
+            .collect::<String>();
+        truncated.push('…');
+        return truncated.into();
+    }
+
+    let stem_len = stem.chars().count();
+    let head_len = (stem_budget + 1) / 2;
+    let tail_len = stem_budget / 2;
+
+    let head = stem.chars().take(head_len);
+    let tail = stem.chars().skip(stem_len - tail_len);
+
+    head.chain(std::iter::once('…'))
+        .chain(tail)
+        .chain(extension.chars())
+        .collect::<String>()
+        .into()
+}
+
+/// Truncates the file name of `abs_path` to at most `max_filename_len` chars first and then
+/// the directory part so that the whole path fits in `max_len`.
+pub fn truncate_absolute_path_and_filename(
+    abs_path: &str,
+    max_len: usize,
+    max_filename_len: usize,
+) -> Cow<'_, str> {
+    let Some((dir, filename)) = abs_path.rsplit_once(MAIN_SEPARATOR) else {
+        return truncate_filename(abs_path, max_len);
+    };
+
+    match truncate_filename(filename, max_filename_len) {
+        Cow::Borrowed(_) => truncate_absolute_path(abs_path, max_len),
+        Cow::Owned(filename) => {
+            let abs_path = format!("{dir}{MAIN_SEPARATOR}{filename}");
+            truncate_absolute_path(&abs_path, max_len)
+                .into_owned()
+                .into()
+        }
+    }
+}
+
 // Get the current working directory.
 // This information is managed internally as the call to std::env::current_dir
 // might fail if the cwd has been deleted.
@@ -255,6 +509,31 @@ pub fn get_normalized_path(path: &Path) -> PathBuf {
     base.join(ret)
 }
 
+/// Joins `path` onto `cwd` and resolves the `.` and `..` components logically.
+///
+/// Unlike [`get_normalized_path`], the file system is never accessed, hence the symlinks
+/// are not followed and the path does not have to exist. `..` beyond the root is ignored.
+pub fn build_abs_path(cwd: &Path, path: impl AsRef<Path>) -> PathBuf {
+    let mut abs_path = PathBuf::new();
+    for component in cwd.join(path).components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                abs_path.pop();
+            }
+            component => abs_path.push(component.as_os_str()),
+        }
+    }
+    abs_path
+}
+
+/// Files or directories indicating the root of a project.
+pub const DEFAULT_ROOT_MARKERS: &[&str] = &[".git", ".hg", ".svn", "Cargo.toml", "go.mod"];
+
+/// Returns the nearest ancestor of `start_dir`, including itself, containing any of
+/// `root_markers`.
+///
+/// The search stops at the root of file system, nothing is cached.
 pub fn find_project_root<'a, P: AsRef<Path>>(
     start_dir: &'a Path,
     root_markers: &[P],
@@ -280,15 +559,9 @@ fn upward_search<F>(path: &Path, predicate: F) -> std::io::Result<&Path>
 where
     F: Fn(&Path) -> bool,
 {
-    if predicate(path) {
-        return Ok(path);
-    }
-
-    let next_path = path.parent().ok_or_else(|| {
-        std::io::Error::new(std::io::ErrorKind::NotFound, "Reached root directory")
-    })?;
-
-    upward_search(next_path, predicate)
+    path.ancestors()
+        .find(|path| predicate(path))
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "Reached root directory"))
 }
 
 #[cfg(test)]
@@ -321,4 +594,190 @@ mod tests {
             "/Users/xuliucheng/src/github.com/subspace/subspace/crates/pallet-domains/src/lib.rs";
         println!("{:?}", truncate_absolute_path(abs_path, 60));
     }
+
+    #[test]
+    #[cfg(not(target_os = "windows"))]
+    fn test_truncate_non_ascii_path() {
+        let home_dir = Dirs::base().home_dir().to_str().unwrap();
+        let abs_path = format!("{home_dir}/项目/源代码/模块/子模块/很长的目录名/很长的文件名.rs");
+        let max_len = abs_path.len() - home_dir.len() - 10;
+        let truncated = truncate_absolute_path(&abs_path, max_len);
+        assert!(truncated.starts_with("~/项目/..."));
+        assert!(truncated.ends_with("/很长的文件名.rs"));
+
+        let abs_path = "/数据/项目/源代码/模块/子模块/很长的目录名/很长的文件名.rs";
+        if !abs_path.starts_with(home_dir) {
+            assert_eq!(
+                truncate_absolute_path(abs_path, 60),
+                "/数据/项目/.../很长的目录名/很长的文件名.rs"
+            );
+        }
+    }
+
+    #[test]
+    fn test_truncate_filename() {
+        assert!(matches!(
+            truncate_filename("lib.rs", 10),
+            Cow::Borrowed("lib.rs")
+        ));
+        assert_eq!(
+            truncate_filename("very_long_generated_code_impl.rs", 22),
+            "very_long…code_impl.rs"
+        );
+        assert_eq!(
+            truncate_filename("很长很长的文件名字.rs", 8),
+            "很长…名字.rs"
+        );
+        assert_eq!(truncate_filename("Makefile_generated", 10), "Makef…ated");
+        assert_eq!(
+            truncate_filename("a_very_long_name.extension", 8),
+            "a_very_…"
+        );
+    }
+
+    #[test]
+    fn test_expand_tilde() {
+        let home_dir = Dirs::base().home_dir();
+        assert_eq!(expand_tilde("~"), home_dir);
+        assert_eq!(
+            expand_tilde(format!("~{MAIN_SEPARATOR}foo")),
+            home_dir.join("foo")
+        );
+        assert_eq!(expand_tilde("foo/~"), PathBuf::from("foo/~"));
+        assert_eq!(
+            expand_tilde("~no_such_user_for_vim_clap/bar"),
+            PathBuf::from("~no_such_user_for_vim_clap/bar")
+        );
+    }
+
+    #[test]
+    fn test_to_home_relative() {
+        let home_dir = Dirs::base().home_dir();
+        assert_eq!(to_home_relative(home_dir), "~");
+        assert_eq!(
+            to_home_relative(&home_dir.join("src").join("lib.rs")),
+            format!("~{MAIN_SEPARATOR}src{MAIN_SEPARATOR}lib.rs")
+        );
+
+        let mut sibling = home_dir.as_os_str().to_owned();
+        sibling.push("_sibling");
+        assert_eq!(
+            to_home_relative(Path::new(&sibling)),
+            sibling.to_string_lossy()
+        );
+    }
+
+    #[test]
+    fn test_relative_to() {
+        let home_dir = Dirs::base().home_dir();
+        let base = home_dir.join("proj");
+
+        let inside = base.join("src").join("foo.rs");
+        assert_eq!(
+            relative_to(&inside, &base),
+            format!("src{MAIN_SEPARATOR}foo.rs")
+        );
+        let mut base_with_separator = base.as_os_str().to_owned();
+        base_with_separator.push(MAIN_SEPARATOR.to_string());
+        assert_eq!(
+            relative_to(&inside, Path::new(&base_with_separator)),
+            format!("src{MAIN_SEPARATOR}foo.rs")
+        );
+        assert_eq!(relative_to(&base, &base), ".");
+
+        let outside = home_dir.join("other").join("bar.rs");
+        assert_eq!(
+            relative_to(&outside, &base),
+            format!("~{MAIN_SEPARATOR}other{MAIN_SEPARATOR}bar.rs")
+        );
+
+        let mut sibling = base.as_os_str().to_owned();
+        sibling.push("_sibling");
+        assert_eq!(
+            relative_to(Path::new(&sibling), &base),
+            to_home_relative(Path::new(&sibling))
+        );
+
+        let deep = ["crates", "maple_core", "src", "tools", "rg", "mod.rs"]
+            .iter()
+            .fold(base.clone(), |path, component| path.join(component));
+        assert_eq!(
+            truncate_relative_path(&deep, &base, 30),
+            ["crates", "maple_core", "...", "rg", "mod.rs"].join(&MAIN_SEPARATOR.to_string())
+        );
+    }
+
+    #[test]
+    fn test_find_project_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let nested = root.join("crates").join("foo").join("src");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::create_dir_all(root.join(".git")).unwrap();
+        std::fs::write(root.join("crates").join("foo").join("Cargo.toml"), "").unwrap();
+
+        assert_eq!(
+            find_project_root(&nested, &["Cargo.toml"]),
+            Some(root.join("crates").join("foo").as_path())
+        );
+        assert_eq!(find_project_root(&nested, &[".git"]), Some(root));
+        assert_eq!(find_project_root(root, DEFAULT_ROOT_MARKERS), Some(root));
+        assert_eq!(
+            find_project_root(&nested, &["no_such_marker_for_vim_clap"]),
+            None
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_build_abs_path() {
+        let cwd = Path::new("/home/user/project");
+        assert_eq!(
+            build_abs_path(cwd, "../src/foo.rs"),
+            PathBuf::from("/home/user/src/foo.rs")
+        );
+        assert_eq!(
+            build_abs_path(cwd, "././a/./b"),
+            PathBuf::from("/home/user/project/a/b")
+        );
+        assert_eq!(
+            build_abs_path(cwd, "../../../../../etc/./hosts"),
+            PathBuf::from("/etc/hosts")
+        );
+        assert_eq!(build_abs_path(cwd, "a/../.."), PathBuf::from("/home/user"));
+        assert_eq!(
+            build_abs_path(cwd, "/tmp/../var/log"),
+            PathBuf::from("/var/log")
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_user_home_dir() {
+        let root_home = user_home_dir("root").unwrap();
+        assert!(root_home.is_absolute());
+        assert_eq!(expand_tilde("~root/bar"), root_home.join("bar"));
+        assert_eq!(user_home_dir("no_such_user_for_vim_clap"), None);
+        assert_eq!(user_home_dir("nul\0user"), None);
+    }
+
+    #[test]
+    #[cfg(target_os = "windows")]
+    fn test_truncate_windows_path() {
+        // Verbatim path on a drive other than the home drive.
+        let abs_path =
+            r#"\\?\D:\src\github.com\paritytech\substrate\bin\node\cli\src\command_helper.rs"#;
+        let expected = r#"D:\src\github.com\...\cli\src\command_helper.rs"#;
+        assert_eq!(truncate_absolute_path(abs_path, 50), expected);
+
+        let abs_path = r#"\\?\UNC\server\share\src\github.com\paritytech\substrate\bin\node\cli\src\command_helper.rs"#;
+        let expected = r#"\\server\share\src\github.com\...\command_helper.rs"#;
+        assert_eq!(truncate_absolute_path(abs_path, 50), expected);
+
+        // Short enough, only the verbatim prefix is stripped.
+        assert!(matches!(
+            truncate_absolute_path(r#"\\?\D:\src\lib.rs"#, 50),
+            Cow::Borrowed(r#"D:\src\lib.rs"#)
+        ));
+    }
 }
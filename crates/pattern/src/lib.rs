@@ -131,6 +131,12 @@ pub fn extract_proj_tags(line: &str) -> Option<(usize, &str)> {
     Some((lnum, fpath))
 }
 
+/// Returns the qualified tag name of the line in proj_tags provider.
+pub fn extract_proj_tags_name(line: &str) -> Option<&str> {
+    let cap = PROJ_TAGS.captures(line)?;
+    cap.get(1).map(|x| x.as_str())
+}
+
 pub fn extract_proj_tags_kind(line: &str) -> Option<&str> {
     let cap = PROJ_TAGS.captures(line)?;
     let kind = cap.get(3).map(|x| x.as_str())?;
@@ -43,6 +43,30 @@ macro_rules! println_json_with_length {
   }
 }
 
+/// Combine json and println macro, in the newline-delimited JSON framing.
+///
+/// Each message is printed as one compact JSON object terminated by a single `\n`
+/// and flushed immediately, which suits the long-lived stream of responses.
+#[macro_export]
+macro_rules! println_ndjson {
+  ( $( $field:expr ),+ ) => {
+    {
+      let msg = serde_json::json!({ $(stringify!($field): $field,)* });
+      let _ = $crate::write_ndjson(&mut std::io::stdout().lock(), &msg);
+    }
+  }
+}
+
+/// Writes `msg` as a compact JSON object followed by a single `\n`.
+///
+/// The newlines inside the strings are always escaped in the compact JSON, hence the
+/// receiver can safely split the records on newlines.
+pub fn write_ndjson<W: std::io::Write>(writer: &mut W, msg: &Value) -> std::io::Result<()> {
+    serde_json::to_writer(&mut *writer, msg)?;
+    writer.write_all(b"\n")?;
+    writer.flush()
+}
+
 /// This structure holds the data that can be easily used to update the UI on the Vim side.
 ///
 /// Potential processing to the display text:
@@ -260,6 +284,25 @@ pub(crate) mod tests {
     use std::sync::Arc;
     use types::{ClapItem, Query};
 
+    #[test]
+    fn test_write_ndjson() {
+        let mut buf = Vec::new();
+        let records = [
+            serde_json::json!({ "total": 2, "lines": ["foo\nbar", "baz"] }),
+            serde_json::json!({ "total": 0, "lines": [] }),
+        ];
+        for record in &records {
+            write_ndjson(&mut buf, record).unwrap();
+        }
+
+        let output = String::from_utf8(buf).unwrap();
+        assert!(output.ends_with("}\n"));
+        assert_eq!(output.matches('\n').count(), records.len());
+        for (line, record) in output.lines().zip(records.iter()) {
+            assert_eq!(&serde_json::from_str::<Value>(line).unwrap(), record);
+        }
+    }
+
     pub(crate) fn wrap_matches(line: &str, indices: &[usize]) -> String {
         let mut ret = String::new();
         let mut peekable = indices.iter().peekable();
@@ -4,7 +4,7 @@ mod search_term;
 mod source_item;
 
 pub use self::matcher::{parse_criteria, MatchResult, Rank, RankCalculator, RankCriterion, Score};
-pub use self::query::Query;
+pub use self::query::{split_jump_target, JumpTarget, Query};
 pub use self::search_term::{
     ExactTerm, ExactTermType, FuzzyTerm, FuzzyTermType, InverseTerm, InverseTermType, SearchTerm,
     TermType, WordTerm,
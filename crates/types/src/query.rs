@@ -43,3 +43,79 @@ impl Query {
         self.fuzzy_terms.iter().map(|f| f.len()).sum()
     }
 }
+
+/// Position parsed from the trailing `:<line>[:<column>]` of the query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JumpTarget {
+    pub line: usize,
+    pub column: Option<usize>,
+}
+
+/// Splits the trailing `:<line>` or `:<line>:<column>` off `query`, e.g., `utils.rs:120`,
+/// returns the path part and the jump target if any.
+///
+/// Only the final number groups are parsed, the drive colon in `C:\foo.rs:12` is kept
+/// in the path part.
+pub fn split_jump_target(query: &str) -> (&str, Option<JumpTarget>) {
+    fn split_number(s: &str) -> Option<(&str, usize)> {
+        let (rest, number) = s.rsplit_once(':')?;
+        if number.is_empty() || !number.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        Some((rest, number.parse().ok()?))
+    }
+
+    let Some((rest, last)) = split_number(query) else {
+        return (query, None);
+    };
+
+    let (path, jump_target) = match split_number(rest) {
+        Some((path, line)) if !path.is_empty() => (
+            path,
+            JumpTarget {
+                line,
+                column: Some(last),
+            },
+        ),
+        _ => (
+            rest,
+            JumpTarget {
+                line: last,
+                column: None,
+            },
+        ),
+    };
+
+    if path.is_empty() {
+        (query, None)
+    } else {
+        (path, Some(jump_target))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_jump_target() {
+        let target = |line, column| Some(JumpTarget { line, column });
+
+        assert_eq!(split_jump_target("utils.rs"), ("utils.rs", None));
+        assert_eq!(split_jump_target("utils.rs:"), ("utils.rs:", None));
+        assert_eq!(
+            split_jump_target("utils.rs:120"),
+            ("utils.rs", target(120, None))
+        );
+        assert_eq!(
+            split_jump_target("utils.rs:120:8"),
+            ("utils.rs", target(120, Some(8)))
+        );
+        assert_eq!(
+            split_jump_target(r"C:\src\utils.rs:120"),
+            (r"C:\src\utils.rs", target(120, None))
+        );
+        assert_eq!(split_jump_target(":120"), (":120", None));
+        assert_eq!(split_jump_target("utils.rs:1a"), ("utils.rs:1a", None));
+    }
+}
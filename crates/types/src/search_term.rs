@@ -1,4 +1,4 @@
-use crate::Score;
+use crate::{CaseMatching, Score};
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum ExactTermType {
@@ -97,6 +97,21 @@ impl InverseTerm {
             InverseTermType::InverseSuffixExact => haystack.ends_with(niddle),
         }
     }
+
+    /// Same as [`Self::exact_matched`], but compares the texts according to `case_matching`.
+    pub fn matched_with_case(&self, full_search_line: &str, case_matching: CaseMatching) -> bool {
+        if case_matching.is_case_sensitive(&self.text) {
+            return self.exact_matched(full_search_line);
+        }
+
+        let niddle = self.text.to_lowercase();
+        let haystack = full_search_line.trim().to_lowercase();
+        match self.ty {
+            InverseTermType::InverseExact => haystack.contains(&niddle),
+            InverseTermType::InversePrefixExact => haystack.starts_with(&niddle),
+            InverseTermType::InverseSuffixExact => haystack.ends_with(&niddle),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -1,6 +1,15 @@
+use flate2::read::MultiGzDecoder;
+use futures::Stream;
+use ignore::overrides::OverrideBuilder;
+use ignore::WalkBuilder;
+use rayon::prelude::*;
+use std::collections::HashMap;
 use std::fs::{read_dir, remove_dir_all, remove_file, File};
-use std::io::{BufRead, BufReader, Lines, Result};
-use std::path::Path;
+use std::io::{BufRead, BufReader, Error, ErrorKind, Lines, Read, Result, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt};
 
 /// Counts lines in the source `handle`.
 ///
@@ -28,6 +37,264 @@ pub fn count_lines<R: std::io::Read>(handle: R) -> std::io::Result<usize> {
     Ok(count)
 }
 
+/// Number of lines counted by [`count_lines_async`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineCount {
+    /// Number of lines counted so far.
+    pub count: usize,
+    /// Whether the counting was cancelled before reaching EOF.
+    pub truncated: bool,
+}
+
+/// Async version of [`line_count`], which can be abandoned by setting `cancelled`.
+///
+/// The partial count is returned with `truncated` set once cancelled.
+pub async fn count_lines_async<P: AsRef<Path>>(
+    path: P,
+    cancelled: &AtomicBool,
+) -> Result<LineCount> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let mut buf = vec![0u8; 1024 * 32];
+    let mut count = 0;
+
+    loop {
+        if cancelled.load(Ordering::SeqCst) {
+            return Ok(LineCount {
+                count,
+                truncated: true,
+            });
+        }
+
+        // Each read is a yield point, so the runtime is not blocked on huge files.
+        let len = file.read(&mut buf).await?;
+        if len == 0 {
+            break;
+        }
+        count += bytecount::count(&buf[..len], b'\n');
+    }
+
+    Ok(LineCount {
+        count,
+        truncated: false,
+    })
+}
+
+/// Returns a stream of the lines in file `path`, the new lines are emitted as the file grows.
+///
+/// The file is polled every `poll_interval` after reaching the end. It's reopened from the
+/// beginning once being truncated or replaced, e.g., by the log rotation. The incomplete
+/// last line is held back until the newline is written. The stream never ends, drop it
+/// to stop tailing.
+pub fn tail_lines(path: impl Into<PathBuf>, poll_interval: Duration) -> impl Stream<Item = String> {
+    struct Tail {
+        path: PathBuf,
+        /// Opened file and its identity.
+        reader: Option<(tokio::io::BufReader<tokio::fs::File>, Option<u64>)>,
+        /// Number of bytes read from the opened file.
+        pos: u64,
+        buf: Vec<u8>,
+    }
+
+    impl Tail {
+        /// Returns `true` if the opened file is no longer the one at `path`.
+        async fn is_stale(&self, file_id: Option<u64>) -> bool {
+            match tokio::fs::metadata(&self.path).await {
+                Ok(metadata) => {
+                    metadata.len() < self.pos
+                        || (file_id.is_some() && self::file_id(&metadata) != file_id)
+                }
+                Err(_) => true,
+            }
+        }
+    }
+
+    let tail = Tail {
+        path: path.into(),
+        reader: None,
+        pos: 0,
+        buf: Vec::new(),
+    };
+
+    futures::stream::unfold(tail, move |mut tail| async move {
+        loop {
+            let Some((reader, file_id)) = tail.reader.as_mut() else {
+                match tokio::fs::File::open(&tail.path).await {
+                    Ok(file) => {
+                        let file_id = file.metadata().await.ok().and_then(|m| file_id(&m));
+                        tail.reader = Some((tokio::io::BufReader::new(file), file_id));
+                        tail.pos = 0;
+                        tail.buf.clear();
+                    }
+                    Err(_) => tokio::time::sleep(poll_interval).await,
+                }
+                continue;
+            };
+            let file_id = *file_id;
+
+            match reader.read_until(b'\n', &mut tail.buf).await {
+                Ok(n) if n > 0 => {
+                    tail.pos += n as u64;
+                    if let Some(line) = tail.buf.strip_suffix(b"\n") {
+                        let line = line.strip_suffix(b"\r").unwrap_or(line);
+                        let line = String::from_utf8_lossy(line).into_owned();
+                        tail.buf.clear();
+                        return Some((line, tail));
+                    }
+                }
+                Ok(_) => {}
+                Err(_) => {
+                    tail.reader = None;
+                    tokio::time::sleep(poll_interval).await;
+                    continue;
+                }
+            }
+
+            // Reached the end of file.
+            if tail.is_stale(file_id).await {
+                tail.reader = None;
+            } else {
+                tokio::time::sleep(poll_interval).await;
+            }
+        }
+    })
+}
+
+/// Returns the identity of a file, which changes when the file at the same path is replaced.
+#[cfg(unix)]
+fn file_id(metadata: &std::fs::Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.ino())
+}
+
+#[cfg(not(unix))]
+fn file_id(_metadata: &std::fs::Metadata) -> Option<u64> {
+    None
+}
+
+/// Magic bytes of the gzip format.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Number of bytes inspected by [`is_probably_binary`].
+const BINARY_SAMPLE_SIZE: usize = 8 * 1024;
+
+/// Returns `true` if `sample`, typically the beginning of a file, looks like binary data.
+///
+/// Only the first 8KiB is inspected, the sample is considered binary if it contains
+/// a NUL byte or the control chars other than the common whitespaces take up more
+/// than 10% of it.
+pub fn is_probably_binary(sample: &[u8]) -> bool {
+    let sample = &sample[..sample.len().min(BINARY_SAMPLE_SIZE)];
+    if sample.contains(&0) {
+        return true;
+    }
+    let control_chars = sample
+        .iter()
+        .filter(|&&b| b < 0x20 && !matches!(b, b'\t' | b'\n' | b'\r' | 0x0c | 0x1b))
+        .count();
+    control_chars * 10 > sample.len()
+}
+
+/// Line total of the file counted by [`count_lines_auto`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileLines {
+    /// Number of lines in the text file.
+    Text(usize),
+    /// The file is probably binary, see [`is_probably_binary`], the lines are not counted.
+    Binary,
+}
+
+/// Counts lines of the file at `path`, the gzip-compressed file is decompressed on the fly.
+///
+/// Returns [`FileLines::Binary`] instead of a meaningless count if the (decompressed)
+/// content looks like binary.
+pub fn count_lines_auto(path: &Path) -> Result<FileLines> {
+    fn count_text_lines<R: Read>(handle: R) -> Result<FileLines> {
+        let mut reader = BufReader::with_capacity(BINARY_SAMPLE_SIZE, handle);
+        if is_probably_binary(reader.fill_buf()?) {
+            return Ok(FileLines::Binary);
+        }
+        count_lines(reader).map(FileLines::Text)
+    }
+
+    let mut file = File::open(path)?;
+
+    let mut magic = [0u8; 2];
+    let is_gzip = file.read_exact(&mut magic).is_ok() && magic == GZIP_MAGIC;
+    file.seek(SeekFrom::Start(0))?;
+
+    if is_gzip {
+        count_text_lines(MultiGzDecoder::new(file))
+    } else {
+        count_text_lines(file)
+    }
+}
+
+/// Line totals of the files under a directory counted by [`count_lines_tree`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TreeLines {
+    /// Number of lines in all the text files.
+    pub total: usize,
+    /// Number of lines per file extension, the files without extension are under `""`.
+    pub by_extension: HashMap<String, usize>,
+}
+
+/// Counts lines of all the text files under `root` in parallel, see [`count_lines_auto`].
+///
+/// The paths matching any glob in `excludes`, e.g., `vendor/**`, are skipped. The hidden
+/// files are always skipped, the ignore files like `.gitignore` are respected if
+/// `respect_ignore` is true.
+pub fn count_lines_tree(
+    root: &Path,
+    excludes: &[String],
+    respect_ignore: bool,
+) -> Result<TreeLines> {
+    let mut overrides = OverrideBuilder::new(root);
+    for glob in excludes {
+        overrides
+            .add(&format!("!{glob}"))
+            .map_err(|e| Error::new(ErrorKind::InvalidInput, e.to_string()))?;
+    }
+    let overrides = overrides
+        .build()
+        .map_err(|e| Error::new(ErrorKind::InvalidInput, e.to_string()))?;
+
+    let files = WalkBuilder::new(root)
+        .overrides(overrides)
+        .git_ignore(respect_ignore)
+        .git_global(respect_ignore)
+        .git_exclude(respect_ignore)
+        .ignore(respect_ignore)
+        .parents(respect_ignore)
+        .build()
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            entry.file_type()?.is_file().then(|| entry.into_path())
+        })
+        .collect::<Vec<_>>();
+
+    let file_lines = files
+        .into_par_iter()
+        .filter_map(|path| match count_lines_auto(&path).ok()? {
+            FileLines::Text(lines) => {
+                let extension = path
+                    .extension()
+                    .map(|ext| ext.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                Some((extension, lines))
+            }
+            FileLines::Binary => None,
+        })
+        .collect::<Vec<_>>();
+
+    let mut tree_lines = TreeLines::default();
+    for (extension, lines) in file_lines {
+        tree_lines.total += lines;
+        *tree_lines.by_extension.entry(extension).or_insert(0) += lines;
+    }
+
+    Ok(tree_lines)
+}
+
 /// Returns the number of total lines of given filepath.
 pub fn line_count<P: AsRef<Path>>(path: P) -> std::io::Result<usize> {
     count_lines(std::fs::File::open(path)?)
@@ -76,6 +343,74 @@ pub fn remove_dir_contents<P: AsRef<Path>>(target_dir: P) -> Result<()> {
     Ok(())
 }
 
+/// Removes the oldest files under `dir` until none of the remaining files is older
+/// than `max_age` and their total size does not exceed `max_total_bytes`.
+///
+/// The files modified within `grace_period` are always kept, even if the size limit
+/// is still exceeded afterwards. Returns the paths of removed files.
+///
+/// The files and directories removed concurrently, e.g., by another Vim instance, are
+/// skipped instead of failing the whole pruning.
+pub fn prune_dir<P: AsRef<Path>>(
+    dir: P,
+    max_age: Duration,
+    max_total_bytes: u64,
+    grace_period: Duration,
+) -> Result<Vec<PathBuf>> {
+    let now = SystemTime::now();
+
+    let mut files = Vec::new();
+    let mut dirs = vec![dir.as_ref().to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let Some(entries) = ignore_not_found(read_dir(dir))? else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let Some(metadata) = ignore_not_found(entry.metadata())? else {
+                continue;
+            };
+            if metadata.is_dir() {
+                dirs.push(entry.path());
+            } else {
+                // The file without a valid modification time is considered as fresh.
+                let age = metadata
+                    .modified()
+                    .ok()
+                    .and_then(|modified| now.duration_since(modified).ok())
+                    .unwrap_or_default();
+                files.push((entry.path(), age, metadata.len()));
+            }
+        }
+    }
+
+    // Oldest first.
+    files.sort_unstable_by(|a, b| b.1.cmp(&a.1));
+
+    let mut total_bytes = files.iter().map(|(_, _, len)| len).sum::<u64>();
+    let mut removed = Vec::new();
+    for (path, age, len) in files {
+        if age < grace_period || (age <= max_age && total_bytes <= max_total_bytes) {
+            continue;
+        }
+        let removed_by_us = ignore_not_found(remove_file(&path))?.is_some();
+        total_bytes -= len;
+        if removed_by_us {
+            removed.push(path);
+        }
+    }
+
+    Ok(removed)
+}
+
+/// Converts the error of [`ErrorKind::NotFound`] to `None`.
+fn ignore_not_found<T>(result: Result<T>) -> Result<Option<T>> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
 /// Attempts to write an entire buffer into the file.
 ///
 /// Creates one if the file does not exist.
@@ -95,6 +430,39 @@ pub fn create_or_overwrite<P: AsRef<Path>>(path: P, buf: &[u8]) -> Result<()> {
     Ok(())
 }
 
+/// Writes an entire buffer into the file atomically.
+///
+/// The buffer is written to a temporary file next to `path` first and then renamed
+/// to `path`, so that a crash in the middle of writing never leaves a truncated file.
+pub fn write_atomically<P: AsRef<Path>>(path: P, buf: &[u8]) -> Result<()> {
+    let path = path.as_ref();
+
+    // Suffixed with the process id so that the concurrent writers do not clobber
+    // the temporary file of each other.
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(format!(".{}.tmp", std::process::id()));
+    let tmp_path = std::path::PathBuf::from(tmp_path);
+
+    {
+        let mut f = File::create(&tmp_path)?;
+        std::io::Write::write_all(&mut f, buf)?;
+        f.sync_all()?;
+    }
+
+    if let Err(err) = std::fs::rename(&tmp_path, path) {
+        // Renaming over an existing file may fail on Windows, e.g., the file is
+        // being read by another process, retry after removing it.
+        #[cfg(windows)]
+        if path.exists() && remove_file(path).is_ok() && std::fs::rename(&tmp_path, path).is_ok() {
+            return Ok(());
+        }
+        let _ = remove_file(&tmp_path);
+        return Err(err);
+    }
+
+    Ok(())
+}
+
 /// Returns an Iterator to the Reader of the lines of the file.
 ///
 /// The output is wrapped in a Result to allow matching on errors.
@@ -125,7 +493,60 @@ pub fn read_first_lines<P: AsRef<Path>>(
     read_lines_from(path, 0usize, number)
 }
 
+/// Returns the first `number` lines and the total number of lines given the file path.
+///
+/// Unlike [`read_first_lines`], the rest of file is scanned for counting the lines.
+pub fn read_first_lines_with_total<P: AsRef<Path>>(
+    path: P,
+    number: usize,
+) -> Result<(Vec<String>, usize)> {
+    let mut reader = BufReader::new(File::open(path)?);
+
+    let mut lines = Vec::with_capacity(number);
+    let mut buf = Vec::new();
+    while lines.len() < number {
+        buf.clear();
+        if reader.read_until(b'\n', &mut buf)? == 0 {
+            break;
+        }
+        lines.push(decode_line(&buf));
+    }
+
+    let mut rest = 0;
+    let mut last_byte = None;
+    loop {
+        let len = {
+            let buf = reader.fill_buf()?;
+            if buf.is_empty() {
+                break;
+            }
+            rest += bytecount::count(buf, b'\n');
+            last_byte = buf.last().copied();
+            buf.len()
+        };
+        reader.consume(len);
+    }
+    // The last line without a trailing newline.
+    if last_byte.is_some_and(|b| b != b'\n') {
+        rest += 1;
+    }
+
+    let total = lines.len() + rest;
+
+    Ok((lines, total))
+}
+
+/// Decodes a line lossily with the line ending `\n` or `\r\n` stripped.
+fn decode_line(line: &[u8]) -> String {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+    String::from_utf8_lossy(line).into_owned()
+}
+
 /// Returns a `number` of lines starting from the line number `from` (0-based).
+///
+/// The line ending `\n` or `\r\n` is stripped, the invalid UTF-8 sequences are replaced
+/// with `U+FFFD` instead of dropping the line.
 pub fn read_lines_from<P: AsRef<Path>>(
     path: P,
     from: usize,
@@ -133,10 +554,11 @@ pub fn read_lines_from<P: AsRef<Path>>(
 ) -> Result<impl Iterator<Item = String>> {
     let file = File::open(path)?;
     Ok(BufReader::new(file)
-        .lines()
+        .split(b'\n')
         .skip(from)
         .filter_map(Result::ok)
-        .take(number))
+        .take(number)
+        .map(|line| decode_line(&line)))
 }
 
 /// Works for utf-8 lines only.
@@ -158,9 +580,198 @@ fn read_preview_lines_utf8<P: AsRef<Path>>(
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_read_first_lines_crlf() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("crlf.log");
+        create_or_overwrite(&path, b"line1\r\nline2\r\nb\xffr\r\nline4\r\nline5").unwrap();
+
+        assert_eq!(
+            read_first_lines(&path, 3).unwrap().collect::<Vec<_>>(),
+            vec!["line1", "line2", "b\u{FFFD}r"]
+        );
+
+        let (lines, total) = read_first_lines_with_total(&path, 2).unwrap();
+        assert_eq!(lines, vec!["line1", "line2"]);
+        assert_eq!(total, 5);
+
+        let (lines, total) = read_first_lines_with_total(&path, 10).unwrap();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[4], "line5");
+        assert_eq!(total, 5);
+    }
+
     #[test]
     fn test_count_lines() {
         let f: &[u8] = b"some text\nwith\nfour\nlines\n";
         assert_eq!(count_lines(f).unwrap(), 4);
     }
+
+    #[tokio::test]
+    async fn test_count_lines_async() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("lines.log");
+        create_or_overwrite(&path, b"some text\nwith\nfour\nlines\n").unwrap();
+
+        let cancelled = AtomicBool::new(false);
+        assert_eq!(
+            count_lines_async(&path, &cancelled).await.unwrap(),
+            LineCount {
+                count: 4,
+                truncated: false
+            }
+        );
+
+        cancelled.store(true, Ordering::SeqCst);
+        assert!(
+            count_lines_async(&path, &cancelled)
+                .await
+                .unwrap()
+                .truncated
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tail_lines() {
+        use futures::StreamExt;
+        use std::io::Write;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tail.log");
+        create_or_overwrite(&path, b"line1\r\nline2\n").unwrap();
+
+        let mut lines = Box::pin(tail_lines(&path, Duration::from_millis(10)));
+        assert_eq!(lines.next().await.unwrap(), "line1");
+        assert_eq!(lines.next().await.unwrap(), "line2");
+
+        let append = |buf: &[u8]| {
+            let mut f = File::options().append(true).open(&path).unwrap();
+            f.write_all(buf).unwrap();
+        };
+
+        append(b"line3\npart");
+        assert_eq!(lines.next().await.unwrap(), "line3");
+        append(b"ial\n");
+        assert_eq!(lines.next().await.unwrap(), "partial");
+
+        // Truncated.
+        create_or_overwrite(&path, b"new\n").unwrap();
+        assert_eq!(lines.next().await.unwrap(), "new");
+    }
+
+    #[test]
+    fn test_count_lines_auto() {
+        use flate2::write::GzEncoder;
+        use flate2::Compression;
+        use std::io::Write;
+
+        let text = b"some text\nwith\nfour\nlines\n";
+        let dir = tempfile::tempdir().unwrap();
+
+        let gz_path = dir.path().join("lines.log.gz");
+        let mut encoder = GzEncoder::new(File::create(&gz_path).unwrap(), Compression::default());
+        encoder.write_all(text).unwrap();
+        encoder.finish().unwrap();
+        assert_eq!(count_lines_auto(&gz_path).unwrap(), FileLines::Text(4));
+
+        let plain_path = dir.path().join("lines.log");
+        create_or_overwrite(&plain_path, text).unwrap();
+        assert_eq!(count_lines_auto(&plain_path).unwrap(), FileLines::Text(4));
+
+        let binary_path = dir.path().join("lines.bin");
+        create_or_overwrite(&binary_path, b"\x7fELF\x02\x01\x01\0\n\n").unwrap();
+        assert_eq!(count_lines_auto(&binary_path).unwrap(), FileLines::Binary);
+    }
+
+    #[test]
+    fn test_count_lines_tree() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::create_dir_all(root.join("vendor/dep")).unwrap();
+
+        create_or_overwrite(root.join("src/main.rs"), b"fn main() {\n}\n").unwrap();
+        create_or_overwrite(root.join("src/lib.rs"), b"mod a;\nmod b;\nmod c;\n").unwrap();
+        create_or_overwrite(root.join("README.md"), b"# readme\n").unwrap();
+        create_or_overwrite(root.join("Makefile"), b"all:\n").unwrap();
+        create_or_overwrite(root.join("logo.png"), b"\x89PNG\r\n\x1a\n\0\0\n").unwrap();
+        create_or_overwrite(root.join("vendor/dep/lib.rs"), b"\n\n\n\n\n").unwrap();
+
+        let tree_lines = count_lines_tree(root, &["vendor/**".into()], false).unwrap();
+        assert_eq!(tree_lines.total, 7);
+        assert_eq!(
+            tree_lines.by_extension,
+            HashMap::from([("rs".into(), 5), ("md".into(), 1), ("".into(), 1)])
+        );
+
+        assert_eq!(count_lines_tree(root, &[], false).unwrap().total, 12);
+    }
+
+    #[test]
+    fn test_is_probably_binary() {
+        assert!(!is_probably_binary(b""));
+        assert!(!is_probably_binary(
+            b"fn main() {\n\tprintln!(\"\x1b[1mhi\");\r\n}\n"
+        ));
+        assert!(!is_probably_binary("中文 text\n".as_bytes()));
+        assert!(is_probably_binary(b"text\0with a NUL byte\n"));
+        assert!(is_probably_binary(b"\x01\x02\x03\x04 mostly control chars"));
+    }
+
+    #[test]
+    fn test_prune_dir() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let dir = tempdir.path().join("cache");
+        std::fs::create_dir_all(dir.join("nested")).unwrap();
+
+        let hour = Duration::from_secs(3600);
+        let create_file = |name: &str, size: usize, age: Duration| {
+            let path = dir.join(name);
+            create_or_overwrite(&path, &vec![b'x'; size]).unwrap();
+            File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(SystemTime::now() - age)
+                .unwrap();
+            path
+        };
+
+        let expired = create_file("expired", 10, 48 * hour);
+        let oldest = create_file("nested/oldest", 100, 10 * hour);
+        let older = create_file("older", 100, 5 * hour);
+        let _fresh = create_file("fresh", 100, Duration::ZERO);
+
+        // `older` is kept as the size limit is satisfied after removing `oldest`.
+        let removed = prune_dir(&dir, 24 * hour, 250, hour).unwrap();
+        assert_eq!(removed, vec![expired, oldest]);
+        assert!(older.exists());
+
+        // The fresh file is kept even if the size limit is still exceeded.
+        let removed = prune_dir(&dir, 24 * hour, 0, hour).unwrap();
+        assert_eq!(removed, vec![older]);
+        assert_eq!(read_dir(dir.join("nested")).unwrap().count(), 0);
+        assert!(dir.join("fresh").exists());
+
+        let _ = remove_dir_all(&dir);
+
+        // The directory removed concurrently has nothing to prune.
+        assert!(prune_dir(&dir, 24 * hour, 0, hour).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_write_atomically() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data.json");
+
+        write_atomically(&path, b"[1, 2, 3]").unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"[1, 2, 3]");
+
+        write_atomically(&path, b"[]").unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"[]");
+
+        let mut tmp_path = path.as_os_str().to_owned();
+        tmp_path.push(format!(".{}.tmp", std::process::id()));
+        assert!(!Path::new(&tmp_path).exists());
+    }
 }
@@ -3,16 +3,21 @@
 use std::collections::hash_map::DefaultHasher;
 use std::ffi::OsStr;
 use std::hash::{Hash, Hasher};
+use std::ops::Range;
 use std::path::Path;
 use std::process::{Command, Output};
 
 pub mod bytelines;
 mod io;
+mod top_n;
 
 pub use self::io::{
-    count_lines, create_or_overwrite, file_size, line_count, read_first_lines, read_line_at,
-    read_lines, read_lines_from, remove_dir_contents, SizeChecker,
+    count_lines, count_lines_async, count_lines_auto, count_lines_tree, create_or_overwrite,
+    file_size, is_probably_binary, line_count, prune_dir, read_first_lines,
+    read_first_lines_with_total, read_line_at, read_lines, read_lines_from, remove_dir_contents,
+    tail_lines, write_atomically, FileLines, LineCount, SizeChecker, TreeLines,
 };
+pub use self::top_n::TopN;
 
 /// Returns the width of displaying `n` on the screen.
 ///
@@ -32,6 +37,18 @@ pub fn display_width(n: usize) -> usize {
     len
 }
 
+/// Returns the width of displaying `n` with a separator inserted every `group` digits,
+/// e.g., `1,234,567` for `group = 3` and `sep_len = 1`.
+///
+/// Same with [`display_width`] if `group` is 0.
+pub fn display_width_grouped(n: usize, group: usize, sep_len: usize) -> usize {
+    let digits = display_width(n);
+    if group == 0 {
+        return digits;
+    }
+    digits + (digits - 1) / group * sep_len
+}
+
 /// Returns true if `dir` is a git repo, including git submodule.
 pub fn is_git_repo(dir: &Path) -> bool {
     dir.join(".git").exists()
@@ -86,6 +103,47 @@ pub fn char_indices_to_byte_indices(s: &str, char_indices: &[usize]) -> Vec<usiz
         .collect::<Vec<_>>()
 }
 
+/// Converts the sorted char indices of `s` to the byte ranges, the consecutive
+/// indices are collapsed into one range.
+pub fn char_indices_to_byte_ranges(s: &str, char_indices: &[usize]) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    let mut char_indices = char_indices.iter().peekable();
+
+    for (char_idx, (byte_idx, c)) in s.char_indices().enumerate() {
+        let Some(&&next_idx) = char_indices.peek() else {
+            break;
+        };
+        if next_idx != char_idx {
+            continue;
+        }
+        char_indices.next();
+
+        let end = byte_idx + c.len_utf8();
+        match ranges.last_mut() {
+            Some(last) if last.end == byte_idx => last.end = end,
+            _ => ranges.push(byte_idx..end),
+        }
+    }
+
+    ranges
+}
+
+/// Coalesces the sorted `indices` into the ranges of consecutive indices, the
+/// duplicate indices are merged, e.g., `[1, 2, 2, 3, 7]` => `[1..4, 7..8]`.
+pub fn coalesce_indices(indices: &[usize]) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+
+    for &idx in indices {
+        match ranges.last_mut() {
+            Some(last) if idx < last.end => {}
+            Some(last) if idx == last.end => last.end = idx + 1,
+            _ => ranges.push(idx..idx + 1),
+        }
+    }
+
+    ranges
+}
+
 /// Returns the char index of given byte index (0-based) in a line.
 pub fn char_index_for(line: &str, byte_idx: usize) -> Option<usize> {
     line.char_indices().enumerate().find_map(
@@ -99,8 +157,76 @@ pub fn char_index_for(line: &str, byte_idx: usize) -> Option<usize> {
     )
 }
 
+/// Converts the byte column (0-based) in `line` to the column in UTF-16 code units,
+/// which is used by the LSP-style consumers.
+///
+/// A column in the middle of a char is rounded up to the next char, a column beyond
+/// the end of `line` is clamped to the end.
+pub fn byte_col_to_utf16(line: &str, byte_col: usize) -> usize {
+    line.char_indices()
+        .take_while(|(b_idx, _c)| *b_idx < byte_col)
+        .map(|(_b_idx, c)| c.len_utf16())
+        .sum()
+}
+
 /// Returns the char at given byte index (0-based) in a line.
 pub fn char_at(line: &str, byte_idx: usize) -> Option<char> {
     line.char_indices()
         .find_map(|(b_idx, c)| if byte_idx == b_idx { Some(c) } else { None })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_byte_col_to_utf16() {
+        assert_eq!(byte_col_to_utf16("let foo = 1;", 4), 4);
+        // `é` takes 2 bytes but 1 code unit.
+        assert_eq!(byte_col_to_utf16("café = 1;", 6), 5);
+        // `🦀` takes 4 bytes and 2 code units.
+        let line = "let 🦀 = foo;";
+        assert_eq!(line.find("foo"), Some(11));
+        assert_eq!(byte_col_to_utf16(line, 11), 9);
+        assert_eq!(byte_col_to_utf16(line, 100), line.encode_utf16().count());
+    }
+
+    #[test]
+    fn test_coalesce_indices() {
+        assert!(coalesce_indices(&[]).is_empty());
+        assert_eq!(coalesce_indices(&[1, 2, 2, 3, 7]), vec![1..4, 7..8]);
+        assert_eq!(coalesce_indices(&[0, 2, 4]), vec![0..1, 2..3, 4..5]);
+        assert_eq!(coalesce_indices(&[5, 6, 7, 8]), vec![5..9]);
+    }
+
+    #[test]
+    fn test_char_indices_to_byte_ranges() {
+        assert_eq!(
+            char_indices_to_byte_ranges("let foo = 1;", &[0, 1, 2, 4, 5, 6]),
+            vec![0..3, 4..7]
+        );
+        assert_eq!(char_indices_to_byte_ranges("é中b", &[0, 1, 2]), vec![0..6]);
+        assert_eq!(char_indices_to_byte_ranges("é中b", &[1, 9]), vec![2..5]);
+        assert!(char_indices_to_byte_ranges("abc", &[]).is_empty());
+    }
+
+    #[test]
+    fn test_display_width_grouped() {
+        for (n, expected) in [
+            (0, "0"),
+            (7, "7"),
+            (999, "999"),
+            (1000, "1,000"),
+            (123_456, "123,456"),
+            (1_000_000, "1,000,000"),
+        ] {
+            assert_eq!(display_width(n), n.to_string().len());
+            assert_eq!(display_width_grouped(n, 3, 1), expected.len());
+            assert_eq!(display_width_grouped(n, 0, 1), display_width(n));
+        }
+
+        // Multi-byte separator, e.g., thin space.
+        assert_eq!(display_width_grouped(1_000_000, 3, 3), 13);
+        assert_eq!(display_width_grouped(10_000, 4, 1), 6);
+    }
+}
@@ -0,0 +1,99 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+/// Upper bound of the capacity allocated upfront, the heap grows as needed beyond it,
+/// so that a huge `n`, e.g., `usize::MAX` for no limit, does not allocate eagerly.
+const MAX_INITIAL_CAPACITY: usize = 1024;
+
+/// Collector keeping only the greatest `n` items pushed so far.
+///
+/// Backed by a min-heap of size `n`, each insertion is `O(log n)`, which is cheaper
+/// than sorting all the items and then truncating them for a large number of items.
+#[derive(Debug, Clone)]
+pub struct TopN<T> {
+    n: usize,
+    heap: BinaryHeap<Reverse<T>>,
+}
+
+impl<T: Ord> TopN<T> {
+    /// Creates a new instance of [`TopN`] retaining at most `n` items.
+    pub fn new(n: usize) -> Self {
+        Self {
+            n,
+            heap: BinaryHeap::with_capacity(n.min(MAX_INITIAL_CAPACITY)),
+        }
+    }
+
+    /// Adds an item, the least one is dropped if there are more than `n` items.
+    pub fn push(&mut self, item: T) {
+        if self.heap.len() < self.n {
+            self.heap.push(Reverse(item));
+        } else if self.heap.peek().is_some_and(|Reverse(least)| item > *least) {
+            self.heap.pop();
+            self.heap.push(Reverse(item));
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+
+    /// Returns the retained items sorted in descending order.
+    pub fn into_sorted_vec(self) -> Vec<T> {
+        self.heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|Reverse(item)| item)
+            .collect()
+    }
+}
+
+impl<T: Ord> Extend<T> for TopN<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.push(item);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_top_n() {
+        // Linear congruential generator, good enough for the random input here.
+        let mut seed = 42u64;
+        let mut random = move || {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (seed >> 33) % 1000
+        };
+
+        for n in [0, 1, 7, 100, 2000] {
+            let input = (0..1000).map(|_| random()).collect::<Vec<_>>();
+
+            let mut top_n = TopN::new(n);
+            top_n.extend(input.iter().copied());
+            assert_eq!(top_n.len(), n.min(input.len()));
+
+            let mut expected = input;
+            expected.sort_unstable_by(|a, b| b.cmp(a));
+            expected.truncate(n);
+
+            assert_eq!(top_n.into_sorted_vec(), expected);
+        }
+    }
+
+    #[test]
+    fn test_top_n_unbounded() {
+        let mut top_n = TopN::new(usize::MAX);
+        top_n.extend([3, 1, 2]);
+        assert_eq!(top_n.into_sorted_vec(), vec![3, 2, 1]);
+    }
+}